@@ -0,0 +1,191 @@
+use crate::{
+    auth::Actor,
+    logging::{RequestId, REQUEST_ID_HEADER},
+    repository::AuditRepo,
+    state::AppState,
+};
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, Bson, DateTime, Document},
+    options::IndexOptions,
+    Database, IndexModel,
+};
+use std::{
+    future::{ready, Ready},
+    time::Duration,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+// Fields kept out of the audit log and personal data exports
+pub const REDACTED_FIELDS: [&str; 1] = ["password_hash"];
+
+// The actor of changes made by background jobs and command line tools
+pub const SYSTEM_ACTOR: &str = "system";
+
+// Create the TTL index that expires audit entries once they pass their retention
+pub async fn ensure_audit_indexes(db: &Database) -> mongodb::error::Result<()> {
+    let collection = db.collection::<Document>("audit_log");
+    collection
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(IndexOptions::builder().expire_after(Duration::ZERO).build())
+                .build(),
+        )
+        .await?;
+    collection
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "created_at": -1 })
+                .build(),
+        )
+        .await?;
+    Ok(())
+}
+
+// One mutation to record: what happened to which document, and how it changed
+pub struct AuditEvent {
+    action: String,
+    target_type: String,
+    target_id: String,
+    actor_id: Option<String>,
+    before: Option<Document>,
+    after: Option<Document>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &str, target_type: &str, target_id: &str) -> AuditEvent {
+        AuditEvent {
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id: target_id.to_string(),
+            actor_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    // The user the handler resolved as acting for the request
+    pub fn by(mut self, actor_id: &str) -> AuditEvent {
+        self.actor_id = Some(actor_id.to_string());
+        self
+    }
+
+    pub fn before(mut self, before: &Document) -> AuditEvent {
+        self.before = Some(before.clone());
+        self
+    }
+
+    pub fn after(mut self, after: &Document) -> AuditEvent {
+        self.after = Some(after.clone());
+        self
+    }
+}
+
+// Field-by-field differences between two versions of a document
+fn diff(before: Option<&Document>, after: Option<&Document>) -> Document {
+    let empty = Document::new();
+    let before = before.unwrap_or(&empty);
+    let after = after.unwrap_or(&empty);
+
+    let mut changes = Document::new();
+    let keys = before.keys().chain(
+        after
+            .keys()
+            .filter(|key| !before.contains_key(key.as_str())),
+    );
+    for key in keys {
+        if REDACTED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let old = before.get(key).cloned().unwrap_or(Bson::Null);
+        let new = after.get(key).cloned().unwrap_or(Bson::Null);
+        if old != new {
+            changes.insert(key, doc! { "before": old, "after": new });
+        }
+    }
+    changes
+}
+
+// Who made a change and how to correlate it. For requests the actor is the caller the
+// role guards authorized, never an unverified header.
+#[derive(Clone, Debug)]
+pub struct AuditContext {
+    pub actor_id: Option<String>,
+    pub request_id: String,
+    pub retention: Duration,
+}
+
+impl FromRequest for AuditContext {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let retention = req
+            .app_data::<web::Data<AppState>>()
+            .map(|state| state.audit_retention)
+            .unwrap_or(Duration::ZERO);
+
+        ready(Ok(AuditContext {
+            actor_id: req
+                .extensions()
+                .get::<Actor>()
+                .map(|actor| actor.user_id.clone()),
+            // Set by the request ID middleware; the header covers apps without it
+            request_id: req
+                .extensions()
+                .get::<RequestId>()
+                .map(|request_id| request_id.0.clone())
+                .or_else(|| header(REQUEST_ID_HEADER))
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            retention,
+        }))
+    }
+}
+
+impl AuditContext {
+    // Context for one run of a background job or command, whose entries share a request ID
+    pub fn system(retention: Duration) -> AuditContext {
+        AuditContext {
+            actor_id: Some(SYSTEM_ACTOR.to_string()),
+            request_id: Uuid::new_v4().to_string(),
+            retention,
+        }
+    }
+
+    // Append an entry to the audit log. Like notifications, auditing is best effort
+    // and never fails the write it describes.
+    pub async fn record(&self, log: &dyn AuditRepo, event: AuditEvent) {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(self.retention).unwrap_or_default();
+        let entry = doc! {
+            "_id": Uuid::new_v4().to_string(),
+            "actor_id": self.actor_id.as_ref().or(event.actor_id.as_ref()),
+            "action": &event.action,
+            "target_type": &event.target_type,
+            "target_id": &event.target_id,
+            "request_id": &self.request_id,
+            "changes": diff(event.before.as_ref(), event.after.as_ref()),
+            "created_at": now.to_rfc3339(),
+            "expires_at": DateTime::from_millis(expires_at.timestamp_millis()),
+        };
+
+        match log.insert(entry).await {
+            Ok(_) => info!(
+                "Audited {} on {} {} (request {})",
+                event.action, event.target_type, event.target_id, self.request_id
+            ),
+            Err(e) => error!("Error writing audit entry for {}: {}", event.action, e),
+        }
+    }
+}
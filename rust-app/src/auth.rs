@@ -0,0 +1,167 @@
+use crate::{errors::AppError, models::UserRole, state::AppState};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderMap, AUTHORIZATION},
+    middleware::Next,
+    web, Error, HttpMessage, HttpRequest,
+};
+use hmac::{Hmac, Mac};
+use mongodb::bson::Document;
+use sha2::Sha256;
+use tracing::{info, warn};
+
+// Header carrying the ID of the user making the request
+pub const USER_ID_HEADER: &str = "X-User-Id";
+
+// A caller proves they are the user in X-User-Id with `Authorization: Bearer <token>`,
+// where the token is the hex HMAC-SHA256 of the user ID under the server's auth secret
+type TokenMac = Hmac<Sha256>;
+
+fn token_mac(secret: &str, user_id: &str) -> TokenMac {
+    let mut mac = TokenMac::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(user_id.as_bytes());
+    mac
+}
+
+// The token that authenticates `user_id`. It does not expire; changing the secret revokes
+// every token at once.
+pub fn issue_token(secret: &str, user_id: &str) -> String {
+    hex::encode(token_mac(secret, user_id).finalize().into_bytes())
+}
+
+// Compares in constant time so the token cannot be guessed byte by byte
+fn verify_token(secret: &str, user_id: &str, token: &str) -> bool {
+    hex::decode(token).is_ok_and(|token| token_mac(secret, user_id).verify_slice(&token).is_ok())
+}
+
+// The authorized caller, stored in the request extensions by the role guards
+#[derive(Clone, Debug)]
+pub struct Actor {
+    pub user_id: String,
+    pub role: UserRole,
+}
+
+// Promote the account with the given email to admin so a fresh deployment has one
+pub async fn promote_bootstrap_admin(state: &AppState, email: &str) -> Result<(), AppError> {
+    let user = match state.users.find_by_email(email).await? {
+        Some(user) => user,
+        None => {
+            warn!("Bootstrap admin {} does not exist yet", email);
+            return Ok(());
+        }
+    };
+
+    let user_id = user.get_str("_id").unwrap_or_default();
+    state
+        .users
+        .set_role(user_id, UserRole::Admin.as_str())
+        .await?;
+    info!("User {} has the admin role", email);
+    Ok(())
+}
+
+// Authenticate the user named by the request headers and look them up along with their
+// account flags
+async fn load_caller(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(String, Document), AppError> {
+    let user_id = headers
+        .get(USER_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| AppError::Unauthorized(format!("Missing {} header", USER_ID_HEADER)))?
+        .to_string();
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or_else(|| {
+            AppError::Unauthorized(format!("Missing bearer token for user {}", user_id))
+        })?;
+    if !verify_token(&state.auth_secret, &user_id, token) {
+        return Err(AppError::Unauthorized(format!(
+            "Invalid token for user {}",
+            user_id
+        )));
+    }
+
+    let user = state
+        .users
+        .find_by_id(&user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized(format!("Unknown user {}", user_id)))?;
+
+    Ok((user_id, user))
+}
+
+// Identify the caller of a route outside the guarded scopes. Soft-deleted users are
+// still identified so they can restore their own account.
+pub async fn current_actor(req: &HttpRequest, state: &AppState) -> Result<Actor, AppError> {
+    let (user_id, user) = load_caller(state, req.headers()).await?;
+    Ok(Actor {
+        user_id,
+        role: UserRole::parse(user.get_str("role").unwrap_or_default()),
+    })
+}
+
+// The caller of a route that anonymous visitors may also use, or None without the
+// X-User-Id header. A header with a missing or invalid token is still rejected.
+pub async fn optional_actor(
+    req: &HttpRequest,
+    state: &AppState,
+) -> Result<Option<Actor>, AppError> {
+    if !req.headers().contains_key(USER_ID_HEADER) {
+        return Ok(None);
+    }
+    current_actor(req, state).await.map(Some)
+}
+
+// Resolve the caller from the request and check their role against `allowed`
+async fn authorize(req: &ServiceRequest, allowed: fn(&UserRole) -> bool) -> Result<(), AppError> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .expect("AppState is registered on the app");
+    let (user_id, user) = load_caller(state, req.headers()).await?;
+
+    let role = UserRole::parse(user.get_str("role").unwrap_or_default());
+    let active = !user.get_bool("suspended").unwrap_or(false) && !user.contains_key("deleted_at");
+    if !active || !allowed(&role) {
+        info!(
+            "User {} with role {} denied access to {}",
+            user_id,
+            role.as_str(),
+            req.path()
+        );
+        return Err(AppError::Forbidden(format!(
+            "User {} is not allowed to access {}",
+            user_id,
+            req.path()
+        )));
+    }
+
+    req.extensions_mut().insert(Actor { user_id, role });
+    Ok(())
+}
+
+// Guard for scopes open to moderators and admins
+pub async fn require_moderator(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(&req, UserRole::can_moderate).await?;
+    next.call(req).await
+}
+
+// Guard for scopes open to admins only
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    authorize(&req, UserRole::is_admin).await?;
+    next.call(req).await
+}
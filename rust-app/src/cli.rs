@@ -0,0 +1,207 @@
+use crate::{
+    audit::{AuditContext, AuditEvent},
+    models::SeedOptions,
+    repository::MongoStore,
+    seeding::populate,
+    transfer::{export_collection, import_collection, ConflictMode, DATA_COLLECTIONS},
+    validation::validate_seed_options,
+};
+use clap::{Args, Parser, Subcommand};
+use mongodb::{bson::doc, Database};
+use std::{io, path::PathBuf, time::Duration};
+use tracing::{info, warn};
+
+/// Social media API backed by a MongoDB replica set. Runs the HTTP server unless a
+/// subcommand is given.
+#[derive(Parser, Debug)]
+#[command(name = "DDBP")]
+pub struct Cli {
+    /// TOML config file [default: $CONFIG_FILE, or ./config.toml when it exists]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export collections to JSON Lines files in MongoDB Extended JSON
+    Export(ExportArgs),
+    /// Import collections from JSON Lines files written by `export`
+    Import(ImportArgs),
+    /// Replace all data with a generated dataset; the same seed gives the same data
+    Seed(SeedArgs),
+    /// Inspect the configuration the server would start with
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective configuration as TOML, with secrets redacted
+    Show,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Directory to write one <collection>.jsonl file per collection into
+    #[arg(long, default_value = "export")]
+    pub dir: PathBuf,
+    /// Collections to export, comma separated [default: every data collection]
+    #[arg(long, value_delimiter = ',')]
+    pub collections: Vec<String>,
+    /// Documents fetched from the server per round trip
+    #[arg(long, default_value_t = 1000)]
+    pub batch_size: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Directory holding the <collection>.jsonl files to import
+    #[arg(long, default_value = "export")]
+    pub dir: PathBuf,
+    /// Collections to import, comma separated [default: every file found for a data collection]
+    #[arg(long, value_delimiter = ',')]
+    pub collections: Vec<String>,
+    /// Documents written per batch
+    #[arg(long, default_value_t = 1000)]
+    pub batch_size: usize,
+    /// What to do with documents whose _id already exists
+    #[arg(long, value_enum, default_value_t = ConflictMode::Fail)]
+    pub on_conflict: ConflictMode,
+}
+
+#[derive(Args, Debug)]
+pub struct SeedArgs {
+    #[arg(long, default_value_t = 150)]
+    pub users: usize,
+    #[arg(long, default_value_t = 300)]
+    pub posts: usize,
+    #[arg(long, default_value_t = 500)]
+    pub comments: usize,
+    #[arg(long, default_value_t = 1000)]
+    pub likes: usize,
+    #[arg(long, default_value_t = 400)]
+    pub follows: usize,
+    /// Seed for the random generator [default: random, printed when done]
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// RFC 3339 time of the newest generated activity [default: midnight UTC today]
+    #[arg(long)]
+    pub anchor: Option<String>,
+    /// Documents per insert_many call
+    #[arg(long, default_value_t = 1000)]
+    pub batch_size: usize,
+}
+
+impl From<SeedArgs> for SeedOptions {
+    fn from(args: SeedArgs) -> Self {
+        SeedOptions {
+            users: args.users,
+            posts: args.posts,
+            comments: args.comments,
+            likes: args.likes,
+            follows: args.follows,
+            seed: args.seed,
+            anchor: args.anchor,
+            batch_size: args.batch_size,
+        }
+    }
+}
+
+// The requested collections, or every data collection when none were named
+fn selected_collections(requested: &[String]) -> io::Result<Vec<String>> {
+    if requested.is_empty() {
+        return Ok(DATA_COLLECTIONS.iter().map(|c| c.to_string()).collect());
+    }
+    for collection in requested {
+        if !DATA_COLLECTIONS.contains(&collection.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown collection {}", collection),
+            ));
+        }
+    }
+    Ok(requested.to_vec())
+}
+
+// Imports are audited as the system, keeping entries for `audit_retention`
+pub async fn run(command: Command, db: Database, audit_retention: Duration) -> io::Result<()> {
+    let store = MongoStore::new(db);
+    match command {
+        Command::Export(args) => {
+            std::fs::create_dir_all(&args.dir)?;
+            for collection in selected_collections(&args.collections)? {
+                let path = args.dir.join(format!("{}.jsonl", collection));
+                let count = export_collection(&store, &collection, &path, args.batch_size).await?;
+                info!(
+                    "Exported {} documents from {} to {}",
+                    count,
+                    collection,
+                    path.display()
+                );
+            }
+        }
+        Command::Import(args) => {
+            let audit = AuditContext::system(audit_retention);
+            let explicit = !args.collections.is_empty();
+            for collection in selected_collections(&args.collections)? {
+                let path = args.dir.join(format!("{}.jsonl", collection));
+                if !explicit && !path.exists() {
+                    warn!("Skipping {}, {} does not exist", collection, path.display());
+                    continue;
+                }
+                let summary = import_collection(
+                    &store,
+                    &collection,
+                    &path,
+                    args.batch_size.max(1),
+                    args.on_conflict,
+                )
+                .await?;
+                info!(
+                    "Imported {} from {}: {} inserted, {} replaced, {} skipped",
+                    collection,
+                    path.display(),
+                    summary.inserted,
+                    summary.replaced,
+                    summary.skipped
+                );
+                audit
+                    .record(
+                        &store,
+                        AuditEvent::new("import_collection", "collection", &collection).after(
+                            &doc! {
+                                "file": path.display().to_string(),
+                                "inserted": summary.inserted as i64,
+                                "replaced": summary.replaced as i64,
+                                "skipped": summary.skipped as i64,
+                            },
+                        ),
+                    )
+                    .await;
+            }
+        }
+        Command::Seed(args) => {
+            let options = SeedOptions::from(args);
+            validate_seed_options(&options)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+            let summary = populate(&store, &options, None)
+                .await
+                .map_err(io::Error::other)?;
+            info!(
+                "Seeded {} users, {} posts, {} comments, {} likes and {} follows with seed {} and anchor {}",
+                summary.users,
+                summary.posts,
+                summary.comments,
+                summary.likes,
+                summary.follows,
+                summary.seed,
+                summary.anchor
+            );
+        }
+        // Handled before connecting to the database
+        Command::Config(ConfigCommand::Show) => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use mongodb::{
+    bson::{doc, Bson, Document},
+    options::{ReadPreference, SelectionCriteria},
+    Client,
+};
+use serde::Serialize;
+
+// Summary of `replSetGetStatus` as seen by the member that answered it
+#[derive(Serialize, Debug)]
+pub struct ClusterStatus {
+    pub set: String,
+    // Election term, bumped on every successful election
+    pub term: Option<i64>,
+    pub primary: Option<String>,
+    // When the answering member produced the report
+    pub checked_at: Option<String>,
+    pub members: Vec<MemberStatus>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberStatus {
+    pub name: String,
+    // PRIMARY, SECONDARY, STARTUP2, RECOVERING, (not reachable/healthy), ...
+    pub state: String,
+    pub healthy: bool,
+    // The member that answered the status command
+    pub is_self: bool,
+    pub uptime_secs: Option<i64>,
+    pub last_applied: Option<String>,
+    // How far the member's last applied write is behind the primary's; unknown without a primary
+    pub lag_secs: Option<i64>,
+    // Last heartbeat the answering member received from this one, never set for itself
+    pub last_heartbeat: Option<String>,
+    pub ping_ms: Option<i64>,
+    pub sync_source: Option<String>,
+}
+
+// The server reports numbers as int32, int64 or double depending on the field and version
+fn number(document: &Document, key: &str) -> Option<i64> {
+    match document.get(key)? {
+        Bson::Int32(value) => Some(i64::from(*value)),
+        Bson::Int64(value) => Some(*value),
+        Bson::Double(value) => Some(*value as i64),
+        _ => None,
+    }
+}
+
+fn millis(document: &Document, key: &str) -> Option<i64> {
+    document
+        .get_datetime(key)
+        .ok()
+        .map(|date| date.timestamp_millis())
+}
+
+fn timestamp(document: &Document, key: &str) -> Option<String> {
+    document
+        .get_datetime(key)
+        .ok()
+        .and_then(|date| date.try_to_rfc3339_string().ok())
+}
+
+fn non_empty(document: &Document, key: &str) -> Option<String> {
+    document
+        .get_str(key)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+impl ClusterStatus {
+    pub fn from_document(status: &Document) -> Self {
+        let members: Vec<&Document> = status
+            .get_array("members")
+            .map(|members| members.iter().filter_map(Bson::as_document).collect())
+            .unwrap_or_default();
+        let primary = members
+            .iter()
+            .find(|member| member.get_str("stateStr") == Ok("PRIMARY"));
+        let primary_applied = primary.and_then(|primary| millis(primary, "optimeDate"));
+
+        ClusterStatus {
+            set: status.get_str("set").unwrap_or_default().to_string(),
+            term: number(status, "term"),
+            primary: primary.and_then(|primary| non_empty(primary, "name")),
+            checked_at: timestamp(status, "date"),
+            members: members
+                .iter()
+                .map(|member| MemberStatus {
+                    name: member.get_str("name").unwrap_or_default().to_string(),
+                    state: member.get_str("stateStr").unwrap_or("UNKNOWN").to_string(),
+                    healthy: number(member, "health") == Some(1),
+                    is_self: member.get_bool("self").unwrap_or(false),
+                    uptime_secs: number(member, "uptime"),
+                    last_applied: timestamp(member, "optimeDate"),
+                    lag_secs: primary_applied
+                        .zip(millis(member, "optimeDate"))
+                        .map(|(primary, applied)| ((primary - applied) / 1000).max(0)),
+                    last_heartbeat: timestamp(member, "lastHeartbeat"),
+                    ping_ms: number(member, "pingMs"),
+                    sync_source: non_empty(member, "syncSourceHost"),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Ask the replica set for its status. Any member can answer, so this still works while
+// there is no primary, which is when the report matters most.
+pub async fn fetch_cluster_status(client: &Client) -> mongodb::error::Result<ClusterStatus> {
+    let status = client
+        .database("admin")
+        .run_command(doc! { "replSetGetStatus": 1 })
+        .selection_criteria(SelectionCriteria::ReadPreference(
+            ReadPreference::PrimaryPreferred { options: None },
+        ))
+        .await?;
+    Ok(ClusterStatus::from_document(&status))
+}
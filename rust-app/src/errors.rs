@@ -1,15 +1,20 @@
-use actix_web::{error::ResponseError, HttpResponse};
-// use serde::Serialize;
+use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use mongodb::error::{ErrorKind, WriteFailure, TRANSIENT_TRANSACTION_ERROR};
+use serde_json::json;
 use std::fmt;
 use tracing::{error, info};
 
-use crate::models::Response;
+use crate::{metrics::metrics, models::Response};
 
 #[derive(Debug)]
 pub enum AppError {
     MongoError(mongodb::error::Error),
     NotFound(String),
     InvalidInput(String),
+    Conflict(String),
+    Forbidden(String),
+    Unauthorized(String),
+    ValidationError { field: String, message: String },
 }
 
 impl fmt::Display for AppError {
@@ -18,12 +23,48 @@ impl fmt::Display for AppError {
             AppError::MongoError(e) => write!(f, "Database error: {}", e),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::ValidationError { field, message } => {
+                write!(f, "Invalid {}: {}", field, message)
+            }
+        }
+    }
+}
+
+impl AppError {
+    // Name of the variant, used as the label of the error counter
+    pub fn variant(&self) -> &'static str {
+        match self {
+            AppError::MongoError(_) => "MongoError",
+            AppError::NotFound(_) => "NotFound",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::Conflict(_) => "Conflict",
+            AppError::Forbidden(_) => "Forbidden",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::ValidationError { .. } => "ValidationError",
         }
     }
 }
 
 impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::MongoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidInput(_) | AppError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        }
+    }
+
     fn error_response(&self) -> HttpResponse {
+        metrics()
+            .app_errors
+            .with_label_values(&[self.variant()])
+            .inc();
         match self {
             AppError::MongoError(e) => {
                 error!("Database error: {}", e);
@@ -49,6 +90,38 @@ impl ResponseError for AppError {
                     data: None,
                 })
             }
+            AppError::Conflict(msg) => {
+                info!("Conflict: {}", msg);
+                HttpResponse::Conflict().json(Response::<()> {
+                    status: "error".to_string(),
+                    message: msg.clone(),
+                    data: None,
+                })
+            }
+            AppError::Forbidden(msg) => {
+                info!("Forbidden: {}", msg);
+                HttpResponse::Forbidden().json(Response::<()> {
+                    status: "error".to_string(),
+                    message: msg.clone(),
+                    data: None,
+                })
+            }
+            AppError::Unauthorized(msg) => {
+                info!("Unauthorized: {}", msg);
+                HttpResponse::Unauthorized().json(Response::<()> {
+                    status: "error".to_string(),
+                    message: msg.clone(),
+                    data: None,
+                })
+            }
+            AppError::ValidationError { field, message } => {
+                info!("Validation failed on {}: {}", field, message);
+                HttpResponse::BadRequest().json(Response {
+                    status: "error".to_string(),
+                    message: format!("{}: {}", field, message),
+                    data: Some(json!({ "field": field })),
+                })
+            }
         }
     }
 }
@@ -58,3 +131,20 @@ impl From<mongodb::error::Error> for AppError {
         AppError::MongoError(error)
     }
 }
+
+// Returns true when a transaction failed on a conflict that a retry may get past
+pub fn is_transient_transaction_error(error: &mongodb::error::Error) -> bool {
+    error.contains_label(TRANSIENT_TRANSACTION_ERROR)
+}
+
+// Returns true when a write failed because of a unique index or duplicate _id
+pub fn is_duplicate_key_error(error: &mongodb::error::Error) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) => write_error.code == 11000,
+        ErrorKind::InsertMany(insert_error) => insert_error
+            .write_errors
+            .as_ref()
+            .is_some_and(|errors| errors.iter().any(|e| e.code == 11000)),
+        _ => false,
+    }
+}
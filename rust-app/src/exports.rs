@@ -0,0 +1,201 @@
+use crate::{
+    audit::REDACTED_FIELDS, lifecycle::BackgroundJobs, models::JobStatus, repository::ExportRepo,
+};
+use chrono::Utc;
+use futures_util::{io::AsyncReadExt, io::AsyncWriteExt, StreamExt};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::Result,
+    options::GridFsBucketOptions,
+    Database,
+};
+use std::{
+    io::{self, Cursor, Write},
+    sync::Arc,
+};
+use tracing::{error, info};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+// GridFS bucket holding finished archives, keyed by export job ID
+const EXPORT_BUCKET: &str = "exports";
+
+// The first file in the archive, describing the rest
+const README_FILE: &str = "README.txt";
+
+// Files in the archive and the query that selects the user's documents for each
+fn export_sections(user_id: &str) -> Vec<(&'static str, &'static str, Document)> {
+    vec![
+        ("profile.json", "users", doc! { "_id": user_id }),
+        ("posts.json", "posts", doc! { "user_id": user_id }),
+        ("comments.json", "comments", doc! { "user_id": user_id }),
+        ("likes.json", "likes", doc! { "user_id": user_id }),
+        ("reposts.json", "reposts", doc! { "user_id": user_id }),
+        ("poll_votes.json", "poll_votes", doc! { "user_id": user_id }),
+        ("following.json", "follows", doc! { "follower_id": user_id }),
+        (
+            "followers.json",
+            "follows",
+            doc! { "following_id": user_id },
+        ),
+        ("bookmarks.json", "bookmarks", doc! { "user_id": user_id }),
+        ("blocks.json", "blocks", doc! { "blocker_id": user_id }),
+        ("mutes.json", "mutes", doc! { "muter_id": user_id }),
+        (
+            "notifications.json",
+            "notifications",
+            doc! { "user_id": user_id },
+        ),
+        ("reports.json", "reports", doc! { "reporter_id": user_id }),
+    ]
+}
+
+// Opening file of the archive, listing the other files
+fn archive_readme(user_id: &str) -> String {
+    let mut readme = format!(
+        "Personal data export for user {}\n\n\
+         Each file holds the user's documents of one kind as a JSON array:\n\n",
+        user_id
+    );
+    for (file_name, _, _) in export_sections(user_id) {
+        readme.push_str(&format!("- {}\n", file_name));
+    }
+    readme.push_str(
+        "\nThere is no direct messaging feature, so the export holds no private messages.\n",
+    );
+    readme
+}
+
+fn bucket(db: &Database) -> mongodb::gridfs::GridFsBucket {
+    db.gridfs_bucket(
+        GridFsBucketOptions::builder()
+            .bucket_name(EXPORT_BUCKET.to_string())
+            .build(),
+    )
+}
+
+// Zip a README and one pretty-printed JSON file per section, leaving out the redacted
+// fields
+async fn build_archive(exports: &dyn ExportRepo, user_id: &str) -> Result<Vec<u8>> {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    archive
+        .start_file(README_FILE, SimpleFileOptions::default())
+        .map_err(io::Error::from)?;
+    archive.write_all(archive_readme(user_id).as_bytes())?;
+    for (file_name, collection, filter) in export_sections(user_id) {
+        let mut documents = exports.collect(collection, filter).await?;
+        for document in &mut documents {
+            for field in REDACTED_FIELDS {
+                document.remove(field);
+            }
+        }
+        let json = serde_json::to_vec_pretty(&documents).map_err(io::Error::from)?;
+        archive
+            .start_file(file_name, SimpleFileOptions::default())
+            .map_err(io::Error::from)?;
+        archive.write_all(&json)?;
+    }
+    Ok(archive.finish().map_err(io::Error::from)?.into_inner())
+}
+
+// Store a finished archive in GridFS under the export job ID
+pub async fn store_archive(
+    db: &Database,
+    export_id: &str,
+    user_id: &str,
+    archive: &[u8],
+) -> Result<()> {
+    let mut upload = bucket(db)
+        .open_upload_stream(format!("export-{}.zip", user_id))
+        .id(Bson::String(export_id.to_string()))
+        .await?;
+    upload.write_all(archive).await?;
+    upload.close().await?;
+    Ok(())
+}
+
+// Build and store the archive for an export job, then mark the job completed
+async fn run_export(exports: &dyn ExportRepo, export_id: &str, user_id: &str) -> Result<()> {
+    exports
+        .update(
+            export_id,
+            doc! {
+                "status": JobStatus::Running.as_str(),
+                "started_at": Utc::now().to_rfc3339(),
+            },
+        )
+        .await?;
+
+    let archive = build_archive(exports, user_id).await?;
+    exports.store_archive(export_id, user_id, &archive).await?;
+
+    exports
+        .update(
+            export_id,
+            doc! {
+                "status": JobStatus::Completed.as_str(),
+                "size_bytes": archive.len() as i64,
+                "completed_at": Utc::now().to_rfc3339(),
+            },
+        )
+        .await
+}
+
+// Run an export job in the background, recording a failure on the job so clients
+// polling its status see it
+pub fn spawn_export(
+    jobs: &BackgroundJobs,
+    exports: Arc<dyn ExportRepo>,
+    export_id: String,
+    user_id: String,
+) {
+    jobs.spawn(async move {
+        match run_export(exports.as_ref(), &export_id, &user_id).await {
+            Ok(()) => info!("Export {} for user {} completed", export_id, user_id),
+            Err(e) => {
+                error!("Export {} for user {} failed: {}", export_id, user_id, e);
+                let result = exports
+                    .update(
+                        &export_id,
+                        doc! {
+                            "status": JobStatus::Failed.as_str(),
+                            "error": e.to_string(),
+                            "completed_at": Utc::now().to_rfc3339(),
+                        },
+                    )
+                    .await;
+                if let Err(e) = result {
+                    error!("Error marking export {} as failed: {}", export_id, e);
+                }
+            }
+        }
+    });
+}
+
+// Read a finished archive back out of GridFS
+pub async fn load_archive(db: &Database, export_id: &str) -> Result<Vec<u8>> {
+    let mut download = bucket(db)
+        .open_download_stream(Bson::String(export_id.to_string()))
+        .await?;
+    let mut archive = Vec::new();
+    download.read_to_end(&mut archive).await?;
+    Ok(archive)
+}
+
+// Remove export jobs matching `filter` together with their archives
+pub async fn delete_exports(db: &Database, filter: Document) -> Result<()> {
+    let exports = db.collection::<Document>("exports");
+    let bucket = bucket(db);
+    let mut cursor = exports
+        .find(filter)
+        .projection(doc! { "_id": 1, "status": 1 })
+        .await?;
+    while let Some(result) = cursor.next().await {
+        let export = result?;
+        let export_id = export.get_str("_id").unwrap_or_default();
+        if export.get_str("status") == Ok(JobStatus::Completed.as_str()) {
+            bucket.delete(Bson::String(export_id.to_string())).await?;
+        }
+        exports.delete_one(doc! { "_id": export_id }).await?;
+    }
+    Ok(())
+}
@@ -1,1180 +1,3659 @@
-use crate::{errors::AppError, models::*, state::AppState};
-use actix_web::{web, HttpResponse, Responder, Result};
-use chrono::Utc;
-use futures_util::StreamExt;
-use mongodb::bson::{doc, Document};
-use rand::seq::SliceRandom;
-use rand::Rng;
-use tracing::{error, info};
-use uuid::Uuid;
-
-// Health Check Handler
-pub async fn health_check_handler(state: web::Data<AppState>) -> Result<impl Responder, AppError> {
-    info!("Health check requested");
-
-    // Longer timeout for health checks (15 seconds instead of 5)
-    let timeout_duration = std::time::Duration::from_secs(15);
-
-    // Retry logic: try 3 times with a short delay between attempts
-    let max_retries = 3;
-    let mut last_error = None;
-
-    for attempt in 1..=max_retries {
-        info!("Health check attempt {}/{}", attempt, max_retries);
-
-        // Try to ping the database with the configured timeout
-        let timeout_future =
-            tokio::time::timeout(timeout_duration, state.db.run_command(doc! {"ping": 1})).await;
-
-        match timeout_future {
-            Ok(Ok(_)) => {
-                // Success! Database is reachable
-                info!("Health check successful on attempt {}", attempt);
-                return Ok(HttpResponse::Ok().json(Response::<()> {
-                    status: "success".to_string(),
-                    message: "Service is healthy".to_string(),
-                    data: None,
-                }));
-            }
-            Ok(Err(e)) => {
-                // Connection established but command failed
-                error!("Health check failed on attempt {}: {}", attempt, e);
-                last_error = Some(e.to_string());
-            }
-            Err(_) => {
-                // Timeout occurred
-                error!("Health check timed out on attempt {}", attempt);
-                last_error = Some("database connection timeout".to_string());
-            }
-        }
-
-        // If we reached the maximum number of retries, break out of the loop
-        if attempt == max_retries {
-            break;
-        }
-
-        // Wait briefly before the next retry
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-    }
-
-    // If we got here, all retries failed
-    let error_message = last_error.unwrap_or_else(|| "unknown database error".to_string());
-    error!(
-        "Health check failed after {} attempts: {}",
-        max_retries, error_message
-    );
-
-    Ok(HttpResponse::ServiceUnavailable().json(Response::<()> {
-        status: "error".to_string(),
-        message: format!("Service is unhealthy: {}", error_message),
-        data: None,
-    }))
-}
-
-// Create User Handler
-pub async fn create_user_handler(
-    user: web::Json<User>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!("Creating new user with username: {}", user.username);
-
-    if user.username.is_empty() {
-        return Err(AppError::InvalidInput(
-            "Username cannot be empty".to_string(),
-        ));
-    }
-
-    if user.email.is_empty() || !user.email.contains('@') {
-        return Err(AppError::InvalidInput("Invalid email format".to_string()));
-    }
-
-    if user.password_hash.is_empty() {
-        return Err(AppError::InvalidInput(
-            "Password hash cannot be empty".to_string(),
-        ));
-    }
-
-    let collection = state.db.collection::<Document>("users");
-
-    let existing_filter = doc! { "email": &user.email };
-    if let Ok(Some(_)) = collection.find_one(existing_filter).await {
-        return Err(AppError::InvalidInput(format!(
-            "User with email {} already exists",
-            user.email
-        )));
-    }
-
-    let user_id = Uuid::new_v4().to_string();
-    let user_doc = doc! {
-        "_id": &user_id,
-        "username": &user.username,
-        "email": &user.email,
-        "password_hash": &user.password_hash,
-        "created_at": Utc::now().to_rfc3339(),
-    };
-
-    match collection.insert_one(user_doc).await {
-        Ok(_) => {
-            info!("User created successfully with ID: {}", user_id);
-            Ok(HttpResponse::Created().json(Response {
-                status: "success".to_string(),
-                message: format!("User created successfully with ID: {}", user_id),
-                data: Some(user_id),
-            }))
-        }
-        Err(e) => {
-            error!("Error creating user: {}", e);
-            Err(AppError::from(e))
-        }
-    }
-}
-
-// Create Post Handler
-pub async fn create_post_handler(
-    post: web::Json<Post>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!("Creating new post for user: {}", post.user_id);
-
-    if post.user_id.is_empty() {
-        return Err(AppError::InvalidInput(
-            "User ID cannot be empty".to_string(),
-        ));
-    }
-
-    if post.content.is_empty() {
-        return Err(AppError::InvalidInput(
-            "Post content cannot be empty".to_string(),
-        ));
-    }
-
-    let users_collection = state.db.collection::<Document>("users");
-    let user_filter = doc! { "_id": &post.user_id };
-
-    if let Ok(None) = users_collection.find_one(user_filter).await {
-        return Err(AppError::NotFound(format!(
-            "User with ID {} not found",
-            post.user_id
-        )));
-    }
-
-    let collection = state.db.collection::<Document>("posts");
-    let post_id = Uuid::new_v4().to_string();
-    let post_doc = doc! {
-        "_id": &post_id,
-        "user_id": &post.user_id,
-        "content": &post.content,
-        "media_urls": &post.media_urls,
-        "post_type": match post.post_type {
-            PostType::Text => "Text",
-            PostType::Image => "Image",
-            PostType::Video => "Video",
-            PostType::Link => "Link",
-        },
-        "like_count": 0,
-        "created_at": Utc::now().to_rfc3339(),
-    };
-
-    match collection.insert_one(post_doc).await {
-        Ok(_) => {
-            info!("Post created successfully with ID: {}", post_id);
-            Ok(HttpResponse::Created().json(Response {
-                status: "success".to_string(),
-                message: format!("Post created successfully with ID: {}", post_id),
-                data: Some(post_id),
-            }))
-        }
-        Err(e) => {
-            error!("Error creating post: {}", e);
-            Err(AppError::from(e))
-        }
-    }
-}
-
-// Create Comment Handler
-pub async fn create_comment_handler(
-    comment: web::Json<Comment>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!("Creating new comment for post: {}", comment.post_id);
-
-    if comment.post_id.is_empty() || comment.user_id.is_empty() || comment.content.is_empty() {
-        return Err(AppError::InvalidInput(
-            "All fields are required".to_string(),
-        ));
-    }
-
-    let collection = state.db.collection::<Document>("comments");
-    let comment_doc = doc! {
-        "post_id": &comment.post_id,
-        "user_id": &comment.user_id,
-        "content": &comment.content,
-        "created_at": Utc::now().to_rfc3339(),
-    };
-
-    match collection.insert_one(comment_doc).await {
-        Ok(_) => Ok(HttpResponse::Created().json(Response::<()> {
-            status: "success".to_string(),
-            message: "Comment created successfully".to_string(),
-            data: None,
-        })),
-        Err(e) => Err(AppError::from(e)),
-    }
-}
-
-// Follow User Handler
-pub async fn follow_user_handler(
-    follow: web::Json<Follow>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!(
-        "User {} is following user {}",
-        follow.follower_id, follow.following_id
-    );
-
-    if follow.follower_id.is_empty() || follow.following_id.is_empty() {
-        return Err(AppError::InvalidInput(
-            "Follower and Following IDs are required".to_string(),
-        ));
-    }
-
-    let collection = state.db.collection::<Document>("follows");
-    let follow_doc = doc! {
-        "follower_id": &follow.follower_id,
-        "following_id": &follow.following_id,
-        "created_at": Utc::now().to_rfc3339(),
-    };
-
-    match collection.insert_one(follow_doc).await {
-        Ok(_) => Ok(HttpResponse::Created().json(Response::<()> {
-            status: "success".to_string(),
-            message: "Follow relationship created successfully".to_string(),
-            data: None,
-        })),
-        Err(e) => Err(AppError::from(e)),
-    }
-}
-
-// Helper function to generate random users with more complete profiles
-fn generate_random_users(count: usize) -> Vec<mongodb::bson::Document> {
-    let mut rng = rand::thread_rng();
-    let names_pool = vec![
-        "Alice", "Bob", "Charlie", "Daisy", "Eve", "Frank", "Grace", "Hank", "Ivy", "Jack",
-        "Karen", "Leo", "Mona", "Nina", "Oscar", "Paul", "Quinn", "Rita", "Steve", "Tina", "Uma",
-        "Victor", "Wendy", "Xander", "Yara", "Zane",
-    ];
-
-    let bio_templates = vec![
-        "Digital creator passionate about {}",
-        "Exploring the world of {} one post at a time",
-        "Professional {} enthusiast",
-        "{} advocate | Content Creator",
-        "Living life through the lens of {}",
-    ];
-
-    let interests = vec![
-        "technology",
-        "art",
-        "music",
-        "photography",
-        "travel",
-        "food",
-        "fitness",
-        "gaming",
-        "books",
-        "nature",
-    ];
-
-    let mut users = Vec::new();
-    let current_time = chrono::Utc::now();
-
-    for _ in 0..count {
-        let name = names_pool.choose(&mut rng).unwrap();
-        let number: u32 = rng.gen_range(1..1000);
-        let username = format!("{}_{}", name, number);
-        let email = format!("{}{}@example.com", name.to_lowercase(), number);
-
-        let bio_template = bio_templates.choose(&mut rng).unwrap();
-        let interest = interests.choose(&mut rng).unwrap();
-        let bio = bio_template.replace("{}", interest);
-
-        let user_id = Uuid::new_v4().to_string();
-
-        users.push(doc! {
-            "_id": &user_id,
-            "username": &username,
-            "email": &email,
-            "password_hash": format!("hashed_password_{}", number),
-            "bio": bio,
-            // Choose one of the following options:
-
-            "profile_picture_url": format!("https://randomuser.me/api/portraits/{}/{}.jpg",
-                if rng.gen_bool(0.5) { "man" } else { "women" },
-                rng.gen_range(1..1000)
-            ),
-
-            "join_date": current_time.to_rfc3339(),
-            "follower_count": 0, // Will be updated after follows are generated
-            "following_count": 0, // Will be updated after follows are generated
-            "post_count": 0, // Will be updated after posts are generated
-            "total_likes_received": 0, // Will be updated after likes are generated
-            "total_likes_given": 0, // Will be updated after likes are generated
-        });
-    }
-
-    users
-}
-
-// Helper function to generate random posts with enhanced details
-fn generate_random_posts(user_ids: &[String], count: usize) -> Vec<mongodb::bson::Document> {
-    let mut rng = rand::thread_rng();
-    let content_templates = vec![
-        "Exploring the transformative power of {} and how it is reshaping industries, driving innovation, and creating new opportunities for growth and development. From its origins to its current applications, this post dives deep into the impact of {} on our world.",
-        "The future of {} is here, and it's more exciting than ever. In this post, we examine the groundbreaking advancements in {} and their potential to revolutionize the way we live, work, and interact with technology.",
-        "What makes {} such a game-changer? This post uncovers the key innovations, challenges, and opportunities that {} brings to the table, and why it is set to redefine the future of technology.",
-        "A comprehensive look at the evolution of {} and its journey from a niche concept to a mainstream phenomenon. Discover the stories, breakthroughs, and visionaries behind {} and its role in shaping the future.",
-        "10 reasons why {} is not just a buzzword but a transformative force that is driving change across industries. This post explores the practical applications, benefits, and future potential of {}.",
-        "How {} is paving the way for a more sustainable, efficient, and connected world. This post delves into the innovations and ideas that are making {} a cornerstone of modern technology.",
-        "The untold story of {}: from its humble beginnings to its current status as a revolutionary technology. This post highlights the milestones, challenges, and future prospects of {}.",
-        "Behind the scenes of {}: a closer look at the people, ideas, and innovations that are driving its success. This post offers an insider's perspective on the world of {}.",
-        "My personal journey with {}: the lessons learned, the challenges faced, and the incredible potential that {} holds for the future. This post is a reflection on the transformative power of {}.",
-        "Revolutionizing the world with {}: an in-depth exploration of the technologies, ideas, and innovations that are making {} a driving force in the modern era.",
-        "The intersection of {} and everyday life: how this technology is quietly influencing the way we work, communicate, and solve problems. This post examines the subtle yet profound impact of {}.",
-        "The challenges and opportunities of {}: a balanced look at the hurdles that need to be overcome and the immense potential that lies ahead for this groundbreaking technology.",
-        "How {} is enabling new possibilities in fields like healthcare, education, and entertainment. This post explores the real-world applications of {} and their impact on society.",
-        "The role of {} in the global economy: how this technology is creating new markets, disrupting traditional industries, and driving economic growth worldwide.",
-        "A visionary look at the future of {}: what lies ahead for this technology, and how it could shape the next decade of innovation and progress.",
-    ];
-
-    let titles = vec![
-        "The Future of AI",
-        "Blockchain Revolution",
-        "Sustainable Living",
-        "Digital Artistry",
-        "Remote Work Culture",
-        "Space Exploration",
-        "Virtual Reality Experiences",
-        "Renewable Energy Solutions",
-        "Quantum Computing Breakthroughs",
-        "Robotics in Everyday Life",
-    ];
-
-    let topics = vec![
-        "AI",
-        "blockchain",
-        "sustainability",
-        "digital art",
-        "remote work",
-        "space exploration",
-        "virtual reality",
-        "renewable energy",
-        "quantum computing",
-        "robotics",
-    ];
-
-    let post_types = vec![
-        PostType::Text,
-        PostType::Image,
-        PostType::Video,
-        PostType::Link,
-    ];
-
-    let mut posts = Vec::new();
-    let current_time = chrono::Utc::now();
-
-    for _ in 0..count {
-        let user_id = user_ids.choose(&mut rng).unwrap();
-        let template = content_templates.choose(&mut rng).unwrap();
-        let topic = topics.choose(&mut rng).unwrap();
-        let content = template.replace("{}", topic);
-        let title = titles.choose(&mut rng).unwrap();
-
-        let post_type = post_types.choose(&mut rng).unwrap();
-        let media_urls = match post_type {
-            PostType::Image => vec![format!(
-                "https://picsum.photos/seed/{}/800/600",
-                Uuid::new_v4()
-            )],
-            PostType::Video => vec![format!("https://example.com/videos/{}.mp4", Uuid::new_v4())],
-            PostType::Link => vec![format!("https://example.com/article/{}", Uuid::new_v4())],
-            PostType::Text => Vec::new(),
-        };
-
-        let post_id = Uuid::new_v4().to_string();
-        posts.push(doc! {
-            "_id": &post_id,
-            "user_id": user_id,
-            "title": title,
-            "content": &content,
-            "media_urls": &media_urls,
-            "post_type": match post_type {
-                PostType::Text => "text",
-                PostType::Image => "image",
-                PostType::Video => "video",
-                PostType::Link => "link",
-            },
-            "created_at": current_time.to_rfc3339(),
-            "like_count": 0, // Will be updated after likes are generated
-            "comment_count": 0, // Will be updated after comments are generated
-        });
-    }
-
-    posts
-}
-
-// Helper function to generate random comments
-fn generate_random_comments(
-    user_ids: &[String],
-    post_ids: &[String],
-    count: usize,
-) -> Vec<Document> {
-    let mut rng = rand::thread_rng();
-    let comment_texts = vec![
-        "This is incredible!",
-        "I totally agree with this.",
-        "Not sure about this one...",
-        "Thanks for sharing!",
-        "Interesting perspective.",
-        "Can you explain more?",
-        "This changed my view completely.",
-        "I have a different opinion.",
-        "Mind = blown 🤯",
-        "This is the content I'm here for!",
-        "Well said!",
-        "Couldn't agree more.",
-        "This is the way.",
-        "Very insightful!",
-        "You might want to reconsider this.",
-    ];
-
-    let mut comments = Vec::new();
-    for _ in 0..count {
-        let user_id = user_ids.choose(&mut rng).unwrap();
-        let post_id = post_ids.choose(&mut rng).unwrap();
-        let content = comment_texts.choose(&mut rng).unwrap();
-
-        comments.push(doc! {
-            "_id": Uuid::new_v4().to_string(),
-            "post_id": post_id,
-            "user_id": user_id,
-            "content": content,
-            "created_at": chrono::Utc::now().to_rfc3339(),
-        });
-    }
-    comments
-}
-
-// Helper function to generate random likes
-fn generate_random_likes(user_ids: &[String], post_ids: &[String], count: usize) -> Vec<Document> {
-    let mut rng = rand::thread_rng();
-    let mut likes = Vec::new();
-    let mut seen_combinations = std::collections::HashSet::new();
-
-    while likes.len() < count {
-        let user_id = user_ids.choose(&mut rng).unwrap();
-        let post_id = post_ids.choose(&mut rng).unwrap();
-
-        // Ensure unique user-post combinations for likes
-        let combination = format!("{}-{}", user_id, post_id);
-        if seen_combinations.insert(combination) {
-            likes.push(doc! {
-                "_id": Uuid::new_v4().to_string(),
-                "user_id": user_id,
-                "post_id": post_id,
-                "created_at": chrono::Utc::now().to_rfc3339(),
-            });
-        }
-    }
-    likes
-}
-
-// Helper function to generate random follows
-fn generate_random_follows(user_ids: &[String], count: usize) -> Vec<Document> {
-    let mut rng = rand::thread_rng();
-    let mut follows = Vec::new();
-    let mut seen_combinations = std::collections::HashSet::new();
-
-    while follows.len() < count {
-        let follower_id = user_ids.choose(&mut rng).unwrap();
-        let following_id = user_ids.choose(&mut rng).unwrap();
-
-        // Avoid self-follows and duplicate relationships
-        if follower_id != following_id {
-            let combination = format!("{}-{}", follower_id, following_id);
-            if seen_combinations.insert(combination) {
-                follows.push(doc! {
-                    "_id": Uuid::new_v4().to_string(),
-                    "follower_id": follower_id,
-                    "following_id": following_id,
-                    "created_at": chrono::Utc::now().to_rfc3339(),
-                });
-            }
-        }
-    }
-    follows
-}
-
-// Populate Database Handler
-pub async fn populate_database_handler(
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!("Populating database with comprehensive test data");
-
-    // Initialize collections
-    let users_collection = state.db.collection::<Document>("users");
-    let posts_collection = state.db.collection::<Document>("posts");
-    let comments_collection = state.db.collection::<Document>("comments");
-    let likes_collection = state.db.collection::<Document>("likes");
-    let follows_collection = state.db.collection::<Document>("follows");
-
-    // Clean existing data first
-    for collection in [
-        &users_collection,
-        &posts_collection,
-        &comments_collection,
-        &likes_collection,
-        &follows_collection,
-    ] {
-        if let Err(e) = collection.delete_many(doc! {}).await {
-            error!("Error cleaning collection: {}", e);
-            return Err(AppError::from(e));
-        }
-    }
-
-    // Generate and insert users
-    let test_users = generate_random_users(150);
-    let mut user_ids = Vec::new();
-
-    for user in &test_users {
-        match users_collection.insert_one(user.clone()).await {
-            Ok(_) => {
-                if let Ok(id) = user.get_str("_id") {
-                    user_ids.push(id.to_string());
-                }
-            }
-            Err(e) => {
-                error!("Error inserting test user: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    // Generate and insert posts
-    let test_posts = generate_random_posts(&user_ids, 300);
-    let mut post_ids = Vec::new();
-
-    for post in &test_posts {
-        match posts_collection.insert_one(post.clone()).await {
-            Ok(_) => {
-                if let Ok(id) = post.get_str("_id") {
-                    post_ids.push(id.to_string());
-                }
-            }
-            Err(e) => {
-                error!("Error inserting test post: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    // Generate and insert comments
-    let test_comments = generate_random_comments(&user_ids, &post_ids, 500);
-    for comment in test_comments {
-        if let Err(e) = comments_collection.insert_one(comment).await {
-            error!("Error inserting test comment: {}", e);
-            return Err(AppError::from(e));
-        }
-    }
-
-    // Generate and insert likes
-    let test_likes = generate_random_likes(&user_ids, &post_ids, 1000);
-    for like in test_likes {
-        if let Err(e) = likes_collection.insert_one(like).await {
-            error!("Error inserting test like: {}", e);
-            return Err(AppError::from(e));
-        }
-    }
-
-    // Generate and insert follows
-    let test_follows = generate_random_follows(&user_ids, 400);
-    for follow in test_follows {
-        if let Err(e) = follows_collection.insert_one(follow).await {
-            error!("Error inserting test follow: {}", e);
-            return Err(AppError::from(e));
-        }
-    }
-
-    // Update statistics for users and posts
-    for user_id in &user_ids {
-        // Count user statistics
-        let post_count = posts_collection
-            .count_documents(doc! { "user_id": user_id })
-            .await? as i32;
-        let follower_count = follows_collection
-            .count_documents(doc! { "following_id": user_id })
-            .await? as i32;
-        let following_count = follows_collection
-            .count_documents(doc! { "follower_id": user_id })
-            .await? as i32;
-        let comment_count = comments_collection
-            .count_documents(doc! { "user_id": user_id })
-            .await? as i32;
-        let likes_given = likes_collection
-            .count_documents(doc! { "user_id": user_id })
-            .await? as i32;
-
-        // Update user document
-        users_collection
-            .update_one(
-                doc! { "_id": user_id },
-                doc! {
-                    "$set": {
-                        "post_count": post_count,
-                        "follower_count": follower_count,
-                        "following_count": following_count,
-                        "comment_count": comment_count,
-                        "total_likes_given": likes_given,
-                    }
-                },
-            )
-            .await?;
-    }
-
-    // Update statistics for posts
-    for post_id in &post_ids {
-        let comment_count = comments_collection
-            .count_documents(doc! { "post_id": post_id })
-            .await? as i32;
-        let like_count = likes_collection
-            .count_documents(doc! { "post_id": post_id })
-            .await? as i32;
-
-        posts_collection
-            .update_one(
-                doc! { "_id": post_id },
-                doc! {
-                    "$set": {
-                        "comment_count": comment_count,
-                        "like_count": like_count,
-                    }
-                },
-            )
-            .await?;
-    }
-
-    Ok(HttpResponse::Ok().json(Response::<()> {
-        status: "success".to_string(),
-        message: format!(
-            "Database populated with {} users, {} posts, {} comments, {} likes, and {} follows. All statistics updated.",
-            test_users.len(),
-            test_posts.len(),
-            500, // comments
-            1000, // likes
-            400, // follows
-        ),
-        data: None,
-    }))
-}
-
-// Clean Database Handler
-pub async fn clean_database_handler(
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    info!("Cleaning all collections in the database");
-
-    let collections = vec![
-        ("users", "users collection"),
-        ("posts", "posts collection"),
-        ("comments", "comments collection"),
-        ("likes", "likes collection"),
-        ("follows", "follows collection"),
-    ];
-
-    for (collection_name, description) in collections {
-        let collection = state.db.collection::<Document>(collection_name);
-        if let Err(e) = collection.delete_many(doc! {}).await {
-            error!("Error cleaning {}: {}", description, e);
-            return Err(AppError::from(e));
-        }
-        info!("Successfully cleaned {}", description);
-    }
-
-    Ok(HttpResponse::Ok().json(Response::<()> {
-        status: "success".to_string(),
-        message: "All collections cleaned successfully".to_string(),
-        data: None,
-    }))
-}
-
-// Get All Posts Handler
-pub async fn get_posts_handler(state: web::Data<AppState>) -> Result<impl Responder, AppError> {
-    info!("Fetching all posts");
-
-    let collection = state.db.collection::<Document>("posts");
-    let mut cursor = match collection.find(doc! {}).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching posts: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut posts = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                posts.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing post document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!("Successfully fetched {} posts", posts.len());
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!("Successfully fetched {} posts", posts.len()),
-        data: Some(posts),
-    }))
-}
-
-// Get Post by ID Handler
-pub async fn get_post_by_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let post_id = path.into_inner();
-    info!("Fetching post with ID: {}", post_id);
-
-    let collection = state.db.collection::<Document>("posts");
-    let filter = doc! { "_id": &post_id };
-
-    match collection.find_one(filter).await {
-        Ok(Some(post)) => {
-            info!("Successfully fetched post with ID: {}", post_id);
-            Ok(HttpResponse::Ok().json(Response {
-                status: "success".to_string(),
-                message: "Post fetched successfully".to_string(),
-                data: Some(post),
-            }))
-        }
-        Ok(None) => {
-            error!("Post with ID {} not found", post_id);
-            Err(AppError::NotFound(format!(
-                "Post with ID {} not found",
-                post_id
-            )))
-        }
-        Err(e) => {
-            error!("Error fetching post: {}", e);
-            Err(AppError::from(e))
-        }
-    }
-}
-
-// Get All Users Handler
-pub async fn get_users_handler(state: web::Data<AppState>) -> Result<impl Responder, AppError> {
-    info!("Fetching all users");
-
-    let collection = state.db.collection::<Document>("users");
-    let mut cursor = match collection.find(doc! {}).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching users: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut users = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                users.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing user document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!("Successfully fetched {} users", users.len());
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!("Successfully fetched {} users", users.len()),
-        data: Some(users),
-    }))
-}
-
-// Get User by ID Handler
-pub async fn get_user_by_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let user_id = path.into_inner();
-    info!("Fetching user with ID: {}", user_id);
-
-    let collection = state.db.collection::<Document>("users");
-    let filter = doc! { "_id": &user_id };
-
-    match collection.find_one(filter).await {
-        Ok(Some(user)) => {
-            info!("Successfully fetched user with ID: {}", user_id);
-            Ok(HttpResponse::Ok().json(Response {
-                status: "success".to_string(),
-                message: "User fetched successfully".to_string(),
-                data: Some(user),
-            }))
-        }
-        Ok(None) => {
-            error!("User with ID {} not found", user_id);
-            Err(AppError::NotFound(format!(
-                "User with ID {} not found",
-                user_id
-            )))
-        }
-        Err(e) => {
-            error!("Error fetching user: {}", e);
-            Err(AppError::from(e))
-        }
-    }
-}
-
-// Get All Comments Handler
-pub async fn get_comments_handler(state: web::Data<AppState>) -> Result<impl Responder, AppError> {
-    info!("Fetching all comments");
-
-    let collection = state.db.collection::<Document>("comments");
-    let mut cursor = match collection.find(doc! {}).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching comments: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut comments = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                comments.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing comment document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!("Successfully fetched {} comments", comments.len());
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!("Successfully fetched {} comments", comments.len()),
-        data: Some(comments),
-    }))
-}
-
-// Get Comment by ID Handler
-pub async fn get_comment_by_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let comment_id = path.into_inner();
-    info!("Fetching comment with ID: {}", comment_id);
-
-    let collection = state.db.collection::<Document>("comments");
-    let filter = doc! { "_id": &comment_id };
-
-    match collection.find_one(filter).await {
-        Ok(Some(comment)) => {
-            info!("Successfully fetched comment with ID: {}", comment_id);
-            Ok(HttpResponse::Ok().json(Response {
-                status: "success".to_string(),
-                message: "Comment fetched successfully".to_string(),
-                data: Some(comment),
-            }))
-        }
-        Ok(None) => {
-            error!("Comment with ID {} not found", comment_id);
-            Err(AppError::NotFound(format!(
-                "Comment with ID {} not found",
-                comment_id
-            )))
-        }
-        Err(e) => {
-            error!("Error fetching comment: {}", e);
-            Err(AppError::from(e))
-        }
-    }
-}
-
-// Get Comments by Post ID Handler
-pub async fn get_comments_by_post_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let post_id = path.into_inner();
-    info!("Fetching comments for post with ID: {}", post_id);
-
-    let collection = state.db.collection::<Document>("comments");
-    let filter = doc! { "post_id": &post_id };
-
-    let mut cursor = match collection.find(filter).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching comments for post: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut comments = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                comments.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing comment document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!(
-        "Successfully fetched {} comments for post {}",
-        comments.len(),
-        post_id
-    );
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!(
-            "Successfully fetched {} comments for post {}",
-            comments.len(),
-            post_id
-        ),
-        data: Some(comments),
-    }))
-}
-
-// Get Comments by User ID Handler
-pub async fn get_comments_by_user_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let user_id = path.into_inner();
-    info!("Fetching comments by user with ID: {}", user_id);
-
-    let collection = state.db.collection::<Document>("comments");
-    let filter = doc! { "user_id": &user_id };
-
-    let mut cursor = match collection.find(filter).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching comments for user: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut comments = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                comments.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing comment document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!(
-        "Successfully fetched {} comments by user {}",
-        comments.len(),
-        user_id
-    );
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!(
-            "Successfully fetched {} comments by user {}",
-            comments.len(),
-            user_id
-        ),
-        data: Some(comments),
-    }))
-}
-
-// Get Following Users Handler
-pub async fn get_following_users_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let user_id = path.into_inner();
-    info!("Fetching users followed by user with ID: {}", user_id);
-
-    let collection = state.db.collection::<Document>("follows");
-    let filter = doc! { "follower_id": &user_id };
-
-    let mut cursor = match collection.find(filter).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching followed users: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut following_users = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                // Extract the followed user ID and fetch their details from users collection
-                if let Ok(following_id) = document.get_str("following_id") {
-                    let users_collection = state.db.collection::<Document>("users");
-                    if let Ok(Some(user_doc)) = users_collection
-                        .find_one(doc! { "_id": following_id })
-                        .await
-                    {
-                        following_users.push(user_doc);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Error parsing follow document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!(
-        "Successfully fetched {} followed users for user {}",
-        following_users.len(),
-        user_id
-    );
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!(
-            "Successfully fetched {} followed users",
-            following_users.len()
-        ),
-        data: Some(following_users),
-    }))
-}
-
-// Get Followers Users Handler
-pub async fn get_followers_users_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let user_id = path.into_inner();
-    info!("Fetching followers for user with ID: {}", user_id);
-
-    let collection = state.db.collection::<Document>("follows");
-    let filter = doc! { "following_id": &user_id };
-
-    let mut cursor = match collection.find(filter).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching followers: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut followers = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                // Extract the follower ID and fetch their details from users collection
-                if let Ok(follower_id) = document.get_str("follower_id") {
-                    let users_collection = state.db.collection::<Document>("users");
-                    if let Ok(Some(user_doc)) =
-                        users_collection.find_one(doc! { "_id": follower_id }).await
-                    {
-                        followers.push(user_doc);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Error parsing follow document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!(
-        "Successfully fetched {} followers for user {}",
-        followers.len(),
-        user_id
-    );
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!("Successfully fetched {} followers", followers.len()),
-        data: Some(followers),
-    }))
-}
-
-// Get Posts by User ID Handler
-pub async fn get_posts_by_user_id_handler(
-    path: web::Path<String>,
-    state: web::Data<AppState>,
-) -> Result<impl Responder, AppError> {
-    let user_id = path.into_inner();
-    info!("Fetching posts for user with ID: {}", user_id);
-
-    // First verify that the user exists
-    let users_collection = state.db.collection::<Document>("users");
-    match users_collection.find_one(doc! { "_id": &user_id }).await {
-        Ok(None) => {
-            error!("User with ID {} not found", user_id);
-            return Err(AppError::NotFound(format!(
-                "User with ID {} not found",
-                user_id
-            )));
-        }
-        Err(e) => {
-            error!("Error verifying user existence: {}", e);
-            return Err(AppError::from(e));
-        }
-        _ => {} // User exists, continue
-    }
-
-    let collection = state.db.collection::<Document>("posts");
-    let filter = doc! { "user_id": &user_id };
-
-    let mut cursor = match collection.find(filter).await {
-        Ok(cursor) => cursor,
-        Err(e) => {
-            error!("Error fetching posts for user: {}", e);
-            return Err(AppError::from(e));
-        }
-    };
-
-    let mut posts = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(document) => {
-                posts.push(document);
-            }
-            Err(e) => {
-                error!("Error parsing post document: {}", e);
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    info!(
-        "Successfully fetched {} posts for user {}",
-        posts.len(),
-        user_id
-    );
-    Ok(HttpResponse::Ok().json(Response {
-        status: "success".to_string(),
-        message: format!(
-            "Successfully fetched {} posts for user {}",
-            posts.len(),
-            user_id
-        ),
-        data: Some(posts),
-    }))
-}
+use crate::{
+    audit::{AuditContext, AuditEvent},
+    auth::{current_actor, issue_token, optional_actor, Actor},
+    errors::{is_duplicate_key_error, is_transient_transaction_error, AppError},
+    exports::spawn_export,
+    metrics::{metrics, CONTENT_TYPE as METRICS_CONTENT_TYPE},
+    models::*,
+    publishing::notification_doc,
+    repository::ListOptions,
+    retention::within_restore_window,
+    seeding::spawn_seed_job,
+    state::AppState,
+    validation::{
+        bookmark_collection_name, parse_timestamp, validate_moderation_note, validate_post,
+        validate_publish_at, validate_report_reason, validate_seed_options,
+    },
+    visibility::{
+        account_state, can_view_account, can_view_comment, can_view_post, user_role,
+        viewer_posts_filter, AccountState, Viewer, ViewerContext,
+    },
+};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, Result};
+use chrono::Utc;
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use subtle::ConstantTimeEq;
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+
+// A readiness probe answers quickly so the load balancer notices a lost database within
+// one probe period instead of after a long stream of retries
+const READINESS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Liveness Handler: the process is up and serving requests, the database is not checked
+#[instrument(skip_all)]
+pub async fn liveness_handler() -> impl Responder {
+    HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "Service is alive".to_string(),
+        data: None,
+    })
+}
+
+// Readiness Handler: a single ping, also served at /api/health for existing clients
+#[instrument(skip_all)]
+pub async fn readiness_handler(state: web::Data<AppState>) -> Result<impl Responder, AppError> {
+    let ping = tokio::time::timeout(READINESS_TIMEOUT, state.database.ping());
+    let error_message = match ping.await {
+        Ok(Ok(_)) => {
+            return Ok(HttpResponse::Ok().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Service is healthy".to_string(),
+                data: None,
+            }))
+        }
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "database connection timeout".to_string(),
+    };
+    error!("Readiness check failed: {}", error_message);
+
+    Ok(HttpResponse::ServiceUnavailable().json(Response::<()> {
+        status: "error".to_string(),
+        message: format!("Service is unhealthy: {}", error_message),
+        data: None,
+    }))
+}
+
+// Metrics Handler: Prometheus scrape endpoint
+#[instrument(skip_all)]
+pub async fn metrics_handler() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(metrics().render())
+}
+
+// Cluster Status Handler: member states, replication lag and elections of the replica set
+#[instrument(skip_all)]
+pub async fn get_cluster_status_handler(
+    actor: web::ReqData<Actor>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Admin {} is fetching the replica set status", actor.user_id);
+
+    let cluster = state.database.cluster_status().await?;
+    if cluster.primary.is_none() {
+        error!("Replica set {} has no primary", cluster.set);
+    }
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Replica set {} has {} member(s)",
+            cluster.set,
+            cluster.members.len()
+        ),
+        data: Some(cluster),
+    }))
+}
+
+// Create User Handler
+#[instrument(skip_all)]
+pub async fn create_user_handler(
+    user: web::Json<User>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Creating new user with username: {}", user.username);
+
+    if user.username.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Username cannot be empty".to_string(),
+        ));
+    }
+
+    if user.email.is_empty() || !user.email.contains('@') {
+        return Err(AppError::InvalidInput("Invalid email format".to_string()));
+    }
+
+    if user.password_hash.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Password hash cannot be empty".to_string(),
+        ));
+    }
+
+    if let Ok(Some(_)) = state.users.find_by_email(&user.email).await {
+        return Err(AppError::InvalidInput(format!(
+            "User with email {} already exists",
+            user.email
+        )));
+    }
+
+    let user_id = Uuid::new_v4().to_string();
+    let user_doc = doc! {
+        "_id": &user_id,
+        "username": &user.username,
+        "email": &user.email,
+        "password_hash": &user.password_hash,
+        "is_private": user.is_private,
+        "role": UserRole::User.as_str(),
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.users.insert(user_doc.clone()).await {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("create_user", "user", &user_id)
+                        .by(&user_id)
+                        .after(&user_doc),
+                )
+                .await;
+            metrics().users_created.inc();
+            info!("User created successfully with ID: {}", user_id);
+            Ok(HttpResponse::Created().json(Response {
+                status: "success".to_string(),
+                message: format!("User created successfully with ID: {}", user_id),
+                data: Some(user_id),
+            }))
+        }
+        Err(e) => {
+            error!("Error creating user: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Create Post Handler
+#[instrument(skip_all)]
+pub async fn create_post_handler(
+    post: web::Json<Post>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Creating new post for user: {}", post.user_id);
+
+    validate_post(&post)?;
+
+    let author = match state.users.find_active(&post.user_id).await? {
+        Some(author) => author,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                post.user_id
+            )))
+        }
+    };
+    if author.get_bool("suspended").unwrap_or(false) {
+        return Err(AppError::Forbidden(format!(
+            "User {} is suspended",
+            post.user_id
+        )));
+    }
+
+    let mut mentions = post.mentions.clone();
+    mentions.sort();
+    mentions.dedup();
+    if !mentions.is_empty() {
+        let found = state.users.count_active(&mentions).await?;
+        if found != mentions.len() as u64 {
+            return Err(AppError::NotFound(
+                "One or more mentioned users were not found".to_string(),
+            ));
+        }
+    }
+
+    if let Some(quoted_post_id) = &post.quoted_post_id {
+        let quoted = state.posts.find_by_id(quoted_post_id).await?;
+        let visible = match &quoted {
+            Some(quoted) => can_view_post(&state, quoted, Viewer::user(&post.user_id)).await?,
+            None => false,
+        };
+        if !visible {
+            return Err(AppError::NotFound(format!(
+                "Quoted post with ID {} not found",
+                quoted_post_id
+            )));
+        }
+    }
+
+    let status = if post.draft {
+        PostStatus::Draft
+    } else if post.publish_at.is_some() {
+        PostStatus::Scheduled
+    } else {
+        PostStatus::Published
+    };
+
+    let post_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut post_doc = doc! {
+        "_id": &post_id,
+        "user_id": &post.user_id,
+        "content": &post.content,
+        "media_urls": &post.media_urls,
+        "post_type": post.post_type.as_str(),
+        "status": status.as_str(),
+        "visibility": post.visibility.as_str(),
+        "mentions": &mentions,
+        // Denormalized so feed queries can filter private accounts without a join
+        "author_private": author.get_bool("is_private").unwrap_or(false),
+        "like_count": 0,
+        "repost_count": 0,
+        "quote_count": 0,
+        "created_at": &now,
+    };
+    if let Some(poll) = &post.poll {
+        post_doc.insert("poll", poll_document(poll));
+    }
+    if let Some(quoted_post_id) = &post.quoted_post_id {
+        post_doc.insert("quoted_post_id", quoted_post_id);
+    }
+    if let Some(publish_at) = post.publish_at.as_deref().and_then(parse_timestamp) {
+        post_doc.insert("publish_at", publish_at.to_rfc3339());
+    }
+    if status == PostStatus::Published {
+        post_doc.insert("published_at", &now);
+    }
+
+    // Published posts go live together with their counters and notifications
+    let result = if status == PostStatus::Published {
+        state.posts.insert_published(post_doc.clone()).await
+    } else {
+        state.posts.insert(post_doc.clone()).await
+    };
+
+    match result {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("create_post", "post", &post_id)
+                        .by(&post.user_id)
+                        .after(&post_doc),
+                )
+                .await;
+            metrics().posts_created.inc();
+            info!(
+                "Post created successfully with ID: {} ({})",
+                post_id,
+                status.as_str()
+            );
+            Ok(HttpResponse::Created().json(Response {
+                status: "success".to_string(),
+                message: format!("Post created successfully with ID: {}", post_id),
+                data: Some(post_id),
+            }))
+        }
+        Err(e) => {
+            error!("Error creating post: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Publish Post Handler
+#[instrument(skip_all)]
+pub async fn publish_post_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: Option<web::Json<PublishPost>>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    let request = body.map(|body| body.into_inner()).unwrap_or_default();
+    info!("Publishing post with ID: {}", post_id);
+
+    let before = match state.posts.find_by_id(&post_id).await? {
+        Some(post) => post,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                post_id
+            )))
+        }
+    };
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, before.get_str("user_id").unwrap_or_default())?;
+
+    if let Some(publish_at) = &request.publish_at {
+        let publish_at = validate_publish_at(publish_at)?;
+        let scheduled = state
+            .posts
+            .schedule(&post_id, &publish_at.to_rfc3339())
+            .await?;
+
+        if !scheduled {
+            return Err(AppError::Conflict(format!(
+                "Post {} has already been published",
+                post_id
+            )));
+        }
+
+        audit
+            .record(
+                state.audit_log.as_ref(),
+                AuditEvent::new("schedule_post", "post", &post_id)
+                    .by(&actor.user_id)
+                    .before(&doc! { "status": before.get("status"), "publish_at": before.get("publish_at") })
+                    .after(&doc! {
+                        "status": PostStatus::Scheduled.as_str(),
+                        "publish_at": publish_at.to_rfc3339(),
+                    }),
+            )
+            .await;
+        info!("Post {} scheduled for {}", post_id, publish_at.to_rfc3339());
+        return Ok(HttpResponse::Ok().json(Response::<()> {
+            status: "success".to_string(),
+            message: format!("Post scheduled for {}", publish_at.to_rfc3339()),
+            data: None,
+        }));
+    }
+
+    match state.posts.publish(&post_id).await? {
+        Some(after) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("publish_post", "post", &post_id)
+                        .by(&actor.user_id)
+                        .before(&before)
+                        .after(&after),
+                )
+                .await;
+            info!("Post {} published", post_id);
+            Ok(HttpResponse::Ok().json(Response::<()> {
+                status: "success".to_string(),
+                message: format!("Post {} published successfully", post_id),
+                data: None,
+            }))
+        }
+        None => Err(AppError::Conflict(format!(
+            "Post {} has already been published",
+            post_id
+        ))),
+    }
+}
+
+// Create Comment Handler
+#[instrument(skip_all)]
+pub async fn create_comment_handler(
+    comment: web::Json<Comment>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Creating new comment for post: {}", comment.post_id);
+
+    if comment.post_id.is_empty() || comment.user_id.is_empty() || comment.content.is_empty() {
+        return Err(AppError::InvalidInput(
+            "All fields are required".to_string(),
+        ));
+    }
+    ensure_active_user(&state, &comment.user_id).await?;
+
+    let post = match state.posts.find_by_id(&comment.post_id).await? {
+        Some(post) if can_view_post(&state, &post, Viewer::user(&comment.user_id)).await? => post,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                comment.post_id
+            )))
+        }
+    };
+
+    let comment_id = Uuid::new_v4().to_string();
+    let comment_doc = doc! {
+        "_id": &comment_id,
+        "post_id": &comment.post_id,
+        "user_id": &comment.user_id,
+        "content": &comment.content,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.comments.insert(comment_doc.clone()).await {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("create_comment", "comment", &comment_id)
+                        .by(&comment.user_id)
+                        .after(&comment_doc),
+                )
+                .await;
+            metrics().comments_created.inc();
+            send_notification(
+                &state,
+                notification_doc(
+                    &comment_id,
+                    post.get_str("user_id").unwrap_or_default(),
+                    &comment.user_id,
+                    NotificationKind::Comment,
+                    Some(&comment.post_id),
+                ),
+            )
+            .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Comment created successfully".to_string(),
+                data: None,
+            }))
+        }
+        Err(e) => Err(AppError::from(e)),
+    }
+}
+
+// Follow User Handler
+#[instrument(skip_all)]
+pub async fn follow_user_handler(
+    follow: web::Json<Follow>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!(
+        "User {} is following user {}",
+        follow.follower_id, follow.following_id
+    );
+
+    if follow.follower_id.is_empty() || follow.following_id.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Follower and Following IDs are required".to_string(),
+        ));
+    }
+    ensure_active_user(&state, &follow.follower_id).await?;
+
+    let target = match state.users.find_active(&follow.following_id).await? {
+        Some(target) => target,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                follow.following_id
+            )))
+        }
+    };
+
+    if state
+        .follows
+        .is_blocked_between(&follow.follower_id, &follow.following_id)
+        .await?
+    {
+        return Err(AppError::Forbidden(format!(
+            "User {} cannot follow user {}",
+            follow.follower_id, follow.following_id
+        )));
+    }
+
+    // Private accounts approve followers, so only record a pending request
+    if target.get_bool("is_private").unwrap_or(false) {
+        let response = request_follow(&state, &follow.follower_id, &follow.following_id).await?;
+        audit
+            .record(
+                state.audit_log.as_ref(),
+                AuditEvent::new(
+                    "request_follow",
+                    "follow_request",
+                    &follow_request_id(&follow.follower_id, &follow.following_id),
+                )
+                .by(&follow.follower_id),
+            )
+            .await;
+        return Ok(response);
+    }
+
+    let follow_id = ObjectId::new();
+    let follow_doc = doc! {
+        "_id": follow_id,
+        "follower_id": &follow.follower_id,
+        "following_id": &follow.following_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.follows.insert(follow_doc.clone()).await {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("follow_user", "follow", &follow_id.to_hex())
+                        .by(&follow.follower_id)
+                        .after(&follow_doc),
+                )
+                .await;
+            metrics().follows_created.inc();
+            send_notification(
+                &state,
+                notification_doc(
+                    &follow.follower_id,
+                    &follow.following_id,
+                    &follow.follower_id,
+                    NotificationKind::Follow,
+                    None,
+                ),
+            )
+            .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Follow relationship created successfully".to_string(),
+                data: None,
+            }))
+        }
+        Err(e) => Err(AppError::from(e)),
+    }
+}
+
+// Follow requests are keyed by both users so only one can be pending at a time
+fn follow_request_id(follower_id: &str, following_id: &str) -> String {
+    format!("{}:{}", follower_id, following_id)
+}
+
+async fn request_follow(
+    state: &AppState,
+    follower_id: &str,
+    following_id: &str,
+) -> Result<HttpResponse, AppError> {
+    if state
+        .follows
+        .is_following(follower_id, following_id)
+        .await?
+    {
+        return Err(AppError::Conflict(format!(
+            "User {} already follows user {}",
+            follower_id, following_id
+        )));
+    }
+
+    let request_doc = doc! {
+        "_id": follow_request_id(follower_id, following_id),
+        "follower_id": follower_id,
+        "following_id": following_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.follows.insert_request(request_doc).await {
+        Ok(()) => {
+            send_notification(
+                state,
+                notification_doc(
+                    follower_id,
+                    following_id,
+                    follower_id,
+                    NotificationKind::FollowRequest,
+                    None,
+                ),
+            )
+            .await;
+            info!(
+                "Follow request from {} to {} is pending approval",
+                follower_id, following_id
+            );
+            Ok(HttpResponse::Accepted().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Follow request sent and awaiting approval".to_string(),
+                data: None,
+            }))
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "A follow request from {} to {} is already pending",
+            follower_id, following_id
+        ))),
+        Err(e) => Err(AppError::from(e)),
+    }
+}
+
+// Turn a pending follow request into a follow. Returns false when no request was pending.
+async fn accept_follow_request(
+    state: &AppState,
+    follower_id: &str,
+    following_id: &str,
+) -> Result<bool, AppError> {
+    let request_id = follow_request_id(follower_id, following_id);
+    let follow_doc = doc! {
+        "_id": ObjectId::new(),
+        "follower_id": follower_id,
+        "following_id": following_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    let accepted = state
+        .follows
+        .accept_request(&request_id, follow_doc)
+        .await?;
+
+    if accepted {
+        metrics().follows_created.inc();
+        send_notification(
+            state,
+            notification_doc(
+                following_id,
+                follower_id,
+                following_id,
+                NotificationKind::FollowAccepted,
+                None,
+            ),
+        )
+        .await;
+    }
+
+    Ok(accepted)
+}
+
+// Get Follow Requests Handler
+#[instrument(skip_all)]
+pub async fn get_follow_requests_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching pending follow requests for user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let requests = state
+        .follows
+        .list_requests(
+            doc! { "following_id": &user_id },
+            ListOptions::sorted(doc! { "created_at": -1 }),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} follow requests", requests.len()),
+        data: Some(requests),
+    }))
+}
+
+// Approve Follow Request Handler
+#[instrument(skip_all)]
+pub async fn approve_follow_request_handler(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (user_id, follower_id) = path.into_inner();
+    info!(
+        "User {} is approving follow request from {}",
+        user_id, follower_id
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    if !accept_follow_request(&state, &follower_id, &user_id).await? {
+        return Err(AppError::NotFound(format!(
+            "No pending follow request from {} to {}",
+            follower_id, user_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new(
+                "approve_follow_request",
+                "follow_request",
+                &follow_request_id(&follower_id, &user_id),
+            )
+            .by(&actor.user_id),
+        )
+        .await;
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "Follow request approved".to_string(),
+        data: None,
+    }))
+}
+
+// Reject Follow Request Handler
+#[instrument(skip_all)]
+pub async fn reject_follow_request_handler(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (user_id, follower_id) = path.into_inner();
+    info!(
+        "User {} is rejecting follow request from {}",
+        user_id, follower_id
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let request_id = follow_request_id(&follower_id, &user_id);
+    let request = match state.follows.reject_request(&request_id).await? {
+        Some(request) => request,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "No pending follow request from {} to {}",
+                follower_id, user_id
+            )))
+        }
+    };
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("reject_follow_request", "follow_request", &request_id)
+                .by(&actor.user_id)
+                .before(&request),
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "Follow request rejected".to_string(),
+        data: None,
+    }))
+}
+
+// Update Account Privacy Handler
+#[instrument(skip_all)]
+pub async fn update_privacy_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    privacy: web::Json<AccountPrivacy>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!(
+        "Setting account privacy for user {} to {}",
+        user_id, privacy.is_private
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let before = match state
+        .users
+        .set_private(&user_id, privacy.is_private)
+        .await?
+    {
+        Some(before) => before,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                user_id
+            )))
+        }
+    };
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("update_privacy", "user", &user_id)
+                .by(&actor.user_id)
+                .before(&doc! { "is_private": before.get_bool("is_private").unwrap_or(false) })
+                .after(&doc! { "is_private": privacy.is_private }),
+        )
+        .await;
+
+    // Going public approves everyone who was waiting
+    let mut approved = 0;
+    if !privacy.is_private {
+        let requests = state
+            .follows
+            .list_requests(doc! { "following_id": &user_id }, ListOptions::default())
+            .await?;
+        let follower_ids = requests
+            .iter()
+            .filter_map(|request| request.get_str("follower_id").ok());
+        for follower_id in follower_ids {
+            if accept_follow_request(&state, follower_id, &user_id).await? {
+                approved += 1;
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: if privacy.is_private {
+            "Account is now private".to_string()
+        } else {
+            format!(
+                "Account is now public, {} pending follow requests approved",
+                approved
+            )
+        },
+        data: None,
+    }))
+}
+
+// Suspended users keep read access but can no longer create content or connections,
+// and deleted users can do neither until they restore their account
+async fn ensure_active_user(state: &AppState, user_id: &str) -> Result<(), AppError> {
+    match account_state(state, user_id).await? {
+        AccountState::Active => Ok(()),
+        AccountState::Suspended => Err(AppError::Forbidden(format!(
+            "User {} is suspended",
+            user_id
+        ))),
+        AccountState::Deleted => Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        ))),
+    }
+}
+
+// Notifications are best effort and never fail the action that triggered them
+async fn send_notification(state: &AppState, notification: Option<Document>) {
+    if let Some(notification) = notification {
+        if let Err(e) = state.notifications.insert(notification).await {
+            if !is_duplicate_key_error(&e) {
+                error!("Error creating notification: {}", e);
+            }
+        }
+    }
+}
+
+// Build the stored representation of a new poll
+fn poll_document(poll: &Poll) -> Document {
+    let options: Vec<Document> = poll
+        .options
+        .iter()
+        .map(|text| doc! { "text": text.trim(), "vote_count": 0 })
+        .collect();
+
+    doc! {
+        "options": options,
+        "multiple_choice": poll.multiple_choice,
+        "closes_at": poll
+            .closes_at
+            .as_deref()
+            .and_then(parse_timestamp)
+            .map(|time| time.to_rfc3339()),
+        "total_votes": 0,
+    }
+}
+
+// Votes are keyed by post and user so the _id index enforces one vote per user
+fn poll_vote_id(post_id: &str, user_id: &str) -> String {
+    format!("{}:{}", post_id, user_id)
+}
+
+// Vote on Poll Handler
+#[instrument(skip_all)]
+pub async fn vote_poll_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    vote: web::Json<Vote>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    let actor = current_actor(&req, &state).await?;
+    let user_id = &actor.user_id;
+    info!("User {} is voting on poll {}", user_id, post_id);
+
+    ensure_active_user(&state, user_id).await?;
+
+    let post = match state.posts.find_by_id(&post_id).await? {
+        Some(post) if can_view_post(&state, &post, Viewer::actor(&actor)).await? => post,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                post_id
+            )))
+        }
+    };
+
+    let poll = match post.get_document("poll") {
+        Ok(poll) => poll,
+        Err(_) => {
+            return Err(AppError::InvalidInput(format!(
+                "Post with ID {} is not a poll",
+                post_id
+            )))
+        }
+    };
+
+    if poll_is_closed(poll) {
+        return Err(AppError::InvalidInput(format!(
+            "Poll on post {} is closed",
+            post_id
+        )));
+    }
+
+    let option_count = poll.get_array("options").map(|o| o.len()).unwrap_or(0);
+    let mut option_indexes = vote.option_indexes.clone();
+    option_indexes.sort_unstable();
+    option_indexes.dedup();
+
+    if option_indexes.is_empty() {
+        return Err(AppError::ValidationError {
+            field: "option_indexes".to_string(),
+            message: "At least one option must be selected".to_string(),
+        });
+    }
+    if !poll.get_bool("multiple_choice").unwrap_or(false) && option_indexes.len() > 1 {
+        return Err(AppError::ValidationError {
+            field: "option_indexes".to_string(),
+            message: "This poll only allows a single choice".to_string(),
+        });
+    }
+    if let Some(index) = option_indexes.iter().find(|&&i| i >= option_count) {
+        return Err(AppError::ValidationError {
+            field: "option_indexes".to_string(),
+            message: format!("Option {} does not exist on this poll", index),
+        });
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let vote_doc = doc! {
+        "_id": poll_vote_id(&post_id, user_id),
+        "post_id": &post_id,
+        "user_id": user_id,
+        "option_indexes": option_indexes.iter().map(|&i| i as i32).collect::<Vec<i32>>(),
+        "created_at": &now,
+    };
+
+    // The store records the vote and bumps the option counts together, and only while the
+    // poll is still open when the write lands
+    match state.posts.vote(vote_doc.clone()).await {
+        Ok(true) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("vote_poll", "poll_vote", &poll_vote_id(&post_id, user_id))
+                        .by(user_id)
+                        .after(&vote_doc),
+                )
+                .await;
+            info!("Recorded vote from user {} on poll {}", user_id, post_id);
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Vote recorded successfully".to_string(),
+                data: None,
+            }))
+        }
+        Ok(false) => Err(AppError::InvalidInput(format!(
+            "Poll on post {} is closed",
+            post_id
+        ))),
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "User {} has already voted on poll {}",
+            user_id, post_id
+        ))),
+        // The store already retried, so other votes keep winning the race for the poll
+        Err(e) if is_transient_transaction_error(&e) => Err(AppError::Conflict(format!(
+            "Poll on post {} is busy, please retry the vote",
+            post_id
+        ))),
+        Err(e) => {
+            error!("Error recording vote: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Reposts are keyed by post and user so each user can repost a post only once
+fn repost_id(post_id: &str, user_id: &str) -> String {
+    format!("{}:{}", post_id, user_id)
+}
+
+// Repost Handler
+#[instrument(skip_all)]
+pub async fn repost_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    let actor = current_actor(&req, &state).await?;
+    let user_id = &actor.user_id;
+    info!("User {} is reposting post {}", user_id, post_id);
+
+    ensure_active_user(&state, user_id).await?;
+
+    let post = match state.posts.find_by_id(&post_id).await? {
+        Some(post) if can_view_post(&state, &post, Viewer::actor(&actor)).await? => post,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                post_id
+            )))
+        }
+    };
+
+    // Reposting would widen the audience of restricted posts
+    if !can_view_post(&state, &post, Viewer::default()).await? {
+        return Err(AppError::InvalidInput(format!(
+            "Post {} is not public and cannot be reposted",
+            post_id
+        )));
+    }
+
+    let repost_doc = doc! {
+        "_id": repost_id(&post_id, user_id),
+        "post_id": &post_id,
+        "user_id": user_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    // Record the repost and bump the counter on the original together
+    let result = state.posts.repost(repost_doc.clone()).await;
+
+    match result {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new("repost", "repost", &repost_id(&post_id, user_id))
+                        .by(user_id)
+                        .after(&repost_doc),
+                )
+                .await;
+            send_notification(
+                &state,
+                notification_doc(
+                    &post_id,
+                    post.get_str("user_id").unwrap_or_default(),
+                    user_id,
+                    NotificationKind::Repost,
+                    Some(&post_id),
+                ),
+            )
+            .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: "Post reposted successfully".to_string(),
+                data: None,
+            }))
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "User {} has already reposted post {}",
+            user_id, post_id
+        ))),
+        Err(e) => {
+            error!("Error creating repost: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Undo Repost Handler
+#[instrument(skip_all)]
+pub async fn undo_repost_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    let actor = current_actor(&req, &state).await?;
+    let user_id = &actor.user_id;
+    info!("User {} is removing repost of post {}", user_id, post_id);
+
+    let id = repost_id(&post_id, user_id);
+    if !state.posts.undo_repost(&id, &post_id).await? {
+        return Err(AppError::NotFound(format!(
+            "User {} has not reposted post {}",
+            user_id, post_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("undo_repost", "repost", &id).by(user_id),
+        )
+        .await;
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "Repost removed successfully".to_string(),
+        data: None,
+    }))
+}
+
+// Deletes and restores are open to the owner of the content and to admins
+fn ensure_owner_or_admin(actor: &Actor, owner_id: &str) -> Result<(), AppError> {
+    if actor.user_id != owner_id && !actor.role.is_admin() {
+        return Err(AppError::Forbidden(format!(
+            "User {} cannot change content owned by {}",
+            actor.user_id, owner_id
+        )));
+    }
+    Ok(())
+}
+
+// Restoring is only possible while the deletion is inside the retention window
+fn ensure_restorable(state: &AppState, document: &Document, what: &str) -> Result<(), AppError> {
+    let deleted_at = document.get_str("deleted_at").unwrap_or_default();
+    if !within_restore_window(deleted_at, state.deletion_retention) {
+        return Err(AppError::Conflict(format!(
+            "The restore window for {} has passed",
+            what
+        )));
+    }
+    Ok(())
+}
+
+// The fields a soft delete sets, as recorded for a new deletion by `actor_id`
+fn deletion_fields(actor_id: &str) -> Document {
+    doc! { "deleted_at": Utc::now().to_rfc3339(), "deleted_by": actor_id }
+}
+
+// The deletion fields currently stored on a soft-deleted document
+fn stored_deletion_fields(document: &Document) -> Document {
+    doc! {
+        "deleted_at": document.get("deleted_at").cloned().unwrap_or(Bson::Null),
+        "deleted_by": document.get("deleted_by").cloned().unwrap_or(Bson::Null),
+    }
+}
+
+// Delete Post Handler
+#[instrument(skip_all)]
+pub async fn delete_post_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    info!("Deleting post with ID: {}", post_id);
+
+    let actor = current_actor(&req, &state).await?;
+    let post = match state.posts.find_by_id(&post_id).await? {
+        Some(post) if !post.contains_key("deleted_at") => post,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                post_id
+            )))
+        }
+    };
+    ensure_owner_or_admin(&actor, post.get_str("user_id").unwrap_or_default())?;
+
+    // Likes, comments and reposts stay in place until the purge job removes the post,
+    // so a restore brings the post back exactly as it was
+    let deletion = deletion_fields(&actor.user_id);
+    if !state.posts.set_deleted(&post, Some(&deletion)).await? {
+        return Err(AppError::NotFound(format!(
+            "Post with ID {} not found",
+            post_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("delete_post", "post", &post_id)
+                .by(&actor.user_id)
+                .after(&deletion),
+        )
+        .await;
+    info!("Post {} deleted by {}", post_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("Post {} deleted successfully", post_id),
+        data: None,
+    }))
+}
+
+// Restore Post Handler
+#[instrument(skip_all)]
+pub async fn restore_post_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    info!("Restoring post with ID: {}", post_id);
+
+    let actor = current_actor(&req, &state).await?;
+    let post = match state.posts.find_by_id(&post_id).await? {
+        Some(post) if post.contains_key("deleted_at") => post,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Deleted post with ID {} not found",
+                post_id
+            )))
+        }
+    };
+    let author_id = post.get_str("user_id").unwrap_or_default();
+    ensure_owner_or_admin(&actor, author_id)?;
+    ensure_restorable(&state, &post, &format!("post {}", post_id))?;
+    if account_state(&state, author_id).await? == AccountState::Deleted {
+        return Err(AppError::Conflict(format!(
+            "The account of user {} must be restored first",
+            author_id
+        )));
+    }
+
+    if !state.posts.set_deleted(&post, None).await? {
+        return Err(AppError::Conflict(format!(
+            "Post {} was changed by another request",
+            post_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("restore_post", "post", &post_id)
+                .by(&actor.user_id)
+                .before(&stored_deletion_fields(&post)),
+        )
+        .await;
+    info!("Post {} restored by {}", post_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("Post {} restored successfully", post_id),
+        data: None,
+    }))
+}
+
+// Delete Comment Handler
+#[instrument(skip_all)]
+pub async fn delete_comment_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let comment_id = path.into_inner();
+    info!("Deleting comment with ID: {}", comment_id);
+
+    let actor = current_actor(&req, &state).await?;
+    let comment = match state.comments.find_by_id(&comment_id).await? {
+        Some(comment) if !comment.contains_key("deleted_at") => comment,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Comment with ID {} not found",
+                comment_id
+            )))
+        }
+    };
+    ensure_owner_or_admin(&actor, comment.get_str("user_id").unwrap_or_default())?;
+
+    let deletion = deletion_fields(&actor.user_id);
+    if !state
+        .comments
+        .set_deleted(&comment_id, Some(&deletion))
+        .await?
+    {
+        return Err(AppError::NotFound(format!(
+            "Comment with ID {} not found",
+            comment_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("delete_comment", "comment", &comment_id)
+                .by(&actor.user_id)
+                .after(&deletion),
+        )
+        .await;
+    info!("Comment {} deleted by {}", comment_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("Comment {} deleted successfully", comment_id),
+        data: None,
+    }))
+}
+
+// Restore Comment Handler
+#[instrument(skip_all)]
+pub async fn restore_comment_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let comment_id = path.into_inner();
+    info!("Restoring comment with ID: {}", comment_id);
+
+    let actor = current_actor(&req, &state).await?;
+    let comment = match state.comments.find_by_id(&comment_id).await? {
+        Some(comment) if comment.contains_key("deleted_at") => comment,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Deleted comment with ID {} not found",
+                comment_id
+            )))
+        }
+    };
+    let author_id = comment.get_str("user_id").unwrap_or_default();
+    ensure_owner_or_admin(&actor, author_id)?;
+    ensure_restorable(&state, &comment, &format!("comment {}", comment_id))?;
+    if account_state(&state, author_id).await? == AccountState::Deleted {
+        return Err(AppError::Conflict(format!(
+            "The account of user {} must be restored first",
+            author_id
+        )));
+    }
+
+    if !state.comments.set_deleted(&comment_id, None).await? {
+        return Err(AppError::Conflict(format!(
+            "Comment {} was changed by another request",
+            comment_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("restore_comment", "comment", &comment_id)
+                .by(&actor.user_id)
+                .before(&stored_deletion_fields(&comment)),
+        )
+        .await;
+    info!("Comment {} restored by {}", comment_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("Comment {} restored successfully", comment_id),
+        data: None,
+    }))
+}
+
+// Delete User Handler
+#[instrument(skip_all)]
+pub async fn delete_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Deleting user with ID: {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    // The user's posts, comments and connections are hidden through the account and
+    // only removed once the purge job hard-deletes it
+    let deletion = deletion_fields(&actor.user_id);
+    if !state.users.set_deleted(&user_id, Some(&deletion)).await? {
+        return Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("delete_user", "user", &user_id)
+                .by(&actor.user_id)
+                .after(&deletion),
+        )
+        .await;
+    info!("User {} deleted by {}", user_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("User {} deleted successfully", user_id),
+        data: None,
+    }))
+}
+
+// Restore User Handler
+#[instrument(skip_all)]
+pub async fn restore_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Restoring user with ID: {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let user = match state.users.find_by_id(&user_id).await? {
+        Some(user) if user.contains_key("deleted_at") => user,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "Deleted user with ID {} not found",
+                user_id
+            )))
+        }
+    };
+    ensure_restorable(&state, &user, &format!("user {}", user_id))?;
+
+    if !state.users.set_deleted(&user_id, None).await? {
+        return Err(AppError::Conflict(format!(
+            "User {} was changed by another request",
+            user_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("restore_user", "user", &user_id)
+                .by(&actor.user_id)
+                .before(&stored_deletion_fields(&user)),
+        )
+        .await;
+    info!("User {} restored by {}", user_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("User {} restored successfully", user_id),
+        data: None,
+    }))
+}
+
+// Request Data Export Handler
+#[instrument(skip_all)]
+pub async fn request_export_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Requesting a data export for user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    if state.users.find_active(&user_id).await?.is_none() {
+        return Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        )));
+    }
+
+    // One export runs per user at a time; a finished one is replaced by the new request.
+    // Jobs still unfinished after an hour were interrupted by a restart and are replaced too.
+    let stale_before = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+    let active_statuses = [JobStatus::Pending.as_str(), JobStatus::Running.as_str()];
+    if let Some(active) = state
+        .exports
+        .find_one(doc! {
+            "user_id": &user_id,
+            "status": { "$in": &active_statuses[..] },
+            "created_at": { "$gt": stale_before },
+        })
+        .await?
+    {
+        return Err(AppError::Conflict(format!(
+            "Export {} for user {} is still in progress",
+            active.get_str("_id").unwrap_or_default(),
+            user_id
+        )));
+    }
+    state.exports.delete(doc! { "user_id": &user_id }).await?;
+
+    let export_id = Uuid::new_v4().to_string();
+    let export_doc = doc! {
+        "_id": &export_id,
+        "user_id": &user_id,
+        "requested_by": &actor.user_id,
+        "status": JobStatus::Pending.as_str(),
+        "created_at": Utc::now().to_rfc3339(),
+    };
+    state.exports.insert(export_doc.clone()).await?;
+    spawn_export(
+        &state.jobs,
+        state.exports.clone(),
+        export_id.clone(),
+        user_id.clone(),
+    );
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("request_export", "export", &export_id)
+                .by(&actor.user_id)
+                .after(&export_doc),
+        )
+        .await;
+    Ok(HttpResponse::Accepted().json(Response {
+        status: "success".to_string(),
+        message: format!("Export {} started for user {}", export_id, user_id),
+        data: Some(export_doc),
+    }))
+}
+
+// Load an export job on behalf of the user it belongs to, or an admin
+async fn find_export(
+    req: &HttpRequest,
+    state: &AppState,
+    user_id: &str,
+    export_id: &str,
+) -> Result<Document, AppError> {
+    let actor = current_actor(req, state).await?;
+    ensure_owner_or_admin(&actor, user_id)?;
+
+    state
+        .exports
+        .find_one(doc! { "_id": export_id, "user_id": user_id })
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Export with ID {} not found", export_id)))
+}
+
+// Get Data Export Status Handler
+#[instrument(skip_all)]
+pub async fn get_export_handler(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (user_id, export_id) = path.into_inner();
+    info!("Fetching export {} for user {}", export_id, user_id);
+
+    let export = find_export(&req, &state, &user_id, &export_id).await?;
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Export {} is {}",
+            export_id,
+            export.get_str("status").unwrap_or_default()
+        ),
+        data: Some(export),
+    }))
+}
+
+// Download Data Export Handler
+#[instrument(skip_all)]
+pub async fn download_export_handler(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (user_id, export_id) = path.into_inner();
+    info!("Downloading export {} for user {}", export_id, user_id);
+
+    let export = find_export(&req, &state, &user_id, &export_id).await?;
+    if export.get_str("status") != Ok(JobStatus::Completed.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Export {} is not ready for download",
+            export_id
+        )));
+    }
+
+    let archive = state.exports.load_archive(&export_id).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"export-{}.zip\"", user_id),
+        ))
+        .body(archive))
+}
+
+// Populate Database Handler
+#[instrument(skip_all)]
+pub async fn populate_database_handler(
+    query: web::Query<SeedOptions>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Starting a seed job");
+    validate_seed_options(&query)?;
+
+    // Seeding wipes every collection, so only one job may run at a time. A job that has
+    // not reported progress for ten minutes was interrupted by a restart and is ignored.
+    let options = query.into_inner();
+    let job_id = Uuid::new_v4().to_string();
+    let stale_before = (Utc::now() - chrono::Duration::minutes(10)).to_rfc3339();
+    if let Some(active) = state.seeding.lock(&job_id, &stale_before).await? {
+        return Err(AppError::Conflict(format!(
+            "Seed job {} is still in progress",
+            active
+        )));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let job_doc = doc! {
+        "_id": &job_id,
+        "status": JobStatus::Pending.as_str(),
+        "options": {
+            "users": options.users as i64,
+            "posts": options.posts as i64,
+            "comments": options.comments as i64,
+            "likes": options.likes as i64,
+            "follows": options.follows as i64,
+            "seed": options.seed.map(|seed| seed.to_string()),
+            "anchor": options.anchor.as_deref(),
+            "batch_size": options.batch_size as i64,
+        },
+        "created_at": &now,
+        "updated_at": &now,
+    };
+    if let Err(e) = state.seeding.insert_job(job_doc.clone()).await {
+        state.seeding.unlock(&job_id).await?;
+        return Err(AppError::from(e));
+    }
+    spawn_seed_job(&state.jobs, state.seeding.clone(), job_id.clone(), options);
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("populate_database", "database", &state.database.name())
+                .after(job_doc.get_document("options").unwrap_or(&Document::new())),
+        )
+        .await;
+    Ok(HttpResponse::Accepted().json(Response {
+        status: "success".to_string(),
+        message: format!("Seed job {} started", job_id),
+        data: Some(job_doc),
+    }))
+}
+
+// Get Seed Job Handler
+#[instrument(skip_all)]
+pub async fn get_seed_job_handler(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let job_id = path.into_inner();
+    info!("Fetching seed job {}", job_id);
+
+    let job = state
+        .seeding
+        .find_job(doc! { "_id": &job_id })
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Seed job with ID {} not found", job_id)))?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Seed job {} is {}",
+            job_id,
+            job.get_str("status").unwrap_or_default()
+        ),
+        data: Some(job),
+    }))
+}
+
+// Clean Database Handler
+#[instrument(skip_all)]
+pub async fn clean_database_handler(
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Cleaning all collections in the database");
+
+    // The audit log is not among the cleaned collections, so the wipe itself stays on record
+    let removed = match state.seeding.clear_data().await {
+        Ok(removed) => removed,
+        Err(e) => {
+            error!("Error cleaning the database: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+    info!("Successfully cleaned {} collections", removed.len());
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("clean_database", "database", &state.database.name()).before(&removed),
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "All collections cleaned successfully".to_string(),
+        data: None,
+    }))
+}
+
+// Get All Posts Handler
+#[instrument(skip_all)]
+pub async fn get_posts_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Fetching all posts");
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    let filter = viewer_posts_filter(&state, viewer).await?;
+    let posts = match state.posts.list(filter, ListOptions::default()).await {
+        Ok(posts) => posts,
+        Err(e) => {
+            error!("Error fetching posts: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!("Successfully fetched {} posts", posts.len());
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} posts", posts.len()),
+        data: Some(posts),
+    }))
+}
+
+// Read a numeric counter regardless of whether it was stored as i32 or i64
+fn get_count(document: &Document, key: &str) -> i32 {
+    match document.get(key) {
+        Some(Bson::Int32(value)) => *value,
+        Some(Bson::Int64(value)) => *value as i32,
+        Some(Bson::Double(value)) => *value as i32,
+        _ => 0,
+    }
+}
+
+// Format a stored RFC 3339 timestamp relative to now, e.g. "5 minutes ago"
+fn human_time(created_at: &str) -> String {
+    let time = match parse_timestamp(created_at) {
+        Some(time) => time,
+        None => return String::new(),
+    };
+
+    let elapsed = Utc::now().signed_duration_since(time);
+    let (amount, unit) = if elapsed.num_seconds() < 60 {
+        return "just now".to_string();
+    } else if elapsed.num_minutes() < 60 {
+        (elapsed.num_minutes(), "minute")
+    } else if elapsed.num_hours() < 24 {
+        (elapsed.num_hours(), "hour")
+    } else if elapsed.num_days() < 30 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_days() < 365 {
+        (elapsed.num_days() / 30, "month")
+    } else {
+        (elapsed.num_days() / 365, "year")
+    };
+
+    if amount == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", amount, unit)
+    }
+}
+
+fn poll_is_closed(poll: &Document) -> bool {
+    poll.get_str("closes_at")
+        .ok()
+        .and_then(parse_timestamp)
+        .is_some_and(|closes_at| closes_at <= Utc::now())
+}
+
+// Build poll results for a post, flagging the options the viewer voted for
+async fn build_poll_results(
+    state: &AppState,
+    post_id: &str,
+    poll: &Document,
+    viewer_id: Option<&str>,
+) -> Result<PollResults, AppError> {
+    let mut viewer_choices = Vec::new();
+    if let Some(viewer_id) = viewer_id {
+        if let Some(vote) = state
+            .posts
+            .find_vote(&poll_vote_id(post_id, viewer_id))
+            .await?
+        {
+            if let Ok(indexes) = vote.get_array("option_indexes") {
+                viewer_choices = indexes.iter().filter_map(|i| i.as_i32()).collect();
+            }
+        }
+    }
+
+    let options = poll
+        .get_array("options")
+        .map(|options| {
+            options
+                .iter()
+                .enumerate()
+                .filter_map(|(index, option)| {
+                    let option = option.as_document()?;
+                    Some(PollOptionResult {
+                        text: option.get_str("text").unwrap_or_default().to_string(),
+                        vote_count: get_count(option, "vote_count"),
+                        voted: viewer_choices.contains(&(index as i32)),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(PollResults {
+        options,
+        multiple_choice: poll.get_bool("multiple_choice").unwrap_or(false),
+        closes_at: poll.get_str("closes_at").ok().map(str::to_string),
+        is_closed: poll_is_closed(poll),
+        total_votes: get_count(poll, "total_votes"),
+        has_voted: !viewer_choices.is_empty(),
+    })
+}
+
+// Build the embedded summary of a quoted post, tolerating a deleted or hidden original
+async fn build_quoted_post(
+    state: &AppState,
+    quoted_post_id: &str,
+    viewer: Viewer<'_>,
+) -> Result<QuotedPost, AppError> {
+    let quoted = match state.posts.find_by_id(quoted_post_id).await? {
+        Some(quoted) if can_view_post(state, &quoted, viewer).await? => quoted,
+        _ => {
+            return Ok(QuotedPost {
+                id: quoted_post_id.to_string(),
+                available: false,
+                user_id: None,
+                username: None,
+                content: None,
+                post_type: None,
+                created_at: None,
+            })
+        }
+    };
+
+    let user_id = quoted.get_str("user_id").unwrap_or_default().to_string();
+    let username = state
+        .users
+        .find_by_id(&user_id)
+        .await?
+        .and_then(|author| author.get_str("username").ok().map(str::to_string));
+
+    Ok(QuotedPost {
+        id: quoted_post_id.to_string(),
+        available: true,
+        user_id: Some(user_id),
+        username,
+        content: quoted.get_str("content").ok().map(str::to_string),
+        post_type: quoted.get_str("post_type").ok().map(PostType::parse),
+        created_at: quoted.get_str("created_at").ok().map(str::to_string),
+    })
+}
+
+// Build the detailed view of a post, including author, counts and viewer interactions
+async fn build_post_details(
+    state: &AppState,
+    post: &Document,
+    viewer: Viewer<'_>,
+) -> Result<PostDetails, AppError> {
+    let viewer_id = viewer.user_id;
+    let post_id = post.get_str("_id").unwrap_or_default().to_string();
+    let user_id = post.get_str("user_id").unwrap_or_default().to_string();
+    let created_at = post.get_str("created_at").unwrap_or_default().to_string();
+
+    let author = state.users.find_by_id(&user_id).await?;
+    let comment_count = state.comments.count_for_post(&post_id).await? as i32;
+
+    let has_liked = match viewer_id {
+        Some(viewer_id) => state.likes.has_liked(&post_id, viewer_id).await?,
+        None => false,
+    };
+
+    let has_reposted = match viewer_id {
+        Some(viewer_id) => {
+            state
+                .posts
+                .has_reposted(&repost_id(&post_id, viewer_id))
+                .await?
+        }
+        None => false,
+    };
+
+    let is_bookmarked = match viewer_id {
+        Some(viewer_id) => state.bookmarks.is_bookmarked(&post_id, viewer_id).await?,
+        None => false,
+    };
+
+    let poll = match post.get_document("poll") {
+        Ok(poll) => Some(build_poll_results(state, &post_id, poll, viewer_id).await?),
+        Err(_) => None,
+    };
+
+    let quoted_post = match post.get_str("quoted_post_id") {
+        Ok(quoted_post_id) => Some(build_quoted_post(state, quoted_post_id, viewer).await?),
+        Err(_) => None,
+    };
+
+    let media_urls = post
+        .get_array("media_urls")
+        .map(|urls| {
+            urls.iter()
+                .filter_map(|url| url.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(PostDetails {
+        id: post_id,
+        user_id,
+        username: author
+            .as_ref()
+            .and_then(|author| author.get_str("username").ok())
+            .unwrap_or_default()
+            .to_string(),
+        profile_picture_url: author
+            .as_ref()
+            .and_then(|author| author.get_str("profile_picture_url").ok())
+            .map(str::to_string),
+        content: post.get_str("content").unwrap_or_default().to_string(),
+        media_urls,
+        post_type: PostType::parse(post.get_str("post_type").unwrap_or_default()),
+        status: PostStatus::parse(post.get_str("status").unwrap_or_default()),
+        visibility: PostVisibility::parse(post.get_str("visibility").unwrap_or_default()),
+        mentions: post
+            .get_array("mentions")
+            .map(|mentions| {
+                mentions
+                    .iter()
+                    .filter_map(|m| m.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        publish_at: post.get_str("publish_at").ok().map(str::to_string),
+        human_time: human_time(&created_at),
+        created_at,
+        like_count: get_count(post, "like_count"),
+        comment_count,
+        has_liked,
+        repost_count: get_count(post, "repost_count"),
+        quote_count: get_count(post, "quote_count"),
+        has_reposted,
+        is_bookmarked,
+        poll,
+        quoted_post,
+    })
+}
+
+// Get Post by ID Handler
+#[instrument(skip_all)]
+pub async fn get_post_by_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    info!("Fetching post with ID: {}", post_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    match state.posts.find_by_id(&post_id).await {
+        Ok(Some(post)) if !post.contains_key("deleted_at") => {
+            // Authors can always see their own posts, including drafts
+            let is_author = viewer.user_id == post.get_str("user_id").ok();
+            if !is_author && !can_view_post(&state, &post, viewer).await? {
+                return Err(AppError::NotFound(format!(
+                    "Post with ID {} not found",
+                    post_id
+                )));
+            }
+
+            let details = build_post_details(&state, &post, viewer).await?;
+            info!("Successfully fetched post with ID: {}", post_id);
+            Ok(HttpResponse::Ok().json(Response {
+                status: "success".to_string(),
+                message: "Post fetched successfully".to_string(),
+                data: Some(details),
+            }))
+        }
+        Ok(_) => {
+            error!("Post with ID {} not found", post_id);
+            Err(AppError::NotFound(format!(
+                "Post with ID {} not found",
+                post_id
+            )))
+        }
+        Err(e) => {
+            error!("Error fetching post: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Create Token Handler: exchange a user's email and password hash for the token that
+// authenticates their requests
+#[instrument(skip_all)]
+pub async fn create_token_handler(
+    credentials: web::Json<Credentials>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let invalid = || AppError::Unauthorized("Invalid email or password".to_string());
+    let user = state
+        .users
+        .find_by_email(&credentials.email)
+        .await?
+        .ok_or_else(invalid)?;
+    let stored_hash = user.get_str("password_hash").unwrap_or_default();
+    if !bool::from(
+        stored_hash
+            .as_bytes()
+            .ct_eq(credentials.password_hash.as_bytes()),
+    ) {
+        return Err(invalid());
+    }
+
+    let user_id = user.get_str("_id").unwrap_or_default().to_string();
+    info!("Issued a token for user {}", user_id);
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Token issued for user {}", user_id),
+        data: Some(AuthToken {
+            token: issue_token(&state.auth_secret, &user_id),
+            user_id,
+        }),
+    }))
+}
+
+// Fields of a user document that are never shown to other callers
+const PRIVATE_USER_FIELDS: [&str; 2] = ["password_hash", "role"];
+
+// The user as the user routes answer with it
+fn public_user(mut user: Document) -> Document {
+    for field in PRIVATE_USER_FIELDS {
+        user.remove(field);
+    }
+    user
+}
+
+// Get All Users Handler
+#[instrument(skip_all)]
+pub async fn get_users_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Fetching all users");
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    let context = ViewerContext::load(&state, viewer).await?;
+    let users = match state.users.list(context.users_filter()).await {
+        Ok(users) => users.into_iter().map(public_user).collect::<Vec<_>>(),
+        Err(e) => {
+            error!("Error fetching users: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!("Successfully fetched {} users", users.len());
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} users", users.len()),
+        data: Some(users),
+    }))
+}
+
+// Get User by ID Handler
+#[instrument(skip_all)]
+pub async fn get_user_by_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching user with ID: {}", user_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    match state.users.find_active(&user_id).await {
+        Ok(Some(user)) => {
+            if user.get_bool("suspended").unwrap_or(false) && !viewer.is_moderator {
+                return Err(AppError::NotFound(format!(
+                    "User with ID {} not found",
+                    user_id
+                )));
+            }
+            if let Some(viewer_id) = viewer.user_id {
+                if state
+                    .follows
+                    .is_blocked_between(viewer_id, &user_id)
+                    .await?
+                {
+                    return Err(AppError::NotFound(format!(
+                        "User with ID {} not found",
+                        user_id
+                    )));
+                }
+            }
+
+            info!("Successfully fetched user with ID: {}", user_id);
+            Ok(HttpResponse::Ok().json(Response {
+                status: "success".to_string(),
+                message: "User fetched successfully".to_string(),
+                data: Some(public_user(user)),
+            }))
+        }
+        Ok(None) => {
+            error!("User with ID {} not found", user_id);
+            Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                user_id
+            )))
+        }
+        Err(e) => {
+            error!("Error fetching user: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Comments matching `comment_filter`, minus those the viewer cannot see or whose post
+// they cannot see
+async fn visible_comments(
+    state: &AppState,
+    comment_filter: Document,
+    viewer: Viewer<'_>,
+) -> mongodb::error::Result<Vec<Document>> {
+    let context = ViewerContext::load(state, viewer).await?;
+    state
+        .comments
+        .list_with_post(
+            doc! { "$and": [comment_filter, context.comments_filter()] },
+            context.posts_filter("post."),
+        )
+        .await
+}
+
+// Get All Comments Handler
+#[instrument(skip_all)]
+pub async fn get_comments_handler(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    info!("Fetching all comments");
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    let comments = match visible_comments(&state, doc! {}, viewer).await {
+        Ok(comments) => comments,
+        Err(e) => {
+            error!("Error fetching comments: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!("Successfully fetched {} comments", comments.len());
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} comments", comments.len()),
+        data: Some(comments),
+    }))
+}
+
+// Get Comment by ID Handler
+#[instrument(skip_all)]
+pub async fn get_comment_by_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let comment_id = path.into_inner();
+    info!("Fetching comment with ID: {}", comment_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    match state.comments.find_by_id(&comment_id).await {
+        Ok(Some(comment)) => {
+            if !can_view_comment(&state, &comment, viewer).await? {
+                return Err(AppError::NotFound(format!(
+                    "Comment with ID {} not found",
+                    comment_id
+                )));
+            }
+
+            info!("Successfully fetched comment with ID: {}", comment_id);
+            Ok(HttpResponse::Ok().json(Response {
+                status: "success".to_string(),
+                message: "Comment fetched successfully".to_string(),
+                data: Some(comment),
+            }))
+        }
+        Ok(None) => {
+            error!("Comment with ID {} not found", comment_id);
+            Err(AppError::NotFound(format!(
+                "Comment with ID {} not found",
+                comment_id
+            )))
+        }
+        Err(e) => {
+            error!("Error fetching comment: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Get Comments by Post ID Handler
+#[instrument(skip_all)]
+pub async fn get_comments_by_post_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let post_id = path.into_inner();
+    info!("Fetching comments for post with ID: {}", post_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    let visible = match state.posts.find_by_id(&post_id).await? {
+        Some(post) => can_view_post(&state, &post, viewer).await?,
+        None => false,
+    };
+    if !visible {
+        return Err(AppError::NotFound(format!(
+            "Post with ID {} not found",
+            post_id
+        )));
+    }
+
+    let context = ViewerContext::load(&state, viewer).await?;
+    let mut filter = context.comments_filter();
+    filter.insert("post_id", &post_id);
+
+    let comments = match state.comments.list(filter).await {
+        Ok(comments) => comments,
+        Err(e) => {
+            error!("Error fetching comments for post: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!(
+        "Successfully fetched {} comments for post {}",
+        comments.len(),
+        post_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Successfully fetched {} comments for post {}",
+            comments.len(),
+            post_id
+        ),
+        data: Some(comments),
+    }))
+}
+
+// Get Comments by User ID Handler
+#[instrument(skip_all)]
+pub async fn get_comments_by_user_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching comments by user with ID: {}", user_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    let comments = match visible_comments(&state, doc! { "user_id": &user_id }, viewer).await {
+        Ok(comments) => comments,
+        Err(e) => {
+            error!("Error fetching comments for user: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!(
+        "Successfully fetched {} comments by user {}",
+        comments.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Successfully fetched {} comments by user {}",
+            comments.len(),
+            user_id
+        ),
+        data: Some(comments),
+    }))
+}
+
+// Private accounts hide their connections from anyone who does not follow them,
+// and blocked users cannot see each other's connections at all
+async fn ensure_account_visible(
+    state: &AppState,
+    user_id: &str,
+    viewer: Viewer<'_>,
+) -> Result<(), AppError> {
+    if let Some(user) = state.users.find_by_id(user_id).await? {
+        if !can_view_account(state, &user, viewer).await? {
+            return Err(AppError::Forbidden(format!(
+                "Connections of user {} are not visible",
+                user_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Get Following Users Handler
+#[instrument(skip_all)]
+pub async fn get_following_users_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching users followed by user with ID: {}", user_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    ensure_account_visible(&state, &user_id, viewer).await?;
+    let context = ViewerContext::load(&state, viewer).await?;
+
+    let filter =
+        doc! { "follower_id": &user_id, "following_id": { "$nin": context.hidden_authors() } };
+
+    let follows = match state.follows.list(filter).await {
+        Ok(follows) => follows,
+        Err(e) => {
+            error!("Error fetching followed users: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    let mut following_users = Vec::new();
+    for follow in follows {
+        // Fetch the details of each followed user
+        if let Ok(following_id) = follow.get_str("following_id") {
+            if let Ok(Some(user_doc)) = state.users.find_by_id(following_id).await {
+                if context.shows_account(&user_doc) {
+                    following_users.push(public_user(user_doc));
+                }
+            }
+        }
+    }
+
+    info!(
+        "Successfully fetched {} followed users for user {}",
+        following_users.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Successfully fetched {} followed users",
+            following_users.len()
+        ),
+        data: Some(following_users),
+    }))
+}
+
+// Get Followers Users Handler
+#[instrument(skip_all)]
+pub async fn get_followers_users_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching followers for user with ID: {}", user_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    ensure_account_visible(&state, &user_id, viewer).await?;
+    let context = ViewerContext::load(&state, viewer).await?;
+
+    let filter =
+        doc! { "following_id": &user_id, "follower_id": { "$nin": context.hidden_authors() } };
+
+    let follows = match state.follows.list(filter).await {
+        Ok(follows) => follows,
+        Err(e) => {
+            error!("Error fetching followers: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    let mut followers = Vec::new();
+    for follow in follows {
+        // Fetch the details of each follower
+        if let Ok(follower_id) = follow.get_str("follower_id") {
+            if let Ok(Some(user_doc)) = state.users.find_by_id(follower_id).await {
+                if context.shows_account(&user_doc) {
+                    followers.push(public_user(user_doc));
+                }
+            }
+        }
+    }
+
+    info!(
+        "Successfully fetched {} followers for user {}",
+        followers.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} followers", followers.len()),
+        data: Some(followers),
+    }))
+}
+
+// Get Posts by User ID Handler
+#[instrument(skip_all)]
+pub async fn get_posts_by_user_id_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching posts for user with ID: {}", user_id);
+
+    let caller = optional_actor(&req, &state).await?;
+    let viewer = Viewer::caller(caller.as_ref());
+    // First verify that the user exists
+    match state.users.find_active(&user_id).await {
+        Ok(None) => {
+            error!("User with ID {} not found", user_id);
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                user_id
+            )));
+        }
+        Err(e) => {
+            error!("Error verifying user existence: {}", e);
+            return Err(AppError::from(e));
+        }
+        _ => {} // User exists, continue
+    }
+
+    let visible = viewer_posts_filter(&state, viewer).await?;
+    let filter = doc! { "$and": [visible, { "user_id": &user_id }] };
+
+    let posts = match state.posts.list(filter, ListOptions::default()).await {
+        Ok(posts) => posts,
+        Err(e) => {
+            error!("Error fetching posts for user: {}", e);
+            return Err(AppError::from(e));
+        }
+    };
+
+    info!(
+        "Successfully fetched {} posts for user {}",
+        posts.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!(
+            "Successfully fetched {} posts for user {}",
+            posts.len(),
+            user_id
+        ),
+        data: Some(posts),
+    }))
+}
+
+// Get Timeline Handler
+#[instrument(skip_all)]
+pub async fn get_timeline_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<PaginationQuery>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    info!(
+        "Fetching timeline for user {} (page {}, limit {})",
+        user_id, page, limit
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    if state.users.find_active(&user_id).await?.is_none() {
+        return Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        )));
+    }
+
+    // The timeline covers the user and everyone they follow, minus blocked and muted users.
+    // Reposts of deleted, hidden or muted posts are skipped rather than shown empty.
+    let viewer = ViewerContext::load(&state, Viewer::user(&user_id)).await?;
+    let mut author_ids = viewer.following.clone();
+    author_ids.push(user_id.clone());
+
+    // Merge original posts and reposts into a single activity stream
+    let entries = state
+        .posts
+        .timeline(
+            &author_ids,
+            &viewer.hidden_from_feed(),
+            viewer.feed_filter("post."),
+            ListOptions::default().page(page, limit),
+        )
+        .await?;
+
+    let mut items = Vec::new();
+    for entry in entries {
+        let post = match entry.get_document("post") {
+            Ok(post) => post,
+            Err(_) => continue,
+        };
+        let reposted_by = entry
+            .get_str("reposted_by")
+            .ok()
+            .map(|reposter_id| RepostAttribution {
+                user_id: reposter_id.to_string(),
+                username: entry
+                    .get_str("reposter_username")
+                    .unwrap_or_default()
+                    .to_string(),
+                reposted_at: entry.get_str("activity_at").unwrap_or_default().to_string(),
+            });
+
+        items.push(TimelineItem {
+            post: build_post_details(&state, post, Viewer::user(&user_id)).await?,
+            reposted_by,
+        });
+    }
+
+    info!(
+        "Successfully fetched {} timeline items for user {}",
+        items.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} timeline items", items.len()),
+        data: Some(items),
+    }))
+}
+
+// Bookmarks are keyed by user, post and collection so a post is saved once per collection
+fn bookmark_id(user_id: &str, post_id: &str, collection: &str) -> String {
+    format!("{}:{}:{}", user_id, post_id, collection)
+}
+
+// Add Bookmark Handler
+#[instrument(skip_all)]
+pub async fn add_bookmark_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    bookmark: web::Json<Bookmark>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    let collection = bookmark_collection_name(bookmark.collection.as_deref())?;
+    info!(
+        "User {} is bookmarking post {} in collection {}",
+        user_id, bookmark.post_id, collection
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    if bookmark.post_id.is_empty() {
+        return Err(AppError::ValidationError {
+            field: "post_id".to_string(),
+            message: "Post ID cannot be empty".to_string(),
+        });
+    }
+
+    if state.users.find_active(&user_id).await?.is_none() {
+        return Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        )));
+    }
+
+    let visible = match state.posts.find_by_id(&bookmark.post_id).await? {
+        Some(post) => can_view_post(&state, &post, Viewer::user(&user_id)).await?,
+        None => false,
+    };
+    if !visible {
+        return Err(AppError::NotFound(format!(
+            "Post with ID {} not found",
+            bookmark.post_id
+        )));
+    }
+
+    let bookmark_doc = doc! {
+        "_id": bookmark_id(&user_id, &bookmark.post_id, &collection),
+        "user_id": &user_id,
+        "post_id": &bookmark.post_id,
+        "collection": &collection,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.bookmarks.insert(bookmark_doc.clone()).await {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new(
+                        "add_bookmark",
+                        "bookmark",
+                        &bookmark_id(&user_id, &bookmark.post_id, &collection),
+                    )
+                    .by(&actor.user_id)
+                    .after(&bookmark_doc),
+                )
+                .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: format!("Post saved to {}", collection),
+                data: None,
+            }))
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "Post {} is already saved in {}",
+            bookmark.post_id, collection
+        ))),
+        Err(e) => {
+            error!("Error creating bookmark: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Remove Bookmark Handler
+#[instrument(skip_all)]
+pub async fn remove_bookmark_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    bookmark: web::Json<Bookmark>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!(
+        "User {} is removing bookmark of post {}",
+        user_id, bookmark.post_id
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    // Without a collection name the post is removed from every collection
+    let mut filter = doc! { "user_id": &user_id, "post_id": &bookmark.post_id };
+    if bookmark.collection.is_some() {
+        let collection = bookmark_collection_name(bookmark.collection.as_deref())?;
+        filter.insert("collection", collection);
+    }
+
+    if state.bookmarks.remove(filter.clone()).await? == 0 {
+        return Err(AppError::NotFound(format!(
+            "Post {} is not bookmarked by user {}",
+            bookmark.post_id, user_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("remove_bookmark", "post", &bookmark.post_id)
+                .by(&actor.user_id)
+                .before(&filter),
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: "Bookmark removed successfully".to_string(),
+        data: None,
+    }))
+}
+
+// Get Bookmarks Handler
+#[instrument(skip_all)]
+pub async fn get_bookmarks_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<BookmarkListQuery>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    info!(
+        "Fetching bookmarks for user {} (page {}, limit {})",
+        user_id, page, limit
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let mut filter = doc! { "user_id": &user_id };
+    if query.collection.is_some() {
+        let collection = bookmark_collection_name(query.collection.as_deref())?;
+        filter.insert("collection", collection);
+    }
+
+    // Join against posts before paginating so deleted or hidden posts do not leave gaps
+    let context = ViewerContext::load(&state, Viewer::actor(&actor)).await?;
+    let bookmarked = state
+        .bookmarks
+        .list_posts(
+            filter,
+            context.posts_filter("post."),
+            ListOptions::default().page(page, limit),
+        )
+        .await?;
+
+    let mut posts = Vec::new();
+    for post in &bookmarked {
+        posts.push(build_post_details(&state, post, Viewer::actor(&actor)).await?);
+    }
+
+    info!(
+        "Successfully fetched {} bookmarked posts for user {}",
+        posts.len(),
+        user_id
+    );
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} bookmarked posts", posts.len()),
+        data: Some(posts),
+    }))
+}
+
+// Get Bookmark Collections Handler
+#[instrument(skip_all)]
+pub async fn get_bookmark_collections_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching bookmark collections for user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let collections: Vec<BookmarkCollection> = state
+        .bookmarks
+        .collections(&user_id)
+        .await?
+        .iter()
+        .map(|entry| BookmarkCollection {
+            name: entry.get_str("_id").unwrap_or_default().to_string(),
+            count: get_count(entry, "count"),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} collections", collections.len()),
+        data: Some(collections),
+    }))
+}
+
+// Get Drafts Handler
+#[instrument(skip_all)]
+pub async fn get_drafts_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching drafts and scheduled posts for user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let filter = doc! {
+        "user_id": &user_id,
+        "status": { "$in": [PostStatus::Draft.as_str(), PostStatus::Scheduled.as_str()] },
+        "deleted_at": { "$exists": false },
+    };
+    let drafts = state
+        .posts
+        .list(filter, ListOptions::sorted(doc! { "created_at": -1 }))
+        .await?;
+
+    let mut posts = Vec::new();
+    for post in &drafts {
+        posts.push(build_post_details(&state, post, Viewer::user(&user_id)).await?);
+    }
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} unpublished posts", posts.len()),
+        data: Some(posts),
+    }))
+}
+
+// Get Notifications Handler
+#[instrument(skip_all)]
+pub async fn get_notifications_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<PaginationQuery>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    info!(
+        "Fetching notifications for user {} (page {}, limit {})",
+        user_id, page, limit
+    );
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    // Notifications from muted, blocked, suspended or deleted users are kept but not shown
+    let viewer = ViewerContext::load(&state, Viewer::user(&user_id)).await?;
+    let notifications = state
+        .notifications
+        .list(
+            doc! {
+                "user_id": &user_id,
+                "actor_id": { "$nin": viewer.hidden_from_feed() },
+                "author_suspended": { "$ne": true },
+                "author_deleted": { "$ne": true },
+            },
+            ListOptions::sorted(doc! { "created_at": -1 }).page(page, limit),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} notifications", notifications.len()),
+        data: Some(notifications),
+    }))
+}
+
+// Blocks and mutes are keyed by both users so each pair is recorded once
+fn relationship_id(actor_id: &str, target_id: &str) -> String {
+    format!("{}:{}", actor_id, target_id)
+}
+
+async fn ensure_relationship_target(
+    state: &AppState,
+    actor_id: &str,
+    target_id: &str,
+) -> Result<(), AppError> {
+    if actor_id == target_id {
+        return Err(AppError::InvalidInput(
+            "Users cannot block or mute themselves".to_string(),
+        ));
+    }
+
+    for user_id in [actor_id, target_id] {
+        if state.users.find_active(user_id).await?.is_none() {
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                user_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Block User Handler
+#[instrument(skip_all)]
+pub async fn block_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let actor = current_actor(&req, &state).await?;
+    let blocked_id = path.into_inner();
+    info!("User {} is blocking user {}", actor.user_id, blocked_id);
+
+    ensure_relationship_target(&state, &actor.user_id, &blocked_id).await?;
+
+    let block_doc = doc! {
+        "_id": relationship_id(&actor.user_id, &blocked_id),
+        "blocker_id": &actor.user_id,
+        "blocked_id": &blocked_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    // Record the block and cut every follow edge and pending request between the two
+    let result = state.blocks.block(block_doc.clone()).await;
+
+    match result {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new(
+                        "block_user",
+                        "block",
+                        &relationship_id(&actor.user_id, &blocked_id),
+                    )
+                    .by(&actor.user_id)
+                    .after(&block_doc),
+                )
+                .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: format!("User {} blocked", blocked_id),
+                data: None,
+            }))
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "User {} has already blocked user {}",
+            actor.user_id, blocked_id
+        ))),
+        Err(e) => {
+            error!("Error blocking user: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Unblock User Handler
+#[instrument(skip_all)]
+pub async fn unblock_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let actor = current_actor(&req, &state).await?;
+    let blocked_id = path.into_inner();
+    info!("User {} is unblocking user {}", actor.user_id, blocked_id);
+
+    let relationship = relationship_id(&actor.user_id, &blocked_id);
+    let removed = match state.blocks.unblock(&relationship).await? {
+        Some(removed) => removed,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User {} has not blocked user {}",
+                actor.user_id, blocked_id
+            )))
+        }
+    };
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("unblock_user", "block", &relationship)
+                .by(&actor.user_id)
+                .before(&removed),
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("User {} unblocked", blocked_id),
+        data: None,
+    }))
+}
+
+// Mute User Handler
+#[instrument(skip_all)]
+pub async fn mute_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let actor = current_actor(&req, &state).await?;
+    let muted_id = path.into_inner();
+    info!("User {} is muting user {}", actor.user_id, muted_id);
+
+    ensure_relationship_target(&state, &actor.user_id, &muted_id).await?;
+
+    let mute_doc = doc! {
+        "_id": relationship_id(&actor.user_id, &muted_id),
+        "muter_id": &actor.user_id,
+        "muted_id": &muted_id,
+        "created_at": Utc::now().to_rfc3339(),
+    };
+
+    match state.blocks.mute(mute_doc.clone()).await {
+        Ok(()) => {
+            audit
+                .record(
+                    state.audit_log.as_ref(),
+                    AuditEvent::new(
+                        "mute_user",
+                        "mute",
+                        &relationship_id(&actor.user_id, &muted_id),
+                    )
+                    .by(&actor.user_id)
+                    .after(&mute_doc),
+                )
+                .await;
+            Ok(HttpResponse::Created().json(Response::<()> {
+                status: "success".to_string(),
+                message: format!("User {} muted", muted_id),
+                data: None,
+            }))
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AppError::Conflict(format!(
+            "User {} has already muted user {}",
+            actor.user_id, muted_id
+        ))),
+        Err(e) => {
+            error!("Error muting user: {}", e);
+            Err(AppError::from(e))
+        }
+    }
+}
+
+// Unmute User Handler
+#[instrument(skip_all)]
+pub async fn unmute_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let actor = current_actor(&req, &state).await?;
+    let muted_id = path.into_inner();
+    info!("User {} is unmuting user {}", actor.user_id, muted_id);
+
+    let relationship = relationship_id(&actor.user_id, &muted_id);
+    let removed = match state.blocks.unmute(&relationship).await? {
+        Some(removed) => removed,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User {} has not muted user {}",
+                actor.user_id, muted_id
+            )))
+        }
+    };
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("unmute_user", "mute", &relationship)
+                .by(&actor.user_id)
+                .before(&removed),
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("User {} unmuted", muted_id),
+        data: None,
+    }))
+}
+
+// Get Blocked Users Handler
+#[instrument(skip_all)]
+pub async fn get_blocked_users_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching users blocked by user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let blocks = state
+        .blocks
+        .list_blocks(
+            doc! { "blocker_id": &user_id },
+            ListOptions::sorted(doc! { "created_at": -1 }),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} blocked users", blocks.len()),
+        data: Some(blocks),
+    }))
+}
+
+// Get Muted Users Handler
+#[instrument(skip_all)]
+pub async fn get_muted_users_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!("Fetching users muted by user {}", user_id);
+
+    let actor = current_actor(&req, &state).await?;
+    ensure_owner_or_admin(&actor, &user_id)?;
+
+    let mutes = state
+        .blocks
+        .list_mutes(
+            doc! { "muter_id": &user_id },
+            ListOptions::sorted(doc! { "created_at": -1 }),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} muted users", mutes.len()),
+        data: Some(mutes),
+    }))
+}
+
+// Every step a report goes through is appended to the moderation log
+fn moderation_log_doc(
+    report: &Document,
+    actor_id: &str,
+    action: &str,
+    note: Option<&str>,
+) -> Document {
+    doc! {
+        "_id": Uuid::new_v4().to_string(),
+        "report_id": report.get_str("_id").unwrap_or_default(),
+        "actor_id": actor_id,
+        "action": action,
+        "target_type": report.get_str("target_type").unwrap_or_default(),
+        "target_id": report.get_str("target_id").unwrap_or_default(),
+        "note": note,
+        "created_at": Utc::now().to_rfc3339(),
+    }
+}
+
+// Report Content Handler
+#[instrument(skip_all)]
+pub async fn report_content_handler(
+    req: HttpRequest,
+    report: web::Json<Report>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let actor = current_actor(&req, &state).await?;
+    let reporter_id = &actor.user_id;
+    info!(
+        "User {} is reporting {} {}",
+        reporter_id,
+        report.target_type.as_str(),
+        report.target_id
+    );
+
+    let reason = validate_report_reason(&report.reason)?;
+
+    if state.users.find_active(reporter_id).await?.is_none() {
+        return Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            reporter_id
+        )));
+    }
+
+    // Reporters can only report what they can see
+    let target = match report.target_type {
+        ReportTargetType::Post => state.posts.find_by_id(&report.target_id).await?,
+        ReportTargetType::User => state.users.find_by_id(&report.target_id).await?,
+        ReportTargetType::Comment => state.comments.find_by_id(&report.target_id).await?,
+    };
+    let reporter = Viewer::user(reporter_id);
+    let visible = match (&report.target_type, &target) {
+        (ReportTargetType::Post, Some(post)) => can_view_post(&state, post, reporter).await?,
+        (ReportTargetType::User, Some(user)) => {
+            !user.get_bool("suspended").unwrap_or(false) && !user.contains_key("deleted_at")
+        }
+        (ReportTargetType::Comment, Some(comment)) => {
+            can_view_comment(&state, comment, reporter).await?
+        }
+        (_, None) => false,
+    };
+    let target = match target {
+        Some(target) if visible => target,
+        _ => {
+            return Err(AppError::NotFound(format!(
+                "{} with ID {} not found",
+                report.target_type.as_str(),
+                report.target_id
+            )))
+        }
+    };
+
+    let target_user_id = match report.target_type {
+        ReportTargetType::User => report.target_id.clone(),
+        _ => target.get_str("user_id").unwrap_or_default().to_string(),
+    };
+    if &target_user_id == reporter_id {
+        return Err(AppError::InvalidInput(
+            "Users cannot report themselves or their own content".to_string(),
+        ));
+    }
+
+    let pending = state
+        .reports
+        .find_one(doc! {
+            "reporter_id": reporter_id,
+            "target_type": report.target_type.as_str(),
+            "target_id": &report.target_id,
+            "status": { "$ne": ReportStatus::Resolved.as_str() },
+        })
+        .await?;
+    if pending.is_some() {
+        return Err(AppError::Conflict(format!(
+            "User {} already has a pending report on {} {}",
+            reporter_id,
+            report.target_type.as_str(),
+            report.target_id
+        )));
+    }
+
+    let report_id = Uuid::new_v4().to_string();
+    let report_doc = doc! {
+        "_id": &report_id,
+        "reporter_id": reporter_id,
+        "target_type": report.target_type.as_str(),
+        "target_id": &report.target_id,
+        "target_user_id": &target_user_id,
+        "reason": &reason,
+        "status": ReportStatus::Open.as_str(),
+        "created_at": Utc::now().to_rfc3339(),
+    };
+    let log_doc = moderation_log_doc(&report_doc, reporter_id, "reported", None);
+
+    state.reports.create(report_doc.clone(), log_doc).await?;
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("report_content", "report", &report_id)
+                .by(reporter_id)
+                .after(&report_doc),
+        )
+        .await;
+    info!("Report {} created", report_id);
+    Ok(HttpResponse::Created().json(Response {
+        status: "success".to_string(),
+        message: format!("Report created successfully with ID: {}", report_id),
+        data: Some(report_id),
+    }))
+}
+
+// Get Reports Handler
+#[instrument(skip_all)]
+pub async fn get_reports_handler(
+    actor: web::ReqData<Actor>,
+    query: web::Query<ReportListQuery>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    info!(
+        "Moderator {} is fetching reports (page {}, limit {})",
+        actor.user_id, page, limit
+    );
+
+    // The queue shows unresolved reports unless a status is asked for, oldest first
+    let filter = match &query.status {
+        Some(status) => doc! { "status": status.as_str() },
+        None => doc! { "status": { "$ne": ReportStatus::Resolved.as_str() } },
+    };
+
+    let reports = state
+        .reports
+        .list(
+            filter,
+            ListOptions::sorted(doc! { "created_at": 1 }).page(page, limit),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} reports", reports.len()),
+        data: Some(reports),
+    }))
+}
+
+// Get Report by ID Handler
+#[instrument(skip_all)]
+pub async fn get_report_by_id_handler(
+    actor: web::ReqData<Actor>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let report_id = path.into_inner();
+    info!(
+        "Moderator {} is fetching report {}",
+        actor.user_id, report_id
+    );
+
+    let mut report = match state.reports.find_by_id(&report_id).await? {
+        Some(report) => report,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "Report with ID {} not found",
+                report_id
+            )))
+        }
+    };
+
+    let history = state.reports.history(&report_id).await?;
+    report.insert("history", history);
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: "Report fetched successfully".to_string(),
+        data: Some(report),
+    }))
+}
+
+// Claim Report Handler
+#[instrument(skip_all)]
+pub async fn claim_report_handler(
+    actor: web::ReqData<Actor>,
+    path: web::Path<String>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let report_id = path.into_inner();
+    info!(
+        "Moderator {} is claiming report {}",
+        actor.user_id, report_id
+    );
+
+    let report = match state.reports.find_by_id(&report_id).await? {
+        Some(report) => report,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "Report with ID {} not found",
+                report_id
+            )))
+        }
+    };
+    if report.get_str("status").unwrap_or_default() != ReportStatus::Open.as_str() {
+        return Err(AppError::Conflict(format!(
+            "Report {} has already been claimed",
+            report_id
+        )));
+    }
+
+    let log_doc = moderation_log_doc(&report, &actor.user_id, "claimed", None);
+    // Only an open report can be claimed, so two moderators never share one
+    let claimed = state
+        .reports
+        .claim(&report_id, &actor.user_id, log_doc)
+        .await?;
+
+    if !claimed {
+        return Err(AppError::Conflict(format!(
+            "Report {} has already been claimed",
+            report_id
+        )));
+    }
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("claim_report", "report", &report_id)
+                .by(&actor.user_id)
+                .before(&doc! { "status": report.get("status") })
+                .after(&doc! {
+                    "status": ReportStatus::Claimed.as_str(),
+                    "claimed_by": &actor.user_id,
+                }),
+        )
+        .await;
+    info!("Report {} claimed by {}", report_id, actor.user_id);
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("Report {} claimed", report_id),
+        data: None,
+    }))
+}
+
+// Resolve Report Handler
+#[instrument(skip_all)]
+pub async fn resolve_report_handler(
+    actor: web::ReqData<Actor>,
+    path: web::Path<String>,
+    resolution: web::Json<ResolveReport>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let report_id = path.into_inner();
+    info!(
+        "Moderator {} is resolving report {} with {}",
+        actor.user_id,
+        report_id,
+        resolution.action.as_str()
+    );
+
+    let note = validate_moderation_note(resolution.note.as_deref())?;
+
+    let report = match state.reports.find_by_id(&report_id).await? {
+        Some(report) => report,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "Report with ID {} not found",
+                report_id
+            )))
+        }
+    };
+
+    let claimed_by_moderator = report.get_str("status").ok()
+        == Some(ReportStatus::Claimed.as_str())
+        && report.get_str("claimed_by").ok() == Some(actor.user_id.as_str());
+    if !claimed_by_moderator {
+        return Err(AppError::Conflict(format!(
+            "Report {} must be claimed by moderator {} before it is resolved",
+            report_id, actor.user_id
+        )));
+    }
+
+    let target_type = report.get_str("target_type").unwrap_or_default();
+    let target_id = report.get_str("target_id").unwrap_or_default();
+    let target_user_id = report.get_str("target_user_id").unwrap_or_default();
+
+    // Work out the content change up front so the transaction only applies it
+    let now = Utc::now().to_rfc3339();
+    let content_update = match resolution.action {
+        ModerationAction::Dismiss => None,
+        ModerationAction::HideContent => {
+            let collection = match target_type {
+                "post" => "posts",
+                "comment" => "comments",
+                _ => {
+                    return Err(AppError::InvalidInput(
+                        "Only posts and comments can be hidden, suspend the user instead"
+                            .to_string(),
+                    ))
+                }
+            };
+            Some((
+                collection,
+                target_id.to_string(),
+                doc! { "$set": {
+                    "hidden": true,
+                    "hidden_at": &now,
+                    "hidden_by": &actor.user_id,
+                } },
+            ))
+        }
+        ModerationAction::SuspendUser => {
+            if target_user_id == actor.user_id {
+                return Err(AppError::InvalidInput(
+                    "Moderators cannot suspend themselves".to_string(),
+                ));
+            }
+            // Only admins can suspend other moderators and admins
+            if !actor.role.is_admin() && user_role(&state, target_user_id).await?.can_moderate() {
+                return Err(AppError::Forbidden(format!(
+                    "Only admins can suspend moderator {}",
+                    target_user_id
+                )));
+            }
+            Some((
+                "users",
+                target_user_id.to_string(),
+                doc! { "$set": {
+                    "suspended": true,
+                    "suspended_at": &now,
+                    "suspended_by": &actor.user_id,
+                } },
+            ))
+        }
+    };
+
+    let report_update = doc! { "$set": {
+        "status": ReportStatus::Resolved.as_str(),
+        "resolution": {
+            "action": resolution.action.as_str(),
+            "moderator_id": &actor.user_id,
+            "note": note.as_deref(),
+            "resolved_at": &now,
+        },
+    } };
+    let log_doc = moderation_log_doc(
+        &report,
+        &actor.user_id,
+        resolution.action.as_str(),
+        note.as_deref(),
+    );
+
+    let resolved = state
+        .reports
+        .resolve(
+            &report_id,
+            &actor.user_id,
+            report_update.clone(),
+            content_update.clone(),
+            log_doc,
+        )
+        .await?;
+
+    if !resolved {
+        return Err(AppError::Conflict(format!(
+            "Report {} has already been resolved",
+            report_id
+        )));
+    }
+
+    let set_fields = |update: &Document| update.get_document("$set").cloned().unwrap_or_default();
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("resolve_report", "report", &report_id)
+                .by(&actor.user_id)
+                .before(&doc! { "status": report.get("status") })
+                .after(&set_fields(&report_update)),
+        )
+        .await;
+    // The moderation action is recorded against the content or account it changed
+    if let Some((collection, id, update)) = &content_update {
+        let target_type = if *collection == "users" {
+            ReportTargetType::User.as_str()
+        } else {
+            target_type
+        };
+        audit
+            .record(
+                state.audit_log.as_ref(),
+                AuditEvent::new(resolution.action.as_str(), target_type, id)
+                    .by(&actor.user_id)
+                    .after(&set_fields(update)),
+            )
+            .await;
+    }
+    info!(
+        "Report {} resolved by {} with {}",
+        report_id,
+        actor.user_id,
+        resolution.action.as_str()
+    );
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!(
+            "Report {} resolved with {}",
+            report_id,
+            resolution.action.as_str()
+        ),
+        data: None,
+    }))
+}
+
+// Update User Role Handler
+#[instrument(skip_all)]
+pub async fn update_user_role_handler(
+    actor: web::ReqData<Actor>,
+    path: web::Path<String>,
+    update: web::Json<RoleUpdate>,
+    audit: AuditContext,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let user_id = path.into_inner();
+    info!(
+        "Admin {} is setting the role of user {} to {}",
+        actor.user_id,
+        user_id,
+        update.role.as_str()
+    );
+
+    // Keeps an admin from locking everyone out by demoting the last admin account
+    if user_id == actor.user_id {
+        return Err(AppError::InvalidInput(
+            "Admins cannot change their own role".to_string(),
+        ));
+    }
+
+    let before = match state.users.set_role(&user_id, update.role.as_str()).await? {
+        Some(before) => before,
+        None => {
+            return Err(AppError::NotFound(format!(
+                "User with ID {} not found",
+                user_id
+            )))
+        }
+    };
+
+    audit
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("update_user_role", "user", &user_id)
+                .by(&actor.user_id)
+                .before(&doc! { "role": before.get_str("role").unwrap_or(UserRole::User.as_str()) })
+                .after(&doc! { "role": update.role.as_str() }),
+        )
+        .await;
+
+    info!("User {} now has role {}", user_id, update.role.as_str());
+    Ok(HttpResponse::Ok().json(Response::<()> {
+        status: "success".to_string(),
+        message: format!("User {} now has role {}", user_id, update.role.as_str()),
+        data: None,
+    }))
+}
+
+// Get Audit Log Handler
+#[instrument(skip_all)]
+pub async fn get_audit_log_handler(
+    actor: web::ReqData<Actor>,
+    query: web::Query<AuditLogQuery>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    info!(
+        "Admin {} is fetching the audit log (page {}, limit {})",
+        actor.user_id, page, limit
+    );
+
+    let mut filter = Document::new();
+    for (field, value) in [
+        ("actor_id", &query.actor_id),
+        ("action", &query.action),
+        ("target_type", &query.target_type),
+        ("target_id", &query.target_id),
+        ("request_id", &query.request_id),
+    ] {
+        if let Some(value) = value {
+            filter.insert(field, value);
+        }
+    }
+
+    let mut created_at = Document::new();
+    for (operator, bound, name) in [("$gte", &query.from, "from"), ("$lte", &query.to, "to")] {
+        if let Some(bound) = bound {
+            let bound = chrono::DateTime::parse_from_rfc3339(bound).map_err(|_| {
+                AppError::ValidationError {
+                    field: name.to_string(),
+                    message: "Must be an RFC3339 timestamp".to_string(),
+                }
+            })?;
+            created_at.insert(operator, bound.with_timezone(&Utc).to_rfc3339());
+        }
+    }
+    if !created_at.is_empty() {
+        filter.insert("created_at", created_at);
+    }
+
+    let entries = state
+        .audit_log
+        .list(
+            filter,
+            ListOptions::sorted(doc! { "created_at": -1 }).page(page, limit),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Response {
+        status: "success".to_string(),
+        message: format!("Successfully fetched {} audit entries", entries.len()),
+        data: Some(entries),
+    }))
+}
@@ -0,0 +1,24 @@
+pub mod audit;
+pub mod auth;
+pub mod cli;
+pub mod cluster;
+pub mod config;
+pub mod errors;
+pub mod exports;
+pub mod handlers;
+pub mod lifecycle;
+pub mod logging;
+pub mod metrics;
+pub mod models;
+pub mod publishing;
+pub mod repository;
+pub mod retention;
+pub mod routes;
+pub mod security;
+pub mod seeding;
+pub mod state;
+pub mod telemetry;
+pub mod tls;
+pub mod transfer;
+pub mod validation;
+pub mod visibility;
@@ -0,0 +1,148 @@
+use mongodb::{
+    bson::doc,
+    options::{ReadPreference, SelectionCriteria},
+    Client,
+};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    runtime::Handle,
+    sync::{watch, Notify},
+    time::Instant,
+};
+use tracing::{info, warn, Instrument};
+
+// Retry a ping that needs the primary until it answers, backing off exponentially up to
+// `max_backoff` between attempts. Returns the last error once `timeout` has passed.
+pub async fn wait_for_primary(
+    client: &Client,
+    timeout: Duration,
+    max_backoff: Duration,
+) -> mongodb::error::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut backoff = Duration::from_secs(1).min(max_backoff);
+    let mut attempt = 1;
+    loop {
+        let ping = client
+            .database("admin")
+            .run_command(doc! { "ping": 1 })
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .await;
+        match ping {
+            Ok(_) => {
+                info!("MongoDB primary reachable after {} attempt(s)", attempt);
+                return Ok(());
+            }
+            Err(e) if Instant::now() + backoff >= deadline => return Err(e),
+            Err(e) => {
+                warn!(
+                    "MongoDB primary not reachable (attempt {}), retrying in {}s: {}",
+                    attempt,
+                    backoff.as_secs_f32(),
+                    e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+// Background work that must outlive the request that started it: the periodic jobs and
+// the seed and export jobs. Tasks run on the runtime that created the tracker rather than
+// on an HTTP worker, so stopping the server does not cut them off, and shutdown waits for
+// them to finish.
+#[derive(Clone)]
+pub struct BackgroundJobs {
+    runtime: Handle,
+    stopping: watch::Sender<bool>,
+    running: Arc<AtomicUsize>,
+    finished: Arc<Notify>,
+}
+
+// Decrements the running count when a task ends, even if it panicked
+struct RunningGuard {
+    running: Arc<AtomicUsize>,
+    finished: Arc<Notify>,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        self.finished.notify_waiters();
+    }
+}
+
+impl BackgroundJobs {
+    // Must be called from within a Tokio runtime
+    pub fn new() -> Self {
+        BackgroundJobs {
+            runtime: Handle::current(),
+            stopping: watch::Sender::new(false),
+            running: Arc::new(AtomicUsize::new(0)),
+            finished: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let guard = RunningGuard {
+            running: self.running.clone(),
+            finished: self.finished.clone(),
+        };
+        // Jobs started by a request keep logging with its request ID
+        self.runtime.spawn(
+            async move {
+                let _guard = guard;
+                task.await;
+            }
+            .in_current_span(),
+        );
+    }
+
+    // Resolves once shutdown has begun; periodic jobs stop between runs when it does
+    pub fn stopping(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut stopping = self.stopping.subscribe();
+        async move {
+            let _ = stopping.wait_for(|stopping| *stopping).await;
+        }
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    // Ask every job to stop and wait up to `timeout` for them. Returns how many were
+    // still running when the time ran out.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.stopping.send_replace(true);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let finished = self.finished.notified();
+            let running = self.running();
+            if running == 0 {
+                return 0;
+            }
+            info!("Waiting for {} background job(s) to finish", running);
+            if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                return self.running();
+            }
+        }
+    }
+}
+
+impl Default for BackgroundJobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
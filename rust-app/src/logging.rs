@@ -0,0 +1,190 @@
+use crate::{
+    config::{LogFormat, LoggingConfig},
+    telemetry,
+};
+use actix_web::{
+    body::{BoxBody, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::{
+        header::{HeaderName, HeaderValue, CONTENT_TYPE},
+        StatusCode,
+    },
+    middleware::Next,
+    web::Bytes,
+    Error, HttpMessage, HttpResponse,
+};
+use opentelemetry_sdk::trace::SdkTracer;
+use serde_json::Value;
+use std::time::Instant;
+use tracing::{field, info, info_span, Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::Targets, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer, Registry,
+};
+use uuid::Uuid;
+
+// Header correlating a request across the frontend, the API and the logs
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Longest request ID accepted from a client, enough for UUIDs and W3C trace IDs
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// ID of the current request, stored in the request extensions by `assign_request_id`
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+// Install the global subscriber. RUST_LOG, when set, replaces the configured levels.
+// With a tracer, the spans of this crate are exported as well, whatever the log levels.
+pub fn init(config: &LoggingConfig, tracer: Option<SdkTracer>) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(config.directives()));
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+    let fmt = tracing_subscriber::fmt::layer();
+    layers.push(match config.format {
+        LogFormat::Text => fmt.with_filter(filter).boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_filter(filter)
+            .boxed(),
+    });
+    if let Some(tracer) = tracer {
+        let spans = Targets::new()
+            .with_target("ddbp", Level::INFO)
+            .with_target("DDBP", Level::INFO);
+        layers.push(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(spans)
+                .boxed(),
+        );
+    }
+    tracing_subscriber::registry().with(layers).init();
+}
+
+// Client IDs end up in logs and headers, so only plain tokens are reused
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LEN
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+}
+
+// Add the request ID header, and the request ID to JSON error bodies
+fn tag_response(
+    response: HttpResponse<BoxBody>,
+    request_id: &HeaderValue,
+) -> HttpResponse<BoxBody> {
+    let mut response = response;
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static("x-request-id"), request_id.clone());
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    let is_error = response.status().is_client_error() || response.status().is_server_error();
+    if !is_json || !is_error {
+        return response;
+    }
+
+    let (response, body) = response.into_parts();
+    match body.try_into_bytes() {
+        Ok(bytes) => {
+            let body = match serde_json::from_slice::<Value>(&bytes) {
+                Ok(Value::Object(mut fields)) => {
+                    fields.insert(
+                        "request_id".to_string(),
+                        Value::from(request_id.to_str().unwrap_or_default()),
+                    );
+                    serde_json::to_vec(&fields).map_or(bytes, Bytes::from)
+                }
+                _ => bytes,
+            };
+            response.set_body(BoxBody::new(body))
+        }
+        // Streamed bodies are left alone
+        Err(body) => response.set_body(body),
+    }
+}
+
+// Middleware giving every request an ID, reusing a valid X-Request-Id sent by the client.
+// Everything logged while the request is handled carries the ID through the request span,
+// and the response returns it in the header and in JSON error bodies. The span is also the
+// server span of the trace, continuing the trace of a caller that sent a traceparent.
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|request_id| is_valid_request_id(request_id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let header = HeaderValue::from_str(&request_id).expect("request IDs are plain ASCII");
+    let route = req.match_pattern();
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+        otel.name = %format!("{} {}", req.method(), route.as_deref().unwrap_or(req.path())),
+        otel.kind = "server",
+        otel.status_code = field::Empty,
+        http.route = route,
+        http.response.status_code = field::Empty,
+    );
+    // Only fails when no tracer is installed, and then there is nothing to continue
+    let _ = span.set_parent(telemetry::extract_context(req.headers()));
+    req.extensions_mut().insert(RequestId(request_id));
+    let record_status = |status: StatusCode| {
+        let span = Span::current();
+        span.record("http.response.status_code", status.as_u16());
+        if status.is_server_error() {
+            span.record("otel.status_code", "error");
+        }
+    };
+
+    async move {
+        let started = Instant::now();
+        match next.call(req).await {
+            Ok(mut res) => {
+                info!(
+                    "Answered {} in {}ms",
+                    res.status(),
+                    started.elapsed().as_millis()
+                );
+                record_status(res.status());
+                if res.status().is_success() || res.status().is_redirection() {
+                    res.headers_mut()
+                        .insert(HeaderName::from_static("x-request-id"), header);
+                    return Ok(res.map_into_left_body());
+                }
+                let (req, response) = res.into_parts();
+                let response = tag_response(response.map_into_boxed_body(), &header);
+                Ok(ServiceResponse::new(req, response).map_into_right_body())
+            }
+            Err(e) => {
+                // The error only becomes a response later on, so render it here to tag it
+                let response = tag_response(e.error_response(), &header);
+                info!(
+                    "Answered {} in {}ms",
+                    response.status(),
+                    started.elapsed().as_millis()
+                );
+                record_status(response.status());
+                Err(InternalError::from_response(e, response).into())
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
@@ -0,0 +1,298 @@
+use crate::telemetry;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use mongodb::{
+    event::{cmap::CmapEvent, command::CommandEvent, EventHandler},
+    options::ClientOptions,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+use tracing::{error, Span};
+
+// Database round trips are much shorter than whole requests, so they get finer buckets
+const MONGO_BUCKETS: [f64; 13] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+// Commands whose reply never arrived, e.g. because the request was cancelled, would pile up
+// in the table of commands in flight. Past this many it is cleared.
+const MAX_COMMANDS_IN_FLIGHT: usize = 10_000;
+
+// Content type of the text format, including the format version scrapers check
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+// Every metric the application exports, served at /metrics
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub mongo_command_duration: HistogramVec,
+    pub mongo_command_failures: IntCounterVec,
+    pub mongo_pool_max_connections: IntGauge,
+    pub mongo_pool_connections: IntGaugeVec,
+    pub mongo_pool_connections_in_use: IntGaugeVec,
+    pub mongo_pool_checkout_failures: IntCounterVec,
+    pub app_errors: IntCounterVec,
+    pub users_created: IntCounter,
+    pub posts_created: IntCounter,
+    pub comments_created: IntCounter,
+    pub follows_created: IntCounter,
+}
+
+fn counter(registry: &Registry, name: &str, help: &str) -> IntCounter {
+    let counter = IntCounter::new(name, help).expect("valid metric");
+    registry
+        .register(Box::new(counter.clone()))
+        .expect("metric registered once");
+    counter
+}
+
+fn counter_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+    registry
+        .register(Box::new(counter.clone()))
+        .expect("metric registered once");
+    counter
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str) -> IntGauge {
+    let gauge = IntGauge::new(name, help).expect("valid metric");
+    registry
+        .register(Box::new(gauge.clone()))
+        .expect("metric registered once");
+    gauge
+}
+
+fn gauge_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels).expect("valid metric");
+    registry
+        .register(Box::new(gauge.clone()))
+        .expect("metric registered once");
+    gauge
+}
+
+fn histogram_vec(registry: &Registry, opts: HistogramOpts, labels: &[&str]) -> HistogramVec {
+    let histogram = HistogramVec::new(opts, labels).expect("valid metric");
+    registry
+        .register(Box::new(histogram.clone()))
+        .expect("metric registered once");
+    histogram
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        Metrics {
+            http_requests: counter_vec(
+                &registry,
+                "ddbp_http_requests_total",
+                "HTTP requests by route pattern and response status",
+                &["method", "route", "status"],
+            ),
+            http_request_duration: histogram_vec(
+                &registry,
+                HistogramOpts::new(
+                    "ddbp_http_request_duration_seconds",
+                    "Time to produce an HTTP response by route pattern and status",
+                ),
+                &["method", "route", "status"],
+            ),
+            mongo_command_duration: histogram_vec(
+                &registry,
+                HistogramOpts::new(
+                    "ddbp_mongo_command_duration_seconds",
+                    "MongoDB command round trips by collection and operation",
+                )
+                .buckets(MONGO_BUCKETS.to_vec()),
+                &["collection", "operation"],
+            ),
+            mongo_command_failures: counter_vec(
+                &registry,
+                "ddbp_mongo_command_failures_total",
+                "MongoDB commands that failed, by collection and operation",
+                &["collection", "operation"],
+            ),
+            mongo_pool_max_connections: gauge(
+                &registry,
+                "ddbp_mongo_pool_max_connections",
+                "Configured maximum size of the connection pool to each MongoDB server",
+            ),
+            mongo_pool_connections: gauge_vec(
+                &registry,
+                "ddbp_mongo_pool_connections",
+                "Open connections in the pool to each MongoDB server",
+                &["server"],
+            ),
+            mongo_pool_connections_in_use: gauge_vec(
+                &registry,
+                "ddbp_mongo_pool_connections_in_use",
+                "Connections checked out of the pool to each MongoDB server",
+                &["server"],
+            ),
+            mongo_pool_checkout_failures: counter_vec(
+                &registry,
+                "ddbp_mongo_pool_checkout_failures_total",
+                "Failed attempts to get a connection from the pool, e.g. on timeouts",
+                &["server"],
+            ),
+            app_errors: counter_vec(
+                &registry,
+                "ddbp_app_errors_total",
+                "Error responses by AppError variant",
+                &["variant"],
+            ),
+            users_created: counter(&registry, "ddbp_users_created_total", "Users created"),
+            posts_created: counter(&registry, "ddbp_posts_created_total", "Posts created"),
+            comments_created: counter(&registry, "ddbp_comments_created_total", "Comments created"),
+            follows_created: counter(
+                &registry,
+                "ddbp_follows_created_total",
+                "Follows created, directly or by accepting a follow request",
+            ),
+            registry,
+        }
+    }
+
+    // The registry in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Could not encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+// Process-wide so that errors and driver events, which have no access to the app data,
+// can record into the same registry as the middleware
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+// Middleware counting and timing every request. Routes are labelled with their pattern,
+// e.g. /api/posts/{id}, so the number of series stays bounded.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    let metrics = metrics();
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
+
+// Collection a command works on: the value of the command name for find, insert, update,
+// aggregate and so on, the `collection` field for getMore, none for commands like ping
+pub(crate) fn command_collection(event: &mongodb::event::command::CommandStartedEvent) -> String {
+    let collection = match event.command_name.as_str() {
+        "getMore" => event.command.get_str("collection"),
+        name => event.command.get_str(name),
+    };
+    collection.unwrap_or("none").to_string()
+}
+
+// Record the latency of every MongoDB command and the state of the connection pools by
+// subscribing to the driver's command and connection pool events. Each command also gets a
+// tracing span, which ends when the reply arrives.
+pub fn monitor(options: &mut ClientOptions) {
+    let metrics = metrics();
+    metrics
+        .mongo_pool_max_connections
+        .set(options.max_pool_size.map_or(10, i64::from));
+
+    // Replies only carry the request ID, so remember which collection each command was for
+    let in_flight: Mutex<HashMap<i32, (String, Span)>> = Mutex::new(HashMap::new());
+    options.command_event_handler = Some(EventHandler::callback(move |event| {
+        let mut in_flight = in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        match event {
+            CommandEvent::Started(event) => {
+                if in_flight.len() >= MAX_COMMANDS_IN_FLIGHT {
+                    in_flight.clear();
+                }
+                let collection = command_collection(&event);
+                let span = telemetry::command_span(&event, &collection);
+                in_flight.insert(event.request_id, (collection, span));
+            }
+            CommandEvent::Succeeded(event) => {
+                let collection = in_flight
+                    .remove(&event.request_id)
+                    .map(|(collection, _)| collection);
+                let labels = [collection.as_deref().unwrap_or("none"), &event.command_name];
+                metrics
+                    .mongo_command_duration
+                    .with_label_values(&labels)
+                    .observe(event.duration.as_secs_f64());
+            }
+            CommandEvent::Failed(event) => {
+                let collection = in_flight
+                    .remove(&event.request_id)
+                    .map(|(collection, span)| {
+                        telemetry::fail_command_span(&span, &event);
+                        collection
+                    });
+                let labels = [collection.as_deref().unwrap_or("none"), &event.command_name];
+                metrics
+                    .mongo_command_duration
+                    .with_label_values(&labels)
+                    .observe(event.duration.as_secs_f64());
+                metrics
+                    .mongo_command_failures
+                    .with_label_values(&labels)
+                    .inc();
+            }
+            _ => {}
+        }
+    }));
+
+    options.cmap_event_handler = Some(EventHandler::callback(move |event| match event {
+        CmapEvent::ConnectionCreated(event) => metrics
+            .mongo_pool_connections
+            .with_label_values(&[event.address.to_string()])
+            .inc(),
+        CmapEvent::ConnectionClosed(event) => metrics
+            .mongo_pool_connections
+            .with_label_values(&[event.address.to_string()])
+            .dec(),
+        CmapEvent::ConnectionCheckedOut(event) => metrics
+            .mongo_pool_connections_in_use
+            .with_label_values(&[event.address.to_string()])
+            .inc(),
+        CmapEvent::ConnectionCheckedIn(event) => metrics
+            .mongo_pool_connections_in_use
+            .with_label_values(&[event.address.to_string()])
+            .dec(),
+        CmapEvent::ConnectionCheckoutFailed(event) => metrics
+            .mongo_pool_checkout_failures
+            .with_label_values(&[event.address.to_string()])
+            .inc(),
+        _ => {}
+    }));
+}
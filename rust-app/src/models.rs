@@ -11,23 +11,152 @@ pub struct User {
     pub profile_picture_url: Option<String>,
     #[serde(skip_deserializing)]
     pub join_date: Option<String>,
+    #[serde(default)]
+    pub is_private: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// The credentials exchanged for a token at /api/auth/token
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Credentials {
+    pub email: String,
+    pub password_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthToken {
+    pub user_id: String,
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountPrivacy {
+    pub is_private: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum PostType {
+    #[default]
     Text,
     Image,
     Video,
     Link,
+    Poll,
+}
+
+impl PostType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostType::Text => "text",
+            PostType::Image => "image",
+            PostType::Video => "video",
+            PostType::Link => "link",
+            PostType::Poll => "poll",
+        }
+    }
+
+    // Older documents stored capitalized variant names, so match case-insensitively
+    pub fn parse(value: &str) -> PostType {
+        match value.to_ascii_lowercase().as_str() {
+            "image" => PostType::Image,
+            "video" => PostType::Video,
+            "link" => PostType::Link,
+            "poll" => PostType::Poll,
+            _ => PostType::Text,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    Draft,
+    Scheduled,
+    Published,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+        }
+    }
+
+    // Posts created before drafts existed have no status and are published
+    pub fn parse(value: &str) -> PostStatus {
+        match value {
+            "draft" => PostStatus::Draft,
+            "scheduled" => PostStatus::Scheduled,
+            _ => PostStatus::Published,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostVisibility {
+    #[default]
+    Public,
+    Followers,
+    Mentioned,
 }
 
-impl Default for PostType {
-    fn default() -> Self {
-        PostType::Text
+impl PostVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostVisibility::Public => "public",
+            PostVisibility::Followers => "followers",
+            PostVisibility::Mentioned => "mentioned",
+        }
+    }
+
+    // Posts created before visibility levels existed are public
+    pub fn parse(value: &str) -> PostVisibility {
+        match value {
+            "followers" => PostVisibility::Followers,
+            "mentioned" => PostVisibility::Mentioned,
+            _ => PostVisibility::Public,
+        }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Follow,
+    FollowRequest,
+    FollowAccepted,
+    Comment,
+    Repost,
+    Quote,
+    Mention,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Follow => "follow",
+            NotificationKind::FollowRequest => "follow_request",
+            NotificationKind::FollowAccepted => "follow_accepted",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Repost => "repost",
+            NotificationKind::Quote => "quote",
+            NotificationKind::Mention => "mention",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Poll {
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub closes_at: Option<String>,
+    #[serde(default)]
+    pub multiple_choice: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Post {
     pub user_id: String,
@@ -38,6 +167,60 @@ pub struct Post {
     pub post_type: PostType,
     #[serde(default)]
     pub like_count: i32,
+    #[serde(default)]
+    pub poll: Option<Poll>,
+    #[serde(default)]
+    pub quoted_post_id: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub publish_at: Option<String>,
+    #[serde(default)]
+    pub visibility: PostVisibility,
+    #[serde(default)]
+    pub mentions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PublishPost {
+    #[serde(default)]
+    pub publish_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Vote {
+    pub option_indexes: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bookmark {
+    pub post_id: String,
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BookmarkListQuery {
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub page: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookmarkCollection {
+    pub name: String,
+    pub count: i32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PaginationQuery {
+    #[serde(default)]
+    pub page: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -53,6 +236,238 @@ pub struct Follow {
     pub following_id: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::User => "user",
+            UserRole::Moderator => "moderator",
+            UserRole::Admin => "admin",
+        }
+    }
+
+    // Users created before roles existed are regular users
+    pub fn parse(value: &str) -> UserRole {
+        match value {
+            "moderator" => UserRole::Moderator,
+            "admin" => UserRole::Admin,
+            _ => UserRole::User,
+        }
+    }
+
+    pub fn can_moderate(&self) -> bool {
+        matches!(self, UserRole::Moderator | UserRole::Admin)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        *self == UserRole::Admin
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoleUpdate {
+    pub role: UserRole,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTargetType {
+    Post,
+    Comment,
+    User,
+}
+
+impl ReportTargetType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportTargetType::Post => "post",
+            ReportTargetType::Comment => "comment",
+            ReportTargetType::User => "user",
+        }
+    }
+
+    pub fn collection(&self) -> &'static str {
+        match self {
+            ReportTargetType::Post => "posts",
+            ReportTargetType::Comment => "comments",
+            ReportTargetType::User => "users",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    Claimed,
+    Resolved,
+}
+
+impl ReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportStatus::Open => "open",
+            ReportStatus::Claimed => "claimed",
+            ReportStatus::Resolved => "resolved",
+        }
+    }
+}
+
+// Lifecycle of a background job such as a data export or a seeding run
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    Dismiss,
+    HideContent,
+    SuspendUser,
+}
+
+impl ModerationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationAction::Dismiss => "dismiss",
+            ModerationAction::HideContent => "hide_content",
+            ModerationAction::SuspendUser => "suspend_user",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Report {
+    pub target_type: ReportTargetType,
+    pub target_id: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveReport {
+    pub action: ModerationAction,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReportListQuery {
+    #[serde(default)]
+    pub status: Option<ReportStatus>,
+    #[serde(default)]
+    pub page: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AuditLogQuery {
+    #[serde(default)]
+    pub actor_id: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub target_type: Option<String>,
+    #[serde(default)]
+    pub target_id: Option<String>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    // RFC3339 bounds on created_at, both inclusive
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub page: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+fn default_users() -> usize {
+    150
+}
+
+fn default_posts() -> usize {
+    300
+}
+
+fn default_comments() -> usize {
+    500
+}
+
+fn default_likes() -> usize {
+    1000
+}
+
+fn default_follows() -> usize {
+    400
+}
+
+fn default_seed_batch_size() -> usize {
+    1000
+}
+
+// What to generate. The same seed and anchor always produce the same documents.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeedOptions {
+    #[serde(default = "default_users")]
+    pub users: usize,
+    #[serde(default = "default_posts")]
+    pub posts: usize,
+    #[serde(default = "default_comments")]
+    pub comments: usize,
+    #[serde(default = "default_likes")]
+    pub likes: usize,
+    #[serde(default = "default_follows")]
+    pub follows: usize,
+    // Random when not given; the seed used is reported back so the run can be repeated
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // RFC3339 time of the newest generated activity, midnight UTC today when not given
+    #[serde(default)]
+    pub anchor: Option<String>,
+    // Documents per insert_many call
+    #[serde(default = "default_seed_batch_size")]
+    pub batch_size: usize,
+}
+
+// What a seeding run produced
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SeedSummary {
+    pub seed: u64,
+    pub anchor: String,
+    pub users: usize,
+    pub posts: usize,
+    pub comments: usize,
+    pub likes: usize,
+    pub follows: usize,
+}
+
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Like {
     pub post_id: String,
@@ -60,6 +475,7 @@ pub struct Like {
     pub created_at: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserProfile {
     pub id: String,
@@ -72,6 +488,40 @@ pub struct UserProfile {
     pub post_count: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PollOptionResult {
+    pub text: String,
+    pub vote_count: i32,
+    pub voted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PollResults {
+    pub options: Vec<PollOptionResult>,
+    pub multiple_choice: bool,
+    pub closes_at: Option<String>,
+    pub is_closed: bool,
+    pub total_votes: i32,
+    pub has_voted: bool,
+}
+
+// Summary of a quoted post; `available` is false once the original has been deleted
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuotedPost {
+    pub id: String,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_type: Option<PostType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PostDetails {
     pub id: String,
@@ -81,13 +531,42 @@ pub struct PostDetails {
     pub content: String,
     pub media_urls: Vec<String>,
     pub post_type: PostType,
+    pub status: PostStatus,
+    pub visibility: PostVisibility,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<String>,
     pub created_at: String,
     pub human_time: String,
     pub like_count: i32,
     pub comment_count: i32,
     pub has_liked: bool,
+    pub repost_count: i32,
+    pub quote_count: i32,
+    pub has_reposted: bool,
+    pub is_bookmarked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll: Option<PollResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quoted_post: Option<QuotedPost>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RepostAttribution {
+    pub user_id: String,
+    pub username: String,
+    pub reposted_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TimelineItem {
+    pub post: PostDetails,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reposted_by: Option<RepostAttribution>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserStats {
     pub post_count: i32,
@@ -0,0 +1,105 @@
+use crate::{
+    audit::{AuditContext, AuditEvent},
+    lifecycle::BackgroundJobs,
+    models::{NotificationKind, PostStatus},
+    repository::{ListOptions, PostRepo, Store},
+};
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, Document},
+    error::Result,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+// Build a notification for `recipient_id`, or None when users act on their own content.
+// The ID is derived from the event so replaying an event cannot notify twice.
+pub fn notification_doc(
+    event_id: &str,
+    recipient_id: &str,
+    actor_id: &str,
+    kind: NotificationKind,
+    post_id: Option<&str>,
+) -> Option<Document> {
+    if recipient_id == actor_id {
+        return None;
+    }
+
+    Some(doc! {
+        "_id": format!("{}:{}:{}", kind.as_str(), event_id, recipient_id),
+        "user_id": recipient_id,
+        "actor_id": actor_id,
+        "kind": kind.as_str(),
+        "post_id": post_id,
+        "read": false,
+        "created_at": Utc::now().to_rfc3339(),
+    })
+}
+
+// Filter matching the scheduled posts whose time has come by `due_by`
+pub fn due_filter(due_by: &str) -> Document {
+    doc! {
+        "status": PostStatus::Scheduled.as_str(),
+        "publish_at": { "$lte": due_by },
+    }
+}
+
+// Publish every scheduled post whose time has come, auditing each as the system
+pub async fn publish_due_posts<S: Store>(store: &S, audit: &AuditContext) -> Result<usize> {
+    let now = Utc::now().to_rfc3339();
+    let due_posts = PostRepo::list(store, due_filter(&now), ListOptions::default()).await?;
+
+    let mut published = 0;
+    for post_id in due_posts.iter().filter_map(|post| post.get_str("_id").ok()) {
+        // The store re-checks the post is due, so one published by another instance is skipped
+        if let Some(post) = store.publish_due(post_id, &now).await? {
+            info!("Published scheduled post {}", post_id);
+            audit
+                .record(
+                    store,
+                    AuditEvent::new("publish_post", "post", post_id)
+                        .before(&doc! { "status": PostStatus::Scheduled.as_str() })
+                        .after(&doc! { "status": post.get("status") }),
+                )
+                .await;
+            published += 1;
+        }
+    }
+
+    Ok(published)
+}
+
+// Spawn the background task that publishes scheduled posts on the actix runtime
+pub fn spawn_post_scheduler<S: Store>(
+    store: Arc<S>,
+    interval: Duration,
+    audit_retention: Duration,
+    jobs: &BackgroundJobs,
+) {
+    info!(
+        "Starting post scheduler with a {} second interval",
+        interval.as_secs()
+    );
+
+    let stopping = jobs.stopping();
+    jobs.spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        tokio::pin!(stopping);
+        loop {
+            // A run in progress is finished before stopping
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = &mut stopping => {
+                    info!("Stopping the post scheduler");
+                    return;
+                }
+            }
+            let audit = AuditContext::system(audit_retention);
+            match publish_due_posts(store.as_ref(), &audit).await {
+                Ok(0) => {}
+                Ok(count) => info!("Post scheduler published {} posts", count),
+                Err(e) => error!("Post scheduler failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,1072 @@
+use super::{
+    author_flag_updates, cleared_filter, follow_counter_updates, moderation_flag_updates,
+    pending_filter, post_counter_updates, publish_notifications,
+    query::{apply_update, matches, sort},
+    removal_counter_updates, user_connection_filters, vote_changes, AuditRepo, BlockRepo,
+    BookmarkRepo, CommentRepo, ContentUpdate, DatabaseRepo, ExportRepo, FollowRepo, LikeRepo,
+    ListOptions, NotificationRepo, PostRepo, ReportRepo, SeedRepo, TransferRepo, UserRepo,
+    POST_DEPENDENTS, SEED_LOCK,
+};
+use crate::{
+    cluster::{ClusterStatus, MemberStatus},
+    models::{PostStatus, ReportStatus},
+    publishing::due_filter,
+    transfer::{ConflictMode, ImportSummary, DATA_COLLECTIONS},
+    visibility::active_user_filter,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, Document},
+    error::{Error, ErrorKind, Result, WriteError, WriteFailure},
+};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+// Repositories held in process memory so the API can be exercised without a database.
+// Collections hold documents in insertion order and are queried with the same filter
+// documents the MongoDB store receives. Every method holds the lock for its whole run, so
+// writes that MongoDB makes in a transaction are just as atomic here.
+#[derive(Default)]
+pub struct MemoryStore {
+    collections: Mutex<Collections>,
+    // Export archives keyed by export job ID
+    archives: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+// A panic while holding a lock cannot leave a document half written, so keep going
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// The error MongoDB reports when a write hits the unique `_id` index
+fn duplicate_key_error(collection: &str, id: &Bson) -> Error {
+    let write_error: WriteError = bson::from_document(doc! {
+        "code": 11000,
+        "codeName": "DuplicateKey",
+        "errmsg": format!("E11000 duplicate key error collection: {} dup key: {}", collection, id),
+    })
+    .expect("a write error document deserializes");
+    Error::from(ErrorKind::Write(WriteFailure::WriteError(write_error)))
+}
+
+// Apply the order and window of a list query to `documents`
+fn window(mut documents: Vec<Document>, options: &ListOptions) -> Vec<Document> {
+    if let Some(order) = &options.sort {
+        sort(&mut documents, order);
+    }
+    let documents = documents.into_iter().skip(options.skip as usize);
+    match options.limit {
+        Some(limit) => documents.take(limit as usize).collect(),
+        None => documents.collect(),
+    }
+}
+
+// Every collection by name
+#[derive(Default)]
+struct Collections(HashMap<String, Vec<Document>>);
+
+impl Collections {
+    fn all(&self, collection: &str) -> &[Document] {
+        self.0
+            .get(collection)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn find(&self, collection: &str, filter: &Document) -> Vec<Document> {
+        self.all(collection)
+            .iter()
+            .filter(|document| matches(document, filter))
+            .cloned()
+            .collect()
+    }
+
+    fn find_one(&self, collection: &str, filter: &Document) -> Option<Document> {
+        self.all(collection)
+            .iter()
+            .find(|document| matches(document, filter))
+            .cloned()
+    }
+
+    fn by_id(&self, collection: &str, id: &str) -> Option<Document> {
+        self.find_one(collection, &doc! { "_id": id })
+    }
+
+    fn insert(&mut self, collection: &str, mut document: Document) -> Result<()> {
+        if !document.contains_key("_id") {
+            document.insert("_id", ObjectId::new());
+        }
+        let documents = self.0.entry(collection.to_string()).or_default();
+        let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+        if documents
+            .iter()
+            .any(|existing| existing.get("_id") == Some(&id))
+        {
+            return Err(duplicate_key_error(collection, &id));
+        }
+        documents.push(document);
+        Ok(())
+    }
+
+    // Returns the document as it was before the update
+    fn update_one(
+        &mut self,
+        collection: &str,
+        filter: &Document,
+        update: &Document,
+    ) -> Option<Document> {
+        let document = self
+            .0
+            .get_mut(collection)?
+            .iter_mut()
+            .find(|document| matches(document, filter))?;
+        let before = document.clone();
+        apply_update(document, update);
+        Some(before)
+    }
+
+    fn update_many(&mut self, collection: &str, filter: &Document, update: &Document) {
+        for document in self.0.get_mut(collection).into_iter().flatten() {
+            if matches(document, filter) {
+                apply_update(document, update);
+            }
+        }
+    }
+
+    // Returns false when there is no document with this `_id`
+    fn replace(&mut self, collection: &str, id: &Bson, replacement: &Document) -> bool {
+        let existing = self
+            .0
+            .get_mut(collection)
+            .into_iter()
+            .flatten()
+            .find(|document| document.get("_id") == Some(id));
+        match existing {
+            Some(existing) => {
+                *existing = replacement.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn delete_one(&mut self, collection: &str, filter: &Document) -> Option<Document> {
+        let documents = self.0.get_mut(collection)?;
+        let index = documents
+            .iter()
+            .position(|document| matches(document, filter))?;
+        Some(documents.remove(index))
+    }
+
+    fn delete_many(&mut self, collection: &str, filter: &Document) -> u64 {
+        let documents = match self.0.get_mut(collection) {
+            Some(documents) => documents,
+            None => return 0,
+        };
+        let before = documents.len();
+        documents.retain(|document| !matches(document, filter));
+        (before - documents.len()) as u64
+    }
+
+    fn increment(&mut self, collection: &str, id: &str, field: &str, delta: i32) {
+        self.update_one(
+            collection,
+            &doc! { "_id": id },
+            &doc! { "$inc": { field: delta } },
+        );
+    }
+
+    fn adjust_follow_counters(&mut self, follow: &Document, delta: i32) {
+        for (filter, update) in follow_counter_updates(follow, delta) {
+            self.update_one("users", &filter, &update);
+        }
+    }
+
+    fn apply_updates(&mut self, updates: Vec<(&str, Document, Document)>) {
+        for (collection, filter, update) in updates {
+            self.update_one(collection, &filter, &update);
+        }
+    }
+
+    // The counters and notifications of `post` going live
+    fn apply_publish_side_effects(&mut self, post: &Document) -> Result<()> {
+        self.apply_updates(post_counter_updates(post, 1));
+        let quoted = post
+            .get_str("quoted_post_id")
+            .ok()
+            .and_then(|quoted_post_id| self.by_id("posts", quoted_post_id));
+        for notification in publish_notifications(post, quoted.as_ref()) {
+            self.insert("notifications", notification)?;
+        }
+        Ok(())
+    }
+
+    // Publish the post matching `filter`, returning it as published
+    fn publish(&mut self, filter: &Document) -> Result<Option<Document>> {
+        let now = Utc::now().to_rfc3339();
+        let update = doc! {
+            "$set": {
+                "status": PostStatus::Published.as_str(),
+                "published_at": &now,
+                "created_at": &now,
+            },
+            "$unset": { "publish_at": "" },
+        };
+
+        let post_id = match self.update_one("posts", filter, &update) {
+            Some(before) => before.get_str("_id").unwrap_or_default().to_string(),
+            None => return Ok(None),
+        };
+        let post = self.by_id("posts", &post_id).unwrap_or_default();
+        self.apply_publish_side_effects(&post)?;
+        Ok(Some(post))
+    }
+
+    fn purge_post(&mut self, post_id: &str) {
+        for collection in POST_DEPENDENTS {
+            self.delete_many(collection, &doc! { "post_id": post_id });
+        }
+        self.delete_one("posts", &doc! { "_id": post_id });
+    }
+}
+
+impl MemoryStore {
+    fn collections(&self) -> MutexGuard<'_, Collections> {
+        lock(&self.collections)
+    }
+
+    fn list(&self, collection: &str, filter: &Document, options: &ListOptions) -> Vec<Document> {
+        window(self.collections().find(collection, filter), options)
+    }
+}
+
+#[async_trait]
+impl UserRepo for MemoryStore {
+    async fn find_by_id(&self, user_id: &str) -> Result<Option<Document>> {
+        Ok(self.collections().by_id("users", user_id))
+    }
+
+    async fn find_active(&self, user_id: &str) -> Result<Option<Document>> {
+        Ok(self
+            .collections()
+            .find_one("users", &active_user_filter(user_id)))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<Document>> {
+        Ok(self
+            .collections()
+            .find_one("users", &doc! { "email": email }))
+    }
+
+    async fn count_active(&self, user_ids: &[String]) -> Result<u64> {
+        let filter = doc! { "_id": { "$in": user_ids }, "deleted_at": { "$exists": false } };
+        Ok(self.collections().find("users", &filter).len() as u64)
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        Ok(self.collections().find("users", &filter))
+    }
+
+    async fn insert(&self, user: Document) -> Result<()> {
+        self.collections().insert("users", user)
+    }
+
+    async fn set_deleted(&self, user_id: &str, deletion: Option<&Document>) -> Result<bool> {
+        let (filter, update) = match deletion {
+            Some(deletion) => (active_user_filter(user_id), doc! { "$set": deletion }),
+            None => (
+                doc! { "_id": user_id, "deleted_at": { "$exists": true } },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+            ),
+        };
+        let mut collections = self.collections();
+        if collections.update_one("users", &filter, &update).is_none() {
+            return Ok(false);
+        }
+        for (collection, filter, update) in
+            author_flag_updates(user_id, "author_deleted", deletion.is_some())
+        {
+            collections.update_many(collection, &filter, &update);
+        }
+        Ok(true)
+    }
+
+    async fn set_private(&self, user_id: &str, is_private: bool) -> Result<Option<Document>> {
+        let mut collections = self.collections();
+        let before = collections.update_one(
+            "users",
+            &doc! { "_id": user_id },
+            &doc! { "$set": { "is_private": is_private } },
+        );
+        if before.is_some() {
+            collections.update_many(
+                "posts",
+                &doc! { "user_id": user_id },
+                &doc! { "$set": { "author_private": is_private } },
+            );
+        }
+        Ok(before)
+    }
+
+    async fn set_role(&self, user_id: &str, role: &str) -> Result<Option<Document>> {
+        Ok(self.collections().update_one(
+            "users",
+            &doc! { "_id": user_id },
+            &doc! { "$set": { "role": role } },
+        ))
+    }
+
+    async fn purge(&self, user_id: &str) -> Result<()> {
+        let mut collections = self.collections();
+        // Posts that were still live count towards the quote counts of the posts they quote
+        for post in collections.find("posts", &doc! { "user_id": user_id }) {
+            collections.purge_post(post.get_str("_id").unwrap_or_default());
+            if !post.contains_key("deleted_at") {
+                collections.apply_updates(post_counter_updates(&post, -1));
+            }
+        }
+
+        for (collection, filter) in user_connection_filters(user_id) {
+            while let Some(removed) = collections.delete_one(collection, &filter) {
+                collections.apply_updates(removal_counter_updates(collection, &removed));
+            }
+        }
+
+        let mut archives = lock(&self.archives);
+        while let Some(export) = collections.delete_one("exports", &doc! { "user_id": user_id }) {
+            archives.remove(export.get_str("_id").unwrap_or_default());
+        }
+        collections.delete_one("users", &doc! { "_id": user_id });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PostRepo for MemoryStore {
+    async fn find_by_id(&self, post_id: &str) -> Result<Option<Document>> {
+        Ok(self.collections().by_id("posts", post_id))
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "posts", &filter, &options))
+    }
+
+    async fn insert(&self, post: Document) -> Result<()> {
+        self.collections().insert("posts", post)
+    }
+
+    async fn insert_published(&self, post: Document) -> Result<()> {
+        let mut collections = self.collections();
+        collections.insert("posts", post.clone())?;
+        collections.apply_publish_side_effects(&post)
+    }
+
+    async fn schedule(&self, post_id: &str, publish_at: &str) -> Result<bool> {
+        let update = doc! { "$set": {
+            "status": PostStatus::Scheduled.as_str(),
+            "publish_at": publish_at,
+        } };
+        Ok(self
+            .collections()
+            .update_one("posts", &pending_filter(post_id), &update)
+            .is_some())
+    }
+
+    async fn publish(&self, post_id: &str) -> Result<Option<Document>> {
+        self.collections().publish(&pending_filter(post_id))
+    }
+
+    async fn publish_due(&self, post_id: &str, due_by: &str) -> Result<Option<Document>> {
+        let mut filter = due_filter(due_by);
+        filter.insert("_id", post_id);
+        self.collections().publish(&filter)
+    }
+
+    async fn set_deleted(&self, post: &Document, deletion: Option<&Document>) -> Result<bool> {
+        let post_id = post.get_str("_id").unwrap_or_default();
+        let (filter, update, delta) = match deletion {
+            Some(deletion) => (
+                doc! { "_id": post_id, "deleted_at": { "$exists": false } },
+                doc! { "$set": deletion },
+                -1,
+            ),
+            None => (
+                doc! { "_id": post_id, "deleted_at": post.get_str("deleted_at").unwrap_or_default() },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+                1,
+            ),
+        };
+
+        let mut collections = self.collections();
+        if collections.update_one("posts", &filter, &update).is_none() {
+            return Ok(false);
+        }
+        collections.apply_updates(post_counter_updates(post, delta));
+        Ok(true)
+    }
+
+    async fn purge(&self, post_id: &str) -> Result<()> {
+        self.collections().purge_post(post_id);
+        Ok(())
+    }
+
+    async fn find_vote(&self, vote_id: &str) -> Result<Option<Document>> {
+        Ok(self.collections().by_id("poll_votes", vote_id))
+    }
+
+    async fn vote(&self, vote: Document) -> Result<bool> {
+        let (open_filter, increments) = vote_changes(&vote);
+        let mut collections = self.collections();
+        if let Some(existing) =
+            collections.by_id("poll_votes", vote.get_str("_id").unwrap_or_default())
+        {
+            let id = existing.get("_id").cloned().unwrap_or(Bson::Null);
+            return Err(duplicate_key_error("poll_votes", &id));
+        }
+        if collections
+            .update_one("posts", &open_filter, &doc! { "$inc": increments })
+            .is_none()
+        {
+            return Ok(false);
+        }
+        collections.insert("poll_votes", vote)?;
+        Ok(true)
+    }
+
+    async fn has_reposted(&self, repost_id: &str) -> Result<bool> {
+        Ok(self.collections().by_id("reposts", repost_id).is_some())
+    }
+
+    async fn repost(&self, repost: Document) -> Result<()> {
+        let post_id = repost.get_str("post_id").unwrap_or_default().to_string();
+        let mut collections = self.collections();
+        collections.insert("reposts", repost)?;
+        collections.increment("posts", &post_id, "repost_count", 1);
+        Ok(())
+    }
+
+    async fn undo_repost(&self, repost_id: &str, post_id: &str) -> Result<bool> {
+        let mut collections = self.collections();
+        if collections
+            .delete_one("reposts", &doc! { "_id": repost_id })
+            .is_none()
+        {
+            return Ok(false);
+        }
+        collections.increment("posts", post_id, "repost_count", -1);
+        Ok(true)
+    }
+
+    async fn timeline(
+        &self,
+        author_ids: &[String],
+        hidden: &[String],
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>> {
+        let collections = self.collections();
+        let posts = collections.find("posts", &doc! { "user_id": { "$in": author_ids } });
+        let reposts = collections.find(
+            "reposts",
+            &doc! {
+                "user_id": { "$in": author_ids, "$nin": hidden },
+                "author_suspended": { "$ne": true },
+                "author_deleted": { "$ne": true },
+            },
+        );
+
+        let entries = posts
+            .iter()
+            .map(|post| {
+                doc! {
+                    "post_id": post.get("_id"),
+                    "activity_at": post.get("created_at"),
+                    "reposted_by": Bson::Null,
+                }
+            })
+            .chain(reposts.iter().map(|repost| {
+                doc! {
+                    "post_id": repost.get("post_id"),
+                    "activity_at": repost.get("created_at"),
+                    "reposted_by": repost.get("user_id"),
+                }
+            }))
+            .filter_map(|mut entry| {
+                let post = collections.by_id("posts", entry.get_str("post_id").ok()?)?;
+                entry.insert("post", post);
+                matches(&entry, &post_filter).then_some(entry)
+            })
+            .collect();
+        let mut options = options;
+        options.sort = Some(doc! { "activity_at": -1, "post_id": 1 });
+        Ok(window(entries, &options)
+            .into_iter()
+            .map(|mut entry| {
+                let username = entry
+                    .get_str("reposted_by")
+                    .ok()
+                    .and_then(|user_id| collections.by_id("users", user_id))
+                    .and_then(|user| user.get("username").cloned());
+                if let Some(username) = username {
+                    entry.insert("reposter_username", username);
+                }
+                entry
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CommentRepo for MemoryStore {
+    async fn find_by_id(&self, comment_id: &str) -> Result<Option<Document>> {
+        Ok(self.collections().by_id("comments", comment_id))
+    }
+
+    async fn count_for_post(&self, post_id: &str) -> Result<u64> {
+        let filter = doc! { "post_id": post_id, "deleted_at": { "$exists": false } };
+        Ok(self.collections().find("comments", &filter).len() as u64)
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        Ok(self.collections().find("comments", &filter))
+    }
+
+    async fn list_with_post(
+        &self,
+        filter: Document,
+        post_filter: Document,
+    ) -> Result<Vec<Document>> {
+        let collections = self.collections();
+        Ok(collections
+            .find("comments", &filter)
+            .into_iter()
+            .filter(|comment| {
+                let post_id = comment.get_str("post_id").unwrap_or_default();
+                collections
+                    .by_id("posts", post_id)
+                    .is_some_and(|post| matches(&doc! { "post": post }, &post_filter))
+            })
+            .collect())
+    }
+
+    async fn insert(&self, comment: Document) -> Result<()> {
+        self.collections().insert("comments", comment)
+    }
+
+    async fn set_deleted(&self, comment_id: &str, deletion: Option<&Document>) -> Result<bool> {
+        let (filter, update) = match deletion {
+            Some(deletion) => (
+                doc! { "_id": comment_id, "deleted_at": { "$exists": false } },
+                doc! { "$set": deletion },
+            ),
+            None => (
+                doc! { "_id": comment_id, "deleted_at": { "$exists": true } },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+            ),
+        };
+        Ok(self
+            .collections()
+            .update_one("comments", &filter, &update)
+            .is_some())
+    }
+
+    async fn purge(&self, comment_id: &str, cutoff: &str) -> Result<bool> {
+        let filter = doc! { "_id": comment_id, "deleted_at": { "$lte": cutoff } };
+        Ok(self.collections().delete_one("comments", &filter).is_some())
+    }
+}
+
+#[async_trait]
+impl FollowRepo for MemoryStore {
+    async fn is_following(&self, follower_id: &str, following_id: &str) -> Result<bool> {
+        let filter = doc! { "follower_id": follower_id, "following_id": following_id };
+        Ok(self.collections().find_one("follows", &filter).is_some())
+    }
+
+    async fn is_blocked_between(&self, user_a: &str, user_b: &str) -> Result<bool> {
+        let filter = doc! {
+            "$or": [
+                { "blocker_id": user_a, "blocked_id": user_b },
+                { "blocker_id": user_b, "blocked_id": user_a },
+            ]
+        };
+        Ok(self.collections().find_one("blocks", &filter).is_some())
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        Ok(self.collections().find("follows", &filter))
+    }
+
+    async fn insert(&self, follow: Document) -> Result<()> {
+        let mut collections = self.collections();
+        collections.insert("follows", follow.clone())?;
+        collections.adjust_follow_counters(&follow, 1);
+        Ok(())
+    }
+
+    async fn insert_request(&self, request: Document) -> Result<()> {
+        self.collections().insert("follow_requests", request)
+    }
+
+    async fn list_requests(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(
+            self,
+            "follow_requests",
+            &filter,
+            &options,
+        ))
+    }
+
+    async fn accept_request(&self, request_id: &str, follow: Document) -> Result<bool> {
+        let mut collections = self.collections();
+        if collections
+            .delete_one("follow_requests", &doc! { "_id": request_id })
+            .is_none()
+        {
+            return Ok(false);
+        }
+        collections.insert("follows", follow.clone())?;
+        collections.adjust_follow_counters(&follow, 1);
+        Ok(true)
+    }
+
+    async fn reject_request(&self, request_id: &str) -> Result<Option<Document>> {
+        Ok(self
+            .collections()
+            .delete_one("follow_requests", &doc! { "_id": request_id }))
+    }
+}
+
+#[async_trait]
+impl LikeRepo for MemoryStore {
+    async fn has_liked(&self, post_id: &str, user_id: &str) -> Result<bool> {
+        let filter = doc! { "post_id": post_id, "user_id": user_id };
+        Ok(self.collections().find_one("likes", &filter).is_some())
+    }
+
+    async fn insert(&self, like: Document) -> Result<()> {
+        self.collections().insert("likes", like)
+    }
+}
+
+#[async_trait]
+impl BookmarkRepo for MemoryStore {
+    async fn is_bookmarked(&self, post_id: &str, user_id: &str) -> Result<bool> {
+        let filter = doc! { "post_id": post_id, "user_id": user_id };
+        Ok(self.collections().find_one("bookmarks", &filter).is_some())
+    }
+
+    async fn insert(&self, bookmark: Document) -> Result<()> {
+        self.collections().insert("bookmarks", bookmark)
+    }
+
+    async fn remove(&self, filter: Document) -> Result<u64> {
+        Ok(self.collections().delete_many("bookmarks", &filter))
+    }
+
+    async fn list_posts(
+        &self,
+        filter: Document,
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>> {
+        let collections = self.collections();
+        let mut bookmarks = collections.find("bookmarks", &filter);
+        sort(&mut bookmarks, &doc! { "created_at": -1 });
+
+        // Each post once, saved at its most recent bookmark
+        let mut entries: Vec<Document> = Vec::new();
+        for bookmark in bookmarks {
+            let post_id = bookmark.get_str("post_id").unwrap_or_default();
+            if entries
+                .iter()
+                .any(|entry| entry.get_str("_id") == Ok(post_id))
+            {
+                continue;
+            }
+            if let Some(post) = collections.by_id("posts", post_id) {
+                let entry = doc! {
+                    "_id": post_id,
+                    "saved_at": bookmark.get("created_at"),
+                    "post": post,
+                };
+                if matches(&entry, &post_filter) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        let mut options = options;
+        options.sort = Some(doc! { "saved_at": -1, "_id": 1 });
+        Ok(window(entries, &options)
+            .into_iter()
+            .filter_map(|mut entry| match entry.remove("post") {
+                Some(Bson::Document(post)) => Some(post),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn collections(&self, user_id: &str) -> Result<Vec<Document>> {
+        let mut counts: Vec<(String, i32)> = Vec::new();
+        for bookmark in self
+            .collections()
+            .find("bookmarks", &doc! { "user_id": user_id })
+        {
+            let name = bookmark.get_str("collection").unwrap_or_default();
+            match counts.iter_mut().find(|(existing, _)| existing == name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((name.to_string(), 1)),
+            }
+        }
+        counts.sort();
+        Ok(counts
+            .into_iter()
+            .map(|(name, count)| doc! { "_id": name, "count": count })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl NotificationRepo for MemoryStore {
+    async fn insert(&self, notification: Document) -> Result<()> {
+        self.collections().insert("notifications", notification)
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "notifications", &filter, &options))
+    }
+}
+
+#[async_trait]
+impl BlockRepo for MemoryStore {
+    async fn block(&self, block: Document) -> Result<()> {
+        let blocker_id = block.get_str("blocker_id").unwrap_or_default().to_string();
+        let blocked_id = block.get_str("blocked_id").unwrap_or_default().to_string();
+        let between = doc! {
+            "$or": [
+                { "follower_id": &blocker_id, "following_id": &blocked_id },
+                { "follower_id": &blocked_id, "following_id": &blocker_id },
+            ]
+        };
+
+        let mut collections = self.collections();
+        collections.insert("blocks", block)?;
+        while let Some(follow) = collections.delete_one("follows", &between) {
+            collections.adjust_follow_counters(&follow, -1);
+        }
+        collections.delete_many("follow_requests", &between);
+        Ok(())
+    }
+
+    async fn unblock(&self, block_id: &str) -> Result<Option<Document>> {
+        Ok(self
+            .collections()
+            .delete_one("blocks", &doc! { "_id": block_id }))
+    }
+
+    async fn list_blocks(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "blocks", &filter, &options))
+    }
+
+    async fn mute(&self, mute: Document) -> Result<()> {
+        self.collections().insert("mutes", mute)
+    }
+
+    async fn unmute(&self, mute_id: &str) -> Result<Option<Document>> {
+        Ok(self
+            .collections()
+            .delete_one("mutes", &doc! { "_id": mute_id }))
+    }
+
+    async fn list_mutes(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "mutes", &filter, &options))
+    }
+}
+
+#[async_trait]
+impl ReportRepo for MemoryStore {
+    async fn find_by_id(&self, report_id: &str) -> Result<Option<Document>> {
+        Ok(self.collections().by_id("reports", report_id))
+    }
+
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>> {
+        Ok(self.collections().find_one("reports", &filter))
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "reports", &filter, &options))
+    }
+
+    async fn history(&self, report_id: &str) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(
+            self,
+            "moderation_log",
+            &doc! { "report_id": report_id },
+            &ListOptions::sorted(doc! { "created_at": 1 }),
+        ))
+    }
+
+    async fn create(&self, report: Document, log: Document) -> Result<()> {
+        let mut collections = self.collections();
+        collections.insert("reports", report)?;
+        collections.insert("moderation_log", log)
+    }
+
+    async fn claim(&self, report_id: &str, moderator_id: &str, log: Document) -> Result<bool> {
+        let mut collections = self.collections();
+        let claimed = collections.update_one(
+            "reports",
+            &doc! { "_id": report_id, "status": ReportStatus::Open.as_str() },
+            &doc! { "$set": {
+                "status": ReportStatus::Claimed.as_str(),
+                "claimed_by": moderator_id,
+                "claimed_at": Utc::now().to_rfc3339(),
+            } },
+        );
+        if claimed.is_none() {
+            return Ok(false);
+        }
+        collections.insert("moderation_log", log)?;
+        Ok(true)
+    }
+
+    async fn resolve(
+        &self,
+        report_id: &str,
+        moderator_id: &str,
+        update: Document,
+        content_update: Option<ContentUpdate>,
+        log: Document,
+    ) -> Result<bool> {
+        let mut collections = self.collections();
+        let resolved = collections.update_one(
+            "reports",
+            &doc! {
+                "_id": report_id,
+                "status": ReportStatus::Claimed.as_str(),
+                "claimed_by": moderator_id,
+            },
+            &update,
+        );
+        if resolved.is_none() {
+            return Ok(false);
+        }
+        if let Some(content_update) = content_update {
+            let (collection, id, content) = &content_update;
+            collections.update_one(collection, &doc! { "_id": id }, content);
+            for (collection, filter, flags) in moderation_flag_updates(&content_update) {
+                collections.update_many(collection, &filter, &flags);
+            }
+        }
+        collections.insert("moderation_log", log)?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl AuditRepo for MemoryStore {
+    async fn insert(&self, entry: Document) -> Result<()> {
+        self.collections().insert("audit_log", entry)
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        Ok(MemoryStore::list(self, "audit_log", &filter, &options))
+    }
+}
+
+#[async_trait]
+impl ExportRepo for MemoryStore {
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>> {
+        Ok(self.collections().find_one("exports", &filter))
+    }
+
+    async fn insert(&self, export: Document) -> Result<()> {
+        self.collections().insert("exports", export)
+    }
+
+    async fn update(&self, export_id: &str, fields: Document) -> Result<()> {
+        self.collections().update_one(
+            "exports",
+            &doc! { "_id": export_id },
+            &doc! { "$set": fields },
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, filter: Document) -> Result<()> {
+        let mut collections = self.collections();
+        let mut archives = lock(&self.archives);
+        while let Some(export) = collections.delete_one("exports", &filter) {
+            archives.remove(export.get_str("_id").unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    async fn collect(&self, collection: &str, filter: Document) -> Result<Vec<Document>> {
+        Ok(self.collections().find(collection, &filter))
+    }
+
+    async fn store_archive(&self, export_id: &str, _user_id: &str, archive: &[u8]) -> Result<()> {
+        lock(&self.archives).insert(export_id.to_string(), archive.to_vec());
+        Ok(())
+    }
+
+    async fn load_archive(&self, export_id: &str) -> Result<Vec<u8>> {
+        lock(&self.archives)
+            .get(export_id)
+            .cloned()
+            .ok_or_else(|| Error::custom(format!("No archive stored for export {}", export_id)))
+    }
+}
+
+#[async_trait]
+impl SeedRepo for MemoryStore {
+    async fn find_job(&self, filter: Document) -> Result<Option<Document>> {
+        Ok(self.collections().find_one("seed_jobs", &filter))
+    }
+
+    async fn insert_job(&self, job: Document) -> Result<()> {
+        self.collections().insert("seed_jobs", job)
+    }
+
+    async fn update_job(&self, job_id: &str, fields: Document) -> Result<()> {
+        let mut collections = self.collections();
+        if let Ok(updated_at) = fields.get_str("updated_at") {
+            collections.update_one(
+                "locks",
+                &doc! { "_id": SEED_LOCK, "job_id": job_id },
+                &doc! { "$set": { "updated_at": updated_at } },
+            );
+        }
+        collections.update_one(
+            "seed_jobs",
+            &doc! { "_id": job_id },
+            &doc! { "$set": fields },
+        );
+        Ok(())
+    }
+
+    async fn lock(&self, job_id: &str, stale_before: &str) -> Result<Option<String>> {
+        let mut collections = self.collections();
+        let live = doc! { "_id": SEED_LOCK, "updated_at": { "$gt": stale_before } };
+        if let Some(holder) = collections.find_one("locks", &live) {
+            return Ok(Some(
+                holder.get_str("job_id").unwrap_or_default().to_string(),
+            ));
+        }
+        collections.delete_one("locks", &doc! { "_id": SEED_LOCK });
+        collections.insert(
+            "locks",
+            doc! { "_id": SEED_LOCK, "job_id": job_id, "updated_at": Utc::now().to_rfc3339() },
+        )?;
+        Ok(None)
+    }
+
+    async fn unlock(&self, job_id: &str) -> Result<()> {
+        self.collections()
+            .delete_one("locks", &doc! { "_id": SEED_LOCK, "job_id": job_id });
+        Ok(())
+    }
+
+    async fn clear_data(&self) -> Result<Document> {
+        let mut collections = self.collections();
+        let mut removed = Document::new();
+        for collection in DATA_COLLECTIONS {
+            let count = collections.delete_many(collection, &cleared_filter(collection));
+            removed.insert(collection, count as i64);
+        }
+        collections.delete_many("exports", &doc! {});
+        lock(&self.archives).clear();
+        Ok(removed)
+    }
+
+    // Like an unordered insert_many, a duplicate does not stop the rest of the batch
+    async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<()> {
+        let mut collections = self.collections();
+        let mut first_error = None;
+        for document in documents {
+            if let Err(e) = collections.insert(collection, document) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+#[async_trait]
+impl TransferRepo for MemoryStore {
+    async fn for_each(
+        &self,
+        collection: &str,
+        _batch_size: u32,
+        each: &mut (dyn FnMut(Document) -> std::io::Result<()> + Send),
+    ) -> Result<u64> {
+        let documents = self.collections().all(collection).to_vec();
+        let count = documents.len() as u64;
+        for document in documents {
+            each(document)?;
+        }
+        Ok(count)
+    }
+
+    async fn write_batch(
+        &self,
+        collection: &str,
+        documents: Vec<Document>,
+        mode: ConflictMode,
+    ) -> Result<ImportSummary> {
+        let mut collections = self.collections();
+        let mut summary = ImportSummary::default();
+        for document in documents {
+            let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+            if mode == ConflictMode::Upsert && collections.replace(collection, &id, &document) {
+                summary.replaced += 1;
+                continue;
+            }
+            match collections.insert(collection, document) {
+                Ok(()) => summary.inserted += 1,
+                Err(_) if mode == ConflictMode::Skip => summary.skipped += 1,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(summary)
+    }
+}
+
+#[async_trait]
+impl DatabaseRepo for MemoryStore {
+    fn name(&self) -> String {
+        "memory".to_string()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    // A single member that is always the healthy primary
+    async fn cluster_status(&self) -> Result<ClusterStatus> {
+        let now = Utc::now().to_rfc3339();
+        Ok(ClusterStatus {
+            set: self.name(),
+            term: None,
+            primary: Some(self.name()),
+            checked_at: Some(now.clone()),
+            members: vec![MemberStatus {
+                name: self.name(),
+                state: "PRIMARY".to_string(),
+                healthy: true,
+                is_self: true,
+                uptime_secs: None,
+                last_applied: Some(now),
+                lag_secs: Some(0),
+                last_heartbeat: None,
+                ping_ms: None,
+                sync_source: None,
+            }],
+        })
+    }
+}
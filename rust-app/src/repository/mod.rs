@@ -0,0 +1,561 @@
+use crate::{
+    cluster::ClusterStatus,
+    models::{NotificationKind, PostStatus, UserRole},
+    publishing::notification_doc,
+    transfer::{ConflictMode, ImportSummary},
+};
+use async_trait::async_trait;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::Result,
+};
+
+mod memory;
+mod mongo;
+mod query;
+
+pub use memory::MemoryStore;
+pub use mongo::MongoStore;
+
+// Storage for everything the API reads and writes, kept behind traits so the API can run
+// against MongoDB or an in-memory store. Documents keep the shape they are stored in, and
+// list filters are MongoDB query documents, which the in-memory store evaluates itself.
+// Writes that must happen together are single methods so each store can make them atomic.
+
+// Order and window of a list query. The default returns every match in storage order.
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    pub sort: Option<Document>,
+    pub skip: u64,
+    pub limit: Option<u64>,
+}
+
+impl ListOptions {
+    pub fn sorted(sort: Document) -> ListOptions {
+        ListOptions {
+            sort: Some(sort),
+            ..ListOptions::default()
+        }
+    }
+
+    // Page `page` (counting from 1) of `limit` entries
+    pub fn page(mut self, page: u64, limit: u64) -> ListOptions {
+        self.skip = (page - 1) * limit;
+        self.limit = Some(limit);
+        self
+    }
+}
+
+// Documents written by a user carry denormalized `author_suspended` and `author_deleted`
+// flags so list queries can leave them out without loading every suspended or deleted
+// account. Each entry is a collection and the field naming the user who wrote it.
+const AUTHORED_COLLECTIONS: [(&str, &str); 4] = [
+    ("posts", "user_id"),
+    ("comments", "user_id"),
+    ("reposts", "user_id"),
+    ("notifications", "actor_id"),
+];
+
+// The updates setting one of the author flags on everything `user_id` wrote
+fn author_flag_updates(
+    user_id: &str,
+    flag: &str,
+    value: bool,
+) -> Vec<(&'static str, Document, Document)> {
+    AUTHORED_COLLECTIONS
+        .iter()
+        .map(|(collection, field)| {
+            (
+                *collection,
+                doc! { *field: user_id },
+                doc! { "$set": { flag: value } },
+            )
+        })
+        .collect()
+}
+
+// The `$inc` updates moving the follower and following counters of the two users in
+// `follow` by `delta`, as filter and update pairs on users
+fn follow_counter_updates(follow: &Document, delta: i32) -> [(Document, Document); 2] {
+    let follower_id = follow.get_str("follower_id").unwrap_or_default();
+    let following_id = follow.get_str("following_id").unwrap_or_default();
+    [
+        (
+            doc! { "_id": follower_id },
+            doc! { "$inc": { "following_count": delta } },
+        ),
+        (
+            doc! { "_id": following_id },
+            doc! { "$inc": { "follower_count": delta } },
+        ),
+    ]
+}
+
+// The `$inc` updates moving a published post's share of its author's post count and the
+// quoted post's quote count by `delta`, as collection, filter and update triples
+fn post_counter_updates(post: &Document, delta: i32) -> Vec<(&'static str, Document, Document)> {
+    if PostStatus::parse(post.get_str("status").unwrap_or_default()) != PostStatus::Published {
+        return Vec::new();
+    }
+
+    let mut updates = vec![(
+        "users",
+        doc! { "_id": post.get_str("user_id").unwrap_or_default() },
+        doc! { "$inc": { "post_count": delta } },
+    )];
+    if let Ok(quoted_post_id) = post.get_str("quoted_post_id") {
+        updates.push((
+            "posts",
+            doc! { "_id": quoted_post_id },
+            doc! { "$inc": { "quote_count": delta } },
+        ));
+    }
+    updates
+}
+
+// The notifications of `post` going live: one for each mentioned user and one for the
+// author of `quoted`, the post it quotes
+fn publish_notifications(post: &Document, quoted: Option<&Document>) -> Vec<Document> {
+    let post_id = post.get_str("_id").unwrap_or_default();
+    let user_id = post.get_str("user_id").unwrap_or_default();
+
+    let mentions = post.get_array("mentions").into_iter().flatten();
+    let mut notifications: Vec<Document> = mentions
+        .filter_map(Bson::as_str)
+        .filter_map(|mentioned_id| {
+            notification_doc(
+                post_id,
+                mentioned_id,
+                user_id,
+                NotificationKind::Mention,
+                Some(post_id),
+            )
+        })
+        .collect();
+
+    if let Some(quoted) = quoted {
+        notifications.extend(notification_doc(
+            post_id,
+            quoted.get_str("user_id").unwrap_or_default(),
+            user_id,
+            NotificationKind::Quote,
+            quoted.get_str("_id").ok(),
+        ));
+    }
+    notifications
+}
+
+// Collections whose documents only make sense alongside the post they reference
+const POST_DEPENDENTS: [&str; 6] = [
+    "reposts",
+    "likes",
+    "comments",
+    "poll_votes",
+    "bookmarks",
+    "notifications",
+];
+
+// What purging `user_id` removes besides their posts, as collections and the filters
+// matching the user's documents in them
+fn user_connection_filters(user_id: &str) -> Vec<(&'static str, Document)> {
+    vec![
+        ("comments", doc! { "user_id": user_id }),
+        ("likes", doc! { "user_id": user_id }),
+        ("reposts", doc! { "user_id": user_id }),
+        ("poll_votes", doc! { "user_id": user_id }),
+        ("follows", doc! { "follower_id": user_id }),
+        ("follows", doc! { "following_id": user_id }),
+        ("bookmarks", doc! { "user_id": user_id }),
+        (
+            "notifications",
+            doc! { "$or": [{ "user_id": user_id }, { "actor_id": user_id }] },
+        ),
+        (
+            "follow_requests",
+            doc! { "$or": [{ "follower_id": user_id }, { "following_id": user_id }] },
+        ),
+        (
+            "blocks",
+            doc! { "$or": [{ "blocker_id": user_id }, { "blocked_id": user_id }] },
+        ),
+        (
+            "mutes",
+            doc! { "$or": [{ "muter_id": user_id }, { "muted_id": user_id }] },
+        ),
+    ]
+}
+
+// Connections that added to counters elsewhere, so they are removed one at a time
+const COUNTED_CONNECTIONS: [&str; 4] = ["likes", "reposts", "poll_votes", "follows"];
+
+// The `$inc` updates taking back what `removed`, a document of `collection`, added to the
+// counters on posts and users
+fn removal_counter_updates(
+    collection: &str,
+    removed: &Document,
+) -> Vec<(&'static str, Document, Document)> {
+    let post_filter = doc! { "_id": removed.get_str("post_id").unwrap_or_default() };
+    match collection {
+        "likes" => vec![("posts", post_filter, doc! { "$inc": { "like_count": -1 } })],
+        "reposts" => vec![(
+            "posts",
+            post_filter,
+            doc! { "$inc": { "repost_count": -1 } },
+        )],
+        "poll_votes" => {
+            let mut decrements = doc! { "poll.total_votes": -1 };
+            let indexes = removed.get_array("option_indexes").into_iter().flatten();
+            for index in indexes.filter_map(Bson::as_i32) {
+                decrements.insert(format!("poll.options.{}.vote_count", index), -1);
+            }
+            vec![("posts", post_filter, doc! { "$inc": decrements })]
+        }
+        "follows" => follow_counter_updates(removed, -1)
+            .into_iter()
+            .map(|(filter, update)| ("users", filter, update))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Filter matching a post that has not gone live yet
+fn pending_filter(post_id: &str) -> Document {
+    doc! {
+        "_id": post_id,
+        "status": { "$in": [PostStatus::Draft.as_str(), PostStatus::Scheduled.as_str()] },
+    }
+}
+
+// The documents of `collection` that clearing the data removes. Admin accounts stay so
+// whoever seeded or cleaned the database can still call the admin-only test endpoints.
+fn cleared_filter(collection: &str) -> Document {
+    match collection {
+        "users" => doc! { "role": { "$ne": UserRole::Admin.as_str() } },
+        _ => doc! {},
+    }
+}
+
+// The singleton document in the `locks` collection held by the running seed job
+const SEED_LOCK: &str = "seed";
+
+// The filter matching the poll while it is still open at the vote's `created_at`, and the
+// `$inc` adding the vote to the tallies
+fn vote_changes(vote: &Document) -> (Document, Document) {
+    let created_at = vote.get_str("created_at").unwrap_or_default();
+    let open_filter = doc! {
+        "_id": vote.get_str("post_id").unwrap_or_default(),
+        "$or": [
+            { "poll.closes_at": Bson::Null },
+            { "poll.closes_at": { "$gt": created_at } },
+        ],
+    };
+
+    let mut increments = doc! { "poll.total_votes": 1 };
+    let indexes = vote.get_array("option_indexes").into_iter().flatten();
+    for index in indexes.filter_map(Bson::as_i32) {
+        increments.insert(format!("poll.options.{}.vote_count", index), 1);
+    }
+    (open_filter, increments)
+}
+
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    // Any user with this ID, including suspended and soft-deleted accounts
+    async fn find_by_id(&self, user_id: &str) -> Result<Option<Document>>;
+    // The user unless they have been soft-deleted
+    async fn find_active(&self, user_id: &str) -> Result<Option<Document>>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<Document>>;
+    // How many of `user_ids` belong to users that have not been soft-deleted
+    async fn count_active(&self, user_ids: &[String]) -> Result<u64>;
+    async fn list(&self, filter: Document) -> Result<Vec<Document>>;
+    async fn insert(&self, user: Document) -> Result<()>;
+    // Soft-delete an active account with `deletion`, or restore a deleted one when None,
+    // keeping the author flags on their content in step. Returns false when the account
+    // was not in the state the change expects.
+    async fn set_deleted(&self, user_id: &str, deletion: Option<&Document>) -> Result<bool>;
+    // Make the account private or public, keeping the flag denormalized onto their posts
+    // in step. Returns the user as it was before.
+    async fn set_private(&self, user_id: &str, is_private: bool) -> Result<Option<Document>>;
+    // Returns the user as it was before
+    async fn set_role(&self, user_id: &str, role: &str) -> Result<Option<Document>>;
+    // Hard-delete a user with their content and connections, keeping the counters on
+    // everyone else's documents in step. Each removal is done one document at a time so a
+    // purge interrupted halfway can be re-run without decrementing anything twice.
+    async fn purge(&self, user_id: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait PostRepo: Send + Sync {
+    async fn find_by_id(&self, post_id: &str) -> Result<Option<Document>>;
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+    // Store a draft or scheduled post, which has no side effects until it goes live
+    async fn insert(&self, post: Document) -> Result<()>;
+    // Store a published post together with the counters and notifications of it going live
+    async fn insert_published(&self, post: Document) -> Result<()>;
+    // Schedule a draft or scheduled post for `publish_at`. Returns false once it is live.
+    async fn schedule(&self, post_id: &str, publish_at: &str) -> Result<bool>;
+    // Publish a draft or scheduled post with its side effects, returning it as published.
+    // Returns None when it is already live, e.g. because the scheduler got there first.
+    async fn publish(&self, post_id: &str) -> Result<Option<Document>>;
+    // Publish a scheduled post whose `publish_at` is at or before `due_by`, like `publish`.
+    // Returns None when it is no longer due, so a post is never published twice.
+    async fn publish_due(&self, post_id: &str, due_by: &str) -> Result<Option<Document>>;
+    // Soft-delete `post` with `deletion`, or restore it when None, moving its share of the
+    // counters along. Returns false when another request changed it first.
+    async fn set_deleted(&self, post: &Document, deletion: Option<&Document>) -> Result<bool>;
+    // Hard-delete a post and everything that references it. Counters the post contributed
+    // to elsewhere were already adjusted when it was soft-deleted.
+    async fn purge(&self, post_id: &str) -> Result<()>;
+    async fn find_vote(&self, vote_id: &str) -> Result<Option<Document>>;
+    // Store a poll vote and add its options to the tallies, unless the poll closed before
+    // the vote's `created_at`. Returns false when it had.
+    async fn vote(&self, vote: Document) -> Result<bool>;
+    async fn has_reposted(&self, repost_id: &str) -> Result<bool>;
+    // Store a repost and bump the repost count of the original
+    async fn repost(&self, repost: Document) -> Result<()>;
+    // Returns false when there was no such repost
+    async fn undo_repost(&self, repost_id: &str, post_id: &str) -> Result<bool>;
+    // Posts by `author_ids` merged with reposts by them, leaving out reposts by `hidden`,
+    // suspended and deleted users, newest first. Entries hold the `post_id`, the
+    // `activity_at`, the `post` itself and, for reposts, `reposted_by` with the
+    // `reposter_username`; `reposted_by` is null for the author's own posts. Only entries
+    // whose post matches `post_filter`, which addresses the post fields as "post.<field>",
+    // are included, and they are filtered before the page is cut.
+    async fn timeline(
+        &self,
+        author_ids: &[String],
+        hidden: &[String],
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>>;
+}
+
+#[async_trait]
+pub trait CommentRepo: Send + Sync {
+    async fn find_by_id(&self, comment_id: &str) -> Result<Option<Document>>;
+    // Comments on the post that have not been soft-deleted
+    async fn count_for_post(&self, post_id: &str) -> Result<u64>;
+    async fn list(&self, filter: Document) -> Result<Vec<Document>>;
+    // Comments matching `filter` whose post matches `post_filter`, which addresses the
+    // post fields as "post.<field>"
+    async fn list_with_post(
+        &self,
+        filter: Document,
+        post_filter: Document,
+    ) -> Result<Vec<Document>>;
+    async fn insert(&self, comment: Document) -> Result<()>;
+    // Soft-delete a comment with `deletion`, or restore it when None. Returns false when
+    // the comment was not in the state the change expects.
+    async fn set_deleted(&self, comment_id: &str, deletion: Option<&Document>) -> Result<bool>;
+    // Hard-delete a comment soft-deleted at or before `cutoff`. Returns false when it was
+    // restored or purged in the meantime.
+    async fn purge(&self, comment_id: &str, cutoff: &str) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait FollowRepo: Send + Sync {
+    async fn is_following(&self, follower_id: &str, following_id: &str) -> Result<bool>;
+    // Blocks hide users from each other regardless of who blocked whom
+    async fn is_blocked_between(&self, user_a: &str, user_b: &str) -> Result<bool>;
+    async fn list(&self, filter: Document) -> Result<Vec<Document>>;
+    // Store a follow and count it on both users
+    async fn insert(&self, follow: Document) -> Result<()>;
+    async fn insert_request(&self, request: Document) -> Result<()>;
+    async fn list_requests(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+    // Replace a pending request with `follow`, counting it on both users. Returns false
+    // when none was pending.
+    async fn accept_request(&self, request_id: &str, follow: Document) -> Result<bool>;
+    // Returns the removed request
+    async fn reject_request(&self, request_id: &str) -> Result<Option<Document>>;
+}
+
+#[async_trait]
+pub trait LikeRepo: Send + Sync {
+    async fn has_liked(&self, post_id: &str, user_id: &str) -> Result<bool>;
+    async fn insert(&self, like: Document) -> Result<()>;
+}
+
+#[async_trait]
+pub trait BookmarkRepo: Send + Sync {
+    async fn is_bookmarked(&self, post_id: &str, user_id: &str) -> Result<bool>;
+    async fn insert(&self, bookmark: Document) -> Result<()>;
+    // Returns how many bookmarks were removed
+    async fn remove(&self, filter: Document) -> Result<u64>;
+    // Each post bookmarked under `filter` once, most recently saved first, among those
+    // matching `post_filter`, which addresses the post fields as "post.<field>"
+    async fn list_posts(
+        &self,
+        filter: Document,
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>>;
+    // The user's collections as { _id: name, count } in name order
+    async fn collections(&self, user_id: &str) -> Result<Vec<Document>>;
+}
+
+#[async_trait]
+pub trait NotificationRepo: Send + Sync {
+    async fn insert(&self, notification: Document) -> Result<()>;
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+}
+
+// Blocks and mutes
+#[async_trait]
+pub trait BlockRepo: Send + Sync {
+    // Store a block and cut every follow and pending follow request between the two users,
+    // taking the cut follows off their counters
+    async fn block(&self, block: Document) -> Result<()>;
+    // Returns the removed block
+    async fn unblock(&self, block_id: &str) -> Result<Option<Document>>;
+    async fn list_blocks(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+    async fn mute(&self, mute: Document) -> Result<()>;
+    // Returns the removed mute
+    async fn unmute(&self, mute_id: &str) -> Result<Option<Document>>;
+    async fn list_mutes(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+}
+
+// A change moderation applies to reported content: collection, document ID and update
+pub type ContentUpdate = (&'static str, String, Document);
+
+// The author flag updates that go with a moderation change, as suspending an account
+// hides everything they wrote
+fn moderation_flag_updates(
+    content_update: &ContentUpdate,
+) -> Vec<(&'static str, Document, Document)> {
+    match content_update {
+        ("users", user_id, _) => author_flag_updates(user_id, "author_suspended", true),
+        _ => Vec::new(),
+    }
+}
+
+// Reports and the moderation log of every step they go through
+#[async_trait]
+pub trait ReportRepo: Send + Sync {
+    async fn find_by_id(&self, report_id: &str) -> Result<Option<Document>>;
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>>;
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+    // The moderation log entries of a report, oldest first
+    async fn history(&self, report_id: &str) -> Result<Vec<Document>>;
+    // Store a new report with its first log entry
+    async fn create(&self, report: Document, log: Document) -> Result<()>;
+    // Claim an open report for `moderator_id`. Returns false when it was not open.
+    async fn claim(&self, report_id: &str, moderator_id: &str, log: Document) -> Result<bool>;
+    // Resolve a report claimed by `moderator_id` with `update`, applying the moderation
+    // action to the content and, for suspensions, flagging the user's content. Returns
+    // false when the report was not claimed by them.
+    async fn resolve(
+        &self,
+        report_id: &str,
+        moderator_id: &str,
+        update: Document,
+        content_update: Option<ContentUpdate>,
+        log: Document,
+    ) -> Result<bool>;
+}
+
+#[async_trait]
+pub trait AuditRepo: Send + Sync {
+    async fn insert(&self, entry: Document) -> Result<()>;
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>>;
+}
+
+// Personal data export jobs and their archives
+#[async_trait]
+pub trait ExportRepo: Send + Sync {
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>>;
+    async fn insert(&self, export: Document) -> Result<()>;
+    // Apply `fields` to the export job with `$set`
+    async fn update(&self, export_id: &str, fields: Document) -> Result<()>;
+    // Remove the export jobs matching `filter` together with their archives
+    async fn delete(&self, filter: Document) -> Result<()>;
+    // Documents of any data collection, for building an archive
+    async fn collect(&self, collection: &str, filter: Document) -> Result<Vec<Document>>;
+    async fn store_archive(&self, export_id: &str, user_id: &str, archive: &[u8]) -> Result<()>;
+    async fn load_archive(&self, export_id: &str) -> Result<Vec<u8>>;
+}
+
+// Seed jobs and the bulk writes they make
+#[async_trait]
+pub trait SeedRepo: Send + Sync {
+    async fn find_job(&self, filter: Document) -> Result<Option<Document>>;
+    async fn insert_job(&self, job: Document) -> Result<()>;
+    // Apply `fields` to the seed job with `$set`. An `updated_at` among them also refreshes
+    // the seeding lock when the job holds it.
+    async fn update_job(&self, job_id: &str, fields: Document) -> Result<()>;
+    // Atomically take the lock that lets one seed job run at a time for `job_id`, unless
+    // a job that refreshed it after `stale_before` holds it. Returns that job's ID then.
+    async fn lock(&self, job_id: &str, stale_before: &str) -> Result<Option<String>>;
+    // Release the lock if `job_id` still holds it
+    async fn unlock(&self, job_id: &str) -> Result<()>;
+    // Empty every data collection except for the admin accounts and remove all exports.
+    // Returns how many documents each collection lost. The audit log and the seed jobs
+    // are kept.
+    async fn clear_data(&self) -> Result<Document>;
+    async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<()>;
+}
+
+// Whole collections copied by the `export` and `import` subcommands
+#[async_trait]
+pub trait TransferRepo: Send + Sync {
+    // Pass every document of `collection` to `each`, fetching `batch_size` per round trip.
+    // Returns how many there were.
+    async fn for_each(
+        &self,
+        collection: &str,
+        batch_size: u32,
+        each: &mut (dyn FnMut(Document) -> std::io::Result<()> + Send),
+    ) -> Result<u64>;
+    // Write `documents` in one go, settling documents whose `_id` already exists as `mode`
+    // says. A failed write in `Fail` mode keeps the documents before the conflict.
+    async fn write_batch(
+        &self,
+        collection: &str,
+        documents: Vec<Document>,
+        mode: ConflictMode,
+    ) -> Result<ImportSummary>;
+}
+
+// The database as a whole
+#[async_trait]
+pub trait DatabaseRepo: Send + Sync {
+    fn name(&self) -> String;
+    async fn ping(&self) -> Result<()>;
+    async fn cluster_status(&self) -> Result<ClusterStatus>;
+}
+
+// Everything a store has to provide to back the whole API
+pub trait Store:
+    UserRepo
+    + PostRepo
+    + CommentRepo
+    + FollowRepo
+    + LikeRepo
+    + BookmarkRepo
+    + NotificationRepo
+    + BlockRepo
+    + ReportRepo
+    + AuditRepo
+    + ExportRepo
+    + SeedRepo
+    + DatabaseRepo
+    + 'static
+{
+}
+
+impl<S> Store for S where
+    S: UserRepo
+        + PostRepo
+        + CommentRepo
+        + FollowRepo
+        + LikeRepo
+        + BookmarkRepo
+        + NotificationRepo
+        + BlockRepo
+        + ReportRepo
+        + AuditRepo
+        + ExportRepo
+        + SeedRepo
+        + DatabaseRepo
+        + 'static
+{
+}
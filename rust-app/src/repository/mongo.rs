@@ -0,0 +1,1271 @@
+use super::{
+    author_flag_updates, cleared_filter, follow_counter_updates, moderation_flag_updates,
+    pending_filter, post_counter_updates, publish_notifications, removal_counter_updates,
+    user_connection_filters, vote_changes, AuditRepo, BlockRepo, BookmarkRepo, CommentRepo,
+    ContentUpdate, DatabaseRepo, ExportRepo, FollowRepo, LikeRepo, ListOptions, NotificationRepo,
+    PostRepo, ReportRepo, SeedRepo, TransferRepo, UserRepo, COUNTED_CONNECTIONS, POST_DEPENDENTS,
+    SEED_LOCK,
+};
+use crate::{
+    cluster::{fetch_cluster_status, ClusterStatus},
+    errors::is_duplicate_key_error,
+    exports::{delete_exports, load_archive, store_archive},
+    models::{PostStatus, ReportStatus},
+    publishing::due_filter,
+    transfer::{ConflictMode, ImportSummary, DATA_COLLECTIONS},
+    visibility::active_user_filter,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::{FutureExt, TryStreamExt};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::{ErrorKind, Result, TRANSIENT_TRANSACTION_ERROR},
+    options::{ReadPreference, ReplaceOneModel, ReturnDocument, SelectionCriteria},
+    ClientSession, Collection, Database,
+};
+
+// How often a poll vote is retried when its transaction hits a transient error
+const VOTE_ATTEMPTS: usize = 3;
+
+// Repositories backed by the MongoDB collections of the same name
+pub struct MongoStore {
+    db: Database,
+}
+
+impl MongoStore {
+    pub fn new(db: Database) -> Self {
+        MongoStore { db }
+    }
+
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.db.collection::<Document>(name)
+    }
+
+    async fn find_one(&self, collection: &str, filter: Document) -> Result<Option<Document>> {
+        self.collection(collection).find_one(filter).await
+    }
+
+    async fn find(
+        &self,
+        collection: &str,
+        filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>> {
+        let collection = self.collection(collection);
+        let mut find = collection.find(filter).skip(options.skip);
+        if let Some(sort) = options.sort {
+            find = find.sort(sort);
+        }
+        if let Some(limit) = options.limit {
+            find = find.limit(limit as i64);
+        }
+        find.await?.try_collect().await
+    }
+
+    async fn insert(&self, collection: &str, document: Document) -> Result<()> {
+        self.collection(collection)
+            .insert_one(document)
+            .await
+            .map(|_| ())
+    }
+
+    // Run `$inc` updates given as collection, filter and update triples
+    async fn apply_updates(&self, updates: Vec<(&str, Document, Document)>) -> Result<()> {
+        for (collection, filter, update) in updates {
+            self.collection(collection)
+                .update_one(filter, update)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn aggregate(&self, collection: &str, pipeline: Vec<Document>) -> Result<Vec<Document>> {
+        self.collection(collection)
+            .aggregate(pipeline)
+            .await?
+            .try_collect()
+            .await
+    }
+}
+
+// `$skip` and `$limit` stages for the window of a list query
+fn window(options: &ListOptions) -> Vec<Document> {
+    let mut stages = vec![doc! { "$skip": options.skip as i64 }];
+    if let Some(limit) = options.limit {
+        stages.push(doc! { "$limit": limit as i64 });
+    }
+    stages
+}
+
+// Run `$inc` updates given as collection, filter and update triples inside `session`
+async fn apply_updates_in_session(
+    db: &Database,
+    session: &mut ClientSession,
+    updates: Vec<(&str, Document, Document)>,
+) -> Result<()> {
+    for (collection, filter, update) in updates {
+        db.collection::<Document>(collection)
+            .update_one(filter, update)
+            .session(&mut *session)
+            .await?;
+    }
+    Ok(())
+}
+
+// Apply the counter increments and notifications of a post going live.
+// Runs inside the caller's transaction so the side effects happen exactly once.
+async fn apply_publish_side_effects(
+    db: &Database,
+    session: &mut ClientSession,
+    post: &Document,
+) -> Result<()> {
+    apply_updates_in_session(db, session, post_counter_updates(post, 1)).await?;
+
+    let quoted = match post.get_str("quoted_post_id") {
+        Ok(quoted_post_id) => {
+            db.collection::<Document>("posts")
+                .find_one(doc! { "_id": quoted_post_id })
+                .session(&mut *session)
+                .await?
+        }
+        Err(_) => None,
+    };
+    let notifications = publish_notifications(post, quoted.as_ref());
+    if !notifications.is_empty() {
+        db.collection::<Document>("notifications")
+            .insert_many(notifications)
+            .session(&mut *session)
+            .await?;
+    }
+    Ok(())
+}
+
+// Flip a post matching `filter` to published and apply its side effects in one transaction.
+// Returns None when no post matched, e.g. because another instance already published it.
+async fn publish_post(db: &Database, filter: Document) -> Result<Option<Document>> {
+    let now = Utc::now().to_rfc3339();
+    let update = doc! {
+        "$set": {
+            "status": PostStatus::Published.as_str(),
+            "published_at": &now,
+            "created_at": &now,
+        },
+        "$unset": { "publish_at": "" },
+    };
+
+    let mut session = db.client().start_session().await?;
+    session
+        .start_transaction()
+        .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+        .and_run((db, &filter, &update), |session, (db, filter, update)| {
+            async move {
+                let post = db
+                    .collection::<Document>("posts")
+                    .find_one_and_update((*filter).clone(), (*update).clone())
+                    .return_document(ReturnDocument::After)
+                    .session(&mut *session)
+                    .await?;
+
+                if let Some(post) = &post {
+                    apply_publish_side_effects(db, session, post).await?;
+                }
+                Ok(post)
+            }
+            .boxed()
+        })
+        .await
+}
+
+#[async_trait]
+impl UserRepo for MongoStore {
+    async fn find_by_id(&self, user_id: &str) -> Result<Option<Document>> {
+        self.find_one("users", doc! { "_id": user_id }).await
+    }
+
+    async fn find_active(&self, user_id: &str) -> Result<Option<Document>> {
+        self.find_one("users", active_user_filter(user_id)).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<Document>> {
+        self.find_one("users", doc! { "email": email }).await
+    }
+
+    async fn count_active(&self, user_ids: &[String]) -> Result<u64> {
+        self.collection("users")
+            .count_documents(
+                doc! { "_id": { "$in": user_ids }, "deleted_at": { "$exists": false } },
+            )
+            .await
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        self.find("users", filter, ListOptions::default()).await
+    }
+
+    async fn insert(&self, user: Document) -> Result<()> {
+        MongoStore::insert(self, "users", user).await
+    }
+
+    async fn set_deleted(&self, user_id: &str, deletion: Option<&Document>) -> Result<bool> {
+        let (filter, update) = match deletion {
+            Some(deletion) => (active_user_filter(user_id), doc! { "$set": deletion }),
+            None => (
+                doc! { "_id": user_id, "deleted_at": { "$exists": true } },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+            ),
+        };
+        let result = self.collection("users").update_one(filter, update).await?;
+        if result.matched_count == 0 {
+            return Ok(false);
+        }
+        for (collection, filter, update) in
+            author_flag_updates(user_id, "author_deleted", deletion.is_some())
+        {
+            self.collection(collection)
+                .update_many(filter, update)
+                .await?;
+        }
+        Ok(true)
+    }
+
+    async fn set_private(&self, user_id: &str, is_private: bool) -> Result<Option<Document>> {
+        let before = self
+            .collection("users")
+            .find_one_and_update(
+                doc! { "_id": user_id },
+                doc! { "$set": { "is_private": is_private } },
+            )
+            .await?;
+        if before.is_some() {
+            // Keep the denormalized flag on the user's posts in step with the account
+            self.collection("posts")
+                .update_many(
+                    doc! { "user_id": user_id },
+                    doc! { "$set": { "author_private": is_private } },
+                )
+                .await?;
+        }
+        Ok(before)
+    }
+
+    async fn set_role(&self, user_id: &str, role: &str) -> Result<Option<Document>> {
+        self.collection("users")
+            .find_one_and_update(doc! { "_id": user_id }, doc! { "$set": { "role": role } })
+            .await
+    }
+
+    async fn purge(&self, user_id: &str) -> Result<()> {
+        // Posts that were still live count towards the quote counts of the posts they quote
+        for post in self
+            .find("posts", doc! { "user_id": user_id }, ListOptions::default())
+            .await?
+        {
+            PostRepo::purge(self, post.get_str("_id").unwrap_or_default()).await?;
+            if !post.contains_key("deleted_at") {
+                self.apply_updates(post_counter_updates(&post, -1)).await?;
+            }
+        }
+
+        for (collection_name, filter) in user_connection_filters(user_id) {
+            let collection = self.collection(collection_name);
+            if !COUNTED_CONNECTIONS.contains(&collection_name) {
+                collection.delete_many(filter).await?;
+                continue;
+            }
+            while let Some(removed) = collection.find_one_and_delete(filter.clone()).await? {
+                self.apply_updates(removal_counter_updates(collection_name, &removed))
+                    .await?;
+            }
+        }
+
+        delete_exports(&self.db, doc! { "user_id": user_id }).await?;
+        self.collection("users")
+            .delete_one(doc! { "_id": user_id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PostRepo for MongoStore {
+    async fn find_by_id(&self, post_id: &str) -> Result<Option<Document>> {
+        self.find_one("posts", doc! { "_id": post_id }).await
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("posts", filter, options).await
+    }
+
+    async fn insert(&self, post: Document) -> Result<()> {
+        MongoStore::insert(self, "posts", post).await
+    }
+
+    async fn insert_published(&self, post: Document) -> Result<()> {
+        let posts = self.collection("posts");
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run((&self.db, &posts, &post), |session, (db, posts, post)| {
+                async move {
+                    posts
+                        .insert_one((*post).clone())
+                        .session(&mut *session)
+                        .await?;
+                    apply_publish_side_effects(db, session, post).await
+                }
+                .boxed()
+            })
+            .await
+    }
+
+    async fn schedule(&self, post_id: &str, publish_at: &str) -> Result<bool> {
+        let result = self
+            .collection("posts")
+            .update_one(
+                pending_filter(post_id),
+                doc! { "$set": {
+                    "status": PostStatus::Scheduled.as_str(),
+                    "publish_at": publish_at,
+                } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn publish(&self, post_id: &str) -> Result<Option<Document>> {
+        publish_post(&self.db, pending_filter(post_id)).await
+    }
+
+    async fn publish_due(&self, post_id: &str, due_by: &str) -> Result<Option<Document>> {
+        let mut filter = due_filter(due_by);
+        filter.insert("_id", post_id);
+        publish_post(&self.db, filter).await
+    }
+
+    async fn set_deleted(&self, post: &Document, deletion: Option<&Document>) -> Result<bool> {
+        let post_id = post.get_str("_id").unwrap_or_default();
+        let (filter, update, delta) = if let Some(deletion) = deletion {
+            (
+                doc! { "_id": post_id, "deleted_at": { "$exists": false } },
+                doc! { "$set": deletion.clone() },
+                -1,
+            )
+        } else {
+            (
+                doc! { "_id": post_id, "deleted_at": post.get_str("deleted_at").unwrap_or_default() },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+                1,
+            )
+        };
+
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (&self.db, post, &filter, &update),
+                |session, (db, post, filter, update)| {
+                    async move {
+                        let result = db
+                            .collection::<Document>("posts")
+                            .update_one((*filter).clone(), (*update).clone())
+                            .session(&mut *session)
+                            .await?;
+                        if result.matched_count == 0 {
+                            return Ok(false);
+                        }
+                        apply_updates_in_session(db, session, post_counter_updates(post, delta))
+                            .await?;
+                        Ok(true)
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+
+    async fn purge(&self, post_id: &str) -> Result<()> {
+        for collection in POST_DEPENDENTS {
+            self.collection(collection)
+                .delete_many(doc! { "post_id": post_id })
+                .await?;
+        }
+        self.collection("posts")
+            .delete_one(doc! { "_id": post_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn find_vote(&self, vote_id: &str) -> Result<Option<Document>> {
+        self.find_one("poll_votes", doc! { "_id": vote_id }).await
+    }
+
+    async fn vote(&self, vote: Document) -> Result<bool> {
+        // Only count the vote if the poll is still open when the write lands
+        let (open_filter, increments) = vote_changes(&vote);
+
+        let votes = self.collection("poll_votes");
+        let posts = self.collection("posts");
+        let mut session = self.db.client().start_session().await?;
+        let mut attempt = 1;
+        loop {
+            session
+                .start_transaction()
+                .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+                .await?;
+
+            let result = async {
+                votes.insert_one(vote.clone()).session(&mut session).await?;
+                posts
+                    .update_one(open_filter.clone(), doc! { "$inc": increments.clone() })
+                    .session(&mut session)
+                    .await
+            }
+            .await;
+
+            let error = match result {
+                Ok(update) if update.matched_count == 1 => {
+                    match session.commit_transaction().await {
+                        Ok(()) => return Ok(true),
+                        Err(e) => e,
+                    }
+                }
+                Ok(_) => {
+                    session.abort_transaction().await?;
+                    return Ok(false);
+                }
+                Err(e) => {
+                    session.abort_transaction().await.ok();
+                    e
+                }
+            };
+            if !error.contains_label(TRANSIENT_TRANSACTION_ERROR) || attempt == VOTE_ATTEMPTS {
+                return Err(error);
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn has_reposted(&self, repost_id: &str) -> Result<bool> {
+        Ok(self
+            .find_one("reposts", doc! { "_id": repost_id })
+            .await?
+            .is_some())
+    }
+
+    async fn repost(&self, repost: Document) -> Result<()> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run((&self.db, &repost), |session, (db, repost)| {
+                async move {
+                    db.collection::<Document>("reposts")
+                        .insert_one((*repost).clone())
+                        .session(&mut *session)
+                        .await?;
+                    db.collection::<Document>("posts")
+                        .update_one(
+                            doc! { "_id": repost.get_str("post_id").unwrap_or_default() },
+                            doc! { "$inc": { "repost_count": 1 } },
+                        )
+                        .session(&mut *session)
+                        .await?;
+                    Ok(())
+                }
+                .boxed()
+            })
+            .await
+    }
+
+    async fn undo_repost(&self, repost_id: &str, post_id: &str) -> Result<bool> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (&self.db, repost_id, post_id),
+                |session, (db, repost_id, post_id)| {
+                    async move {
+                        let result = db
+                            .collection::<Document>("reposts")
+                            .delete_one(doc! { "_id": *repost_id })
+                            .session(&mut *session)
+                            .await?;
+                        if result.deleted_count == 0 {
+                            return Ok(false);
+                        }
+                        db.collection::<Document>("posts")
+                            .update_one(
+                                doc! { "_id": *post_id },
+                                doc! { "$inc": { "repost_count": -1 } },
+                            )
+                            .session(&mut *session)
+                            .await?;
+                        Ok(true)
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+
+    async fn timeline(
+        &self,
+        author_ids: &[String],
+        hidden: &[String],
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>> {
+        // Join every entry to its post and filter before paginating, so reposts of posts
+        // the viewer cannot see do not leave gaps in a page
+        let mut pipeline = vec![
+            doc! { "$match": { "user_id": { "$in": author_ids } } },
+            doc! { "$project": {
+                "_id": 0,
+                "post_id": "$_id",
+                "activity_at": "$created_at",
+                "reposted_by": Bson::Null,
+            } },
+            doc! { "$unionWith": {
+                "coll": "reposts",
+                "pipeline": [
+                    { "$match": {
+                        "user_id": { "$in": author_ids, "$nin": hidden },
+                        "author_suspended": { "$ne": true },
+                        "author_deleted": { "$ne": true },
+                    } },
+                    { "$project": {
+                        "_id": 0,
+                        "post_id": 1,
+                        "activity_at": "$created_at",
+                        "reposted_by": "$user_id",
+                    } },
+                ],
+            } },
+            doc! { "$lookup": {
+                "from": "posts",
+                "localField": "post_id",
+                "foreignField": "_id",
+                "as": "post",
+            } },
+            doc! { "$unwind": "$post" },
+            doc! { "$match": post_filter },
+            doc! { "$sort": { "activity_at": -1, "post_id": 1 } },
+        ];
+        pipeline.extend(window(&options));
+        pipeline.extend([
+            doc! { "$lookup": {
+                "from": "users",
+                "localField": "reposted_by",
+                "foreignField": "_id",
+                "pipeline": [{ "$project": { "username": 1 } }],
+                "as": "reposter",
+            } },
+            doc! { "$set": {
+                "reposter_username": { "$first": "$reposter.username" },
+            } },
+            doc! { "$project": { "reposter": 0 } },
+        ]);
+        self.aggregate("posts", pipeline).await
+    }
+}
+
+#[async_trait]
+impl CommentRepo for MongoStore {
+    async fn find_by_id(&self, comment_id: &str) -> Result<Option<Document>> {
+        self.find_one("comments", doc! { "_id": comment_id }).await
+    }
+
+    async fn count_for_post(&self, post_id: &str) -> Result<u64> {
+        self.collection("comments")
+            .count_documents(doc! { "post_id": post_id, "deleted_at": { "$exists": false } })
+            .await
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        self.find("comments", filter, ListOptions::default()).await
+    }
+
+    async fn list_with_post(
+        &self,
+        filter: Document,
+        post_filter: Document,
+    ) -> Result<Vec<Document>> {
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$lookup": {
+                "from": "posts",
+                "localField": "post_id",
+                "foreignField": "_id",
+                "as": "post",
+            } },
+            doc! { "$unwind": "$post" },
+            doc! { "$match": post_filter },
+            doc! { "$project": { "post": 0 } },
+        ];
+        self.aggregate("comments", pipeline).await
+    }
+
+    async fn insert(&self, comment: Document) -> Result<()> {
+        MongoStore::insert(self, "comments", comment).await
+    }
+
+    async fn set_deleted(&self, comment_id: &str, deletion: Option<&Document>) -> Result<bool> {
+        let (filter, update) = match deletion {
+            Some(deletion) => (
+                doc! { "_id": comment_id, "deleted_at": { "$exists": false } },
+                doc! { "$set": deletion },
+            ),
+            None => (
+                doc! { "_id": comment_id, "deleted_at": { "$exists": true } },
+                doc! { "$unset": { "deleted_at": "", "deleted_by": "" } },
+            ),
+        };
+        let result = self
+            .collection("comments")
+            .update_one(filter, update)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn purge(&self, comment_id: &str, cutoff: &str) -> Result<bool> {
+        let result = self
+            .collection("comments")
+            .delete_one(doc! { "_id": comment_id, "deleted_at": { "$lte": cutoff } })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[async_trait]
+impl FollowRepo for MongoStore {
+    async fn is_following(&self, follower_id: &str, following_id: &str) -> Result<bool> {
+        let follow = self
+            .find_one(
+                "follows",
+                doc! { "follower_id": follower_id, "following_id": following_id },
+            )
+            .await?;
+        Ok(follow.is_some())
+    }
+
+    async fn is_blocked_between(&self, user_a: &str, user_b: &str) -> Result<bool> {
+        let block = self
+            .find_one(
+                "blocks",
+                doc! {
+                    "$or": [
+                        { "blocker_id": user_a, "blocked_id": user_b },
+                        { "blocker_id": user_b, "blocked_id": user_a },
+                    ]
+                },
+            )
+            .await?;
+        Ok(block.is_some())
+    }
+
+    async fn list(&self, filter: Document) -> Result<Vec<Document>> {
+        self.find("follows", filter, ListOptions::default()).await
+    }
+
+    async fn insert(&self, follow: Document) -> Result<()> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run((&self.db, &follow), |session, (db, follow)| {
+                async move {
+                    db.collection::<Document>("follows")
+                        .insert_one((*follow).clone())
+                        .session(&mut *session)
+                        .await?;
+                    for (filter, update) in follow_counter_updates(follow, 1) {
+                        db.collection::<Document>("users")
+                            .update_one(filter, update)
+                            .session(&mut *session)
+                            .await?;
+                    }
+                    Ok(())
+                }
+                .boxed()
+            })
+            .await
+    }
+
+    async fn insert_request(&self, request: Document) -> Result<()> {
+        MongoStore::insert(self, "follow_requests", request).await
+    }
+
+    async fn list_requests(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("follow_requests", filter, options).await
+    }
+
+    async fn accept_request(&self, request_id: &str, follow: Document) -> Result<bool> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (&self.db, request_id, &follow),
+                |session, (db, request_id, follow)| {
+                    async move {
+                        let deleted = db
+                            .collection::<Document>("follow_requests")
+                            .delete_one(doc! { "_id": *request_id })
+                            .session(&mut *session)
+                            .await?;
+                        if deleted.deleted_count == 0 {
+                            return Ok(false);
+                        }
+                        db.collection::<Document>("follows")
+                            .insert_one((*follow).clone())
+                            .session(&mut *session)
+                            .await?;
+                        for (filter, update) in follow_counter_updates(follow, 1) {
+                            db.collection::<Document>("users")
+                                .update_one(filter, update)
+                                .session(&mut *session)
+                                .await?;
+                        }
+                        Ok(true)
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+
+    async fn reject_request(&self, request_id: &str) -> Result<Option<Document>> {
+        self.collection("follow_requests")
+            .find_one_and_delete(doc! { "_id": request_id })
+            .await
+    }
+}
+
+#[async_trait]
+impl LikeRepo for MongoStore {
+    async fn has_liked(&self, post_id: &str, user_id: &str) -> Result<bool> {
+        let like = self
+            .find_one("likes", doc! { "post_id": post_id, "user_id": user_id })
+            .await?;
+        Ok(like.is_some())
+    }
+
+    async fn insert(&self, like: Document) -> Result<()> {
+        MongoStore::insert(self, "likes", like).await
+    }
+}
+
+#[async_trait]
+impl BookmarkRepo for MongoStore {
+    async fn is_bookmarked(&self, post_id: &str, user_id: &str) -> Result<bool> {
+        let bookmark = self
+            .find_one("bookmarks", doc! { "post_id": post_id, "user_id": user_id })
+            .await?;
+        Ok(bookmark.is_some())
+    }
+
+    async fn insert(&self, bookmark: Document) -> Result<()> {
+        MongoStore::insert(self, "bookmarks", bookmark).await
+    }
+
+    async fn remove(&self, filter: Document) -> Result<u64> {
+        let result = self.collection("bookmarks").delete_many(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn list_posts(
+        &self,
+        filter: Document,
+        post_filter: Document,
+        options: ListOptions,
+    ) -> Result<Vec<Document>> {
+        // Join against posts before paginating so deleted or hidden posts do not leave gaps
+        let mut pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$sort": { "created_at": -1 } },
+            doc! { "$group": {
+                "_id": "$post_id",
+                "saved_at": { "$first": "$created_at" },
+            } },
+            doc! { "$lookup": {
+                "from": "posts",
+                "localField": "_id",
+                "foreignField": "_id",
+                "as": "post",
+            } },
+            doc! { "$unwind": "$post" },
+            doc! { "$match": post_filter },
+            doc! { "$sort": { "saved_at": -1, "_id": 1 } },
+        ];
+        pipeline.extend(window(&options));
+
+        let entries = self.aggregate("bookmarks", pipeline).await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|mut entry| match entry.remove("post") {
+                Some(Bson::Document(post)) => Some(post),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn collections(&self, user_id: &str) -> Result<Vec<Document>> {
+        let pipeline = vec![
+            doc! { "$match": { "user_id": user_id } },
+            doc! { "$group": { "_id": "$collection", "count": { "$sum": 1 } } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+        self.aggregate("bookmarks", pipeline).await
+    }
+}
+
+#[async_trait]
+impl NotificationRepo for MongoStore {
+    async fn insert(&self, notification: Document) -> Result<()> {
+        MongoStore::insert(self, "notifications", notification).await
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("notifications", filter, options).await
+    }
+}
+
+#[async_trait]
+impl BlockRepo for MongoStore {
+    async fn block(&self, block: Document) -> Result<()> {
+        let blocker_id = block.get_str("blocker_id").unwrap_or_default();
+        let blocked_id = block.get_str("blocked_id").unwrap_or_default();
+        let between = doc! {
+            "$or": [
+                { "follower_id": blocker_id, "following_id": blocked_id },
+                { "follower_id": blocked_id, "following_id": blocker_id },
+            ]
+        };
+
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (&self.db, &block, &between),
+                |session, (db, block, between)| {
+                    async move {
+                        db.collection::<Document>("blocks")
+                            .insert_one((*block).clone())
+                            .session(&mut *session)
+                            .await?;
+                        let follows = db.collection::<Document>("follows");
+                        while let Some(follow) = follows
+                            .find_one_and_delete((*between).clone())
+                            .session(&mut *session)
+                            .await?
+                        {
+                            for (filter, update) in follow_counter_updates(&follow, -1) {
+                                db.collection::<Document>("users")
+                                    .update_one(filter, update)
+                                    .session(&mut *session)
+                                    .await?;
+                            }
+                        }
+                        db.collection::<Document>("follow_requests")
+                            .delete_many((*between).clone())
+                            .session(&mut *session)
+                            .await?;
+                        Ok(())
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+
+    async fn unblock(&self, block_id: &str) -> Result<Option<Document>> {
+        self.collection("blocks")
+            .find_one_and_delete(doc! { "_id": block_id })
+            .await
+    }
+
+    async fn list_blocks(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("blocks", filter, options).await
+    }
+
+    async fn mute(&self, mute: Document) -> Result<()> {
+        MongoStore::insert(self, "mutes", mute).await
+    }
+
+    async fn unmute(&self, mute_id: &str) -> Result<Option<Document>> {
+        self.collection("mutes")
+            .find_one_and_delete(doc! { "_id": mute_id })
+            .await
+    }
+
+    async fn list_mutes(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("mutes", filter, options).await
+    }
+}
+
+#[async_trait]
+impl ReportRepo for MongoStore {
+    async fn find_by_id(&self, report_id: &str) -> Result<Option<Document>> {
+        self.find_one("reports", doc! { "_id": report_id }).await
+    }
+
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>> {
+        MongoStore::find_one(self, "reports", filter).await
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("reports", filter, options).await
+    }
+
+    async fn history(&self, report_id: &str) -> Result<Vec<Document>> {
+        self.find(
+            "moderation_log",
+            doc! { "report_id": report_id },
+            ListOptions::sorted(doc! { "created_at": 1 }),
+        )
+        .await
+    }
+
+    async fn create(&self, report: Document, log: Document) -> Result<()> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run((&self.db, &report, &log), |session, (db, report, log)| {
+                async move {
+                    db.collection::<Document>("reports")
+                        .insert_one((*report).clone())
+                        .session(&mut *session)
+                        .await?;
+                    db.collection::<Document>("moderation_log")
+                        .insert_one((*log).clone())
+                        .session(&mut *session)
+                        .await?;
+                    Ok(())
+                }
+                .boxed()
+            })
+            .await
+    }
+
+    async fn claim(&self, report_id: &str, moderator_id: &str, log: Document) -> Result<bool> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (&self.db, report_id, moderator_id, &log),
+                |session, (db, report_id, moderator_id, log)| {
+                    async move {
+                        let result = db
+                            .collection::<Document>("reports")
+                            .update_one(
+                                doc! { "_id": *report_id, "status": ReportStatus::Open.as_str() },
+                                doc! { "$set": {
+                                    "status": ReportStatus::Claimed.as_str(),
+                                    "claimed_by": *moderator_id,
+                                    "claimed_at": Utc::now().to_rfc3339(),
+                                } },
+                            )
+                            .session(&mut *session)
+                            .await?;
+                        if result.matched_count == 0 {
+                            return Ok(false);
+                        }
+
+                        db.collection::<Document>("moderation_log")
+                            .insert_one((*log).clone())
+                            .session(&mut *session)
+                            .await?;
+                        Ok(true)
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+
+    async fn resolve(
+        &self,
+        report_id: &str,
+        moderator_id: &str,
+        update: Document,
+        content_update: Option<ContentUpdate>,
+        log: Document,
+    ) -> Result<bool> {
+        let mut session = self.db.client().start_session().await?;
+        session
+            .start_transaction()
+            .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+            .and_run(
+                (
+                    &self.db,
+                    report_id,
+                    moderator_id,
+                    &update,
+                    &content_update,
+                    &log,
+                ),
+                |session, (db, report_id, moderator_id, update, content_update, log)| {
+                    async move {
+                        let result = db
+                            .collection::<Document>("reports")
+                            .update_one(
+                                doc! {
+                                    "_id": *report_id,
+                                    "status": ReportStatus::Claimed.as_str(),
+                                    "claimed_by": *moderator_id,
+                                },
+                                (*update).clone(),
+                            )
+                            .session(&mut *session)
+                            .await?;
+                        if result.matched_count == 0 {
+                            return Ok(false);
+                        }
+
+                        if let Some(content_update) = content_update {
+                            let (collection, id, content) = content_update;
+                            db.collection::<Document>(collection)
+                                .update_one(doc! { "_id": id }, content.clone())
+                                .session(&mut *session)
+                                .await?;
+                            for (collection, filter, flags) in
+                                moderation_flag_updates(content_update)
+                            {
+                                db.collection::<Document>(collection)
+                                    .update_many(filter, flags)
+                                    .session(&mut *session)
+                                    .await?;
+                            }
+                        }
+
+                        db.collection::<Document>("moderation_log")
+                            .insert_one((*log).clone())
+                            .session(&mut *session)
+                            .await?;
+                        Ok(true)
+                    }
+                    .boxed()
+                },
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl AuditRepo for MongoStore {
+    async fn insert(&self, entry: Document) -> Result<()> {
+        MongoStore::insert(self, "audit_log", entry).await
+    }
+
+    async fn list(&self, filter: Document, options: ListOptions) -> Result<Vec<Document>> {
+        self.find("audit_log", filter, options).await
+    }
+}
+
+#[async_trait]
+impl ExportRepo for MongoStore {
+    async fn find_one(&self, filter: Document) -> Result<Option<Document>> {
+        MongoStore::find_one(self, "exports", filter).await
+    }
+
+    async fn insert(&self, export: Document) -> Result<()> {
+        MongoStore::insert(self, "exports", export).await
+    }
+
+    async fn update(&self, export_id: &str, fields: Document) -> Result<()> {
+        self.collection("exports")
+            .update_one(doc! { "_id": export_id }, doc! { "$set": fields })
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete(&self, filter: Document) -> Result<()> {
+        delete_exports(&self.db, filter).await
+    }
+
+    async fn collect(&self, collection: &str, filter: Document) -> Result<Vec<Document>> {
+        self.find(collection, filter, ListOptions::default()).await
+    }
+
+    async fn store_archive(&self, export_id: &str, user_id: &str, archive: &[u8]) -> Result<()> {
+        store_archive(&self.db, export_id, user_id, archive).await
+    }
+
+    async fn load_archive(&self, export_id: &str) -> Result<Vec<u8>> {
+        load_archive(&self.db, export_id).await
+    }
+}
+
+#[async_trait]
+impl SeedRepo for MongoStore {
+    async fn find_job(&self, filter: Document) -> Result<Option<Document>> {
+        MongoStore::find_one(self, "seed_jobs", filter).await
+    }
+
+    async fn insert_job(&self, job: Document) -> Result<()> {
+        MongoStore::insert(self, "seed_jobs", job).await
+    }
+
+    async fn update_job(&self, job_id: &str, fields: Document) -> Result<()> {
+        if let Ok(updated_at) = fields.get_str("updated_at") {
+            self.collection("locks")
+                .update_one(
+                    doc! { "_id": SEED_LOCK, "job_id": job_id },
+                    doc! { "$set": { "updated_at": updated_at } },
+                )
+                .await?;
+        }
+        self.collection("seed_jobs")
+            .update_one(doc! { "_id": job_id }, doc! { "$set": fields })
+            .await
+            .map(|_| ())
+    }
+
+    // A held lock does not match the filter, so the upsert tries to insert a second
+    // document with the singleton `_id` and fails with a duplicate key error
+    async fn lock(&self, job_id: &str, stale_before: &str) -> Result<Option<String>> {
+        let taken = self
+            .collection("locks")
+            .find_one_and_update(
+                doc! { "_id": SEED_LOCK, "updated_at": { "$lte": stale_before } },
+                doc! { "$set": { "job_id": job_id, "updated_at": Utc::now().to_rfc3339() } },
+            )
+            .upsert(true)
+            .await;
+        match taken {
+            Ok(_) => Ok(None),
+            Err(e) if is_duplicate_key_error(&e) => {
+                let holder = self.find_one("locks", doc! { "_id": SEED_LOCK }).await?;
+                Ok(Some(
+                    holder
+                        .and_then(|lock| lock.get_str("job_id").ok().map(str::to_string))
+                        .unwrap_or_default(),
+                ))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn unlock(&self, job_id: &str) -> Result<()> {
+        self.collection("locks")
+            .delete_one(doc! { "_id": SEED_LOCK, "job_id": job_id })
+            .await
+            .map(|_| ())
+    }
+
+    async fn clear_data(&self) -> Result<Document> {
+        let mut removed = Document::new();
+        for collection in DATA_COLLECTIONS {
+            let result = self
+                .collection(collection)
+                .delete_many(cleared_filter(collection))
+                .await?;
+            removed.insert(collection, result.deleted_count as i64);
+        }
+        delete_exports(&self.db, doc! {}).await?;
+        Ok(removed)
+    }
+
+    async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<()> {
+        self.collection(collection)
+            .insert_many(documents)
+            .ordered(false)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[async_trait]
+impl TransferRepo for MongoStore {
+    async fn for_each(
+        &self,
+        collection: &str,
+        batch_size: u32,
+        each: &mut (dyn FnMut(Document) -> std::io::Result<()> + Send),
+    ) -> Result<u64> {
+        let mut cursor = self
+            .collection(collection)
+            .find(doc! {})
+            .batch_size(batch_size)
+            .await?;
+        let mut count = 0;
+        while let Some(document) = cursor.try_next().await? {
+            each(document)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn write_batch(
+        &self,
+        collection: &str,
+        documents: Vec<Document>,
+        mode: ConflictMode,
+    ) -> Result<ImportSummary> {
+        let target = self.collection(collection);
+        let mut summary = ImportSummary::default();
+        if mode == ConflictMode::Upsert {
+            // Replacements go out as one bulk write rather than a round trip each
+            let replacements = documents.into_iter().map(|document| {
+                ReplaceOneModel::builder()
+                    .namespace(target.namespace())
+                    .filter(doc! { "_id": document.get("_id").cloned().unwrap_or(Bson::Null) })
+                    .replacement(document)
+                    .upsert(true)
+                    .build()
+            });
+            let result = self.db.client().bulk_write(replacements).await?;
+            summary.inserted = result.upserted_count as u64;
+            summary.replaced = result.matched_count as u64;
+            return Ok(summary);
+        }
+
+        let count = documents.len() as u64;
+        // Unordered inserts keep going past duplicates so skipped documents can be counted
+        let ordered = mode == ConflictMode::Fail;
+        match target.insert_many(documents).ordered(ordered).await {
+            Ok(result) => summary.inserted = result.inserted_ids.len() as u64,
+            Err(e) if mode == ConflictMode::Skip && is_duplicate_key_error(&e) => {
+                let duplicates = match e.kind.as_ref() {
+                    ErrorKind::InsertMany(failure) if failure.write_concern_error.is_none() => {
+                        failure.write_errors.as_ref().and_then(|errors| {
+                            errors
+                                .iter()
+                                .all(|error| error.code == 11000)
+                                .then_some(errors.len() as u64)
+                        })
+                    }
+                    _ => None,
+                };
+                // Anything other than duplicates still fails the import
+                let skipped = duplicates.ok_or(e)?;
+                summary.inserted = count - skipped;
+                summary.skipped = skipped;
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(summary)
+    }
+}
+
+#[async_trait]
+impl DatabaseRepo for MongoStore {
+    fn name(&self) -> String {
+        self.db.name().to_string()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.db.run_command(doc! { "ping": 1 }).await.map(|_| ())
+    }
+
+    async fn cluster_status(&self) -> Result<ClusterStatus> {
+        fetch_cluster_status(self.db.client()).await
+    }
+}
@@ -0,0 +1,221 @@
+use mongodb::bson::{Bson, Document};
+use std::cmp::Ordering;
+
+// The subset of MongoDB query, update and sort documents the API builds, evaluated
+// against documents held in memory. Supports equality (including matching an element of
+// an array field), $in, $nin, $ne, $exists, $gt, $gte, $lt, $lte, $or and $and on dotted
+// paths, and $set, $unset and $inc updates.
+
+// The value at a dotted path, stepping into embedded documents and array indexes
+pub fn lookup<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut segments = path.split('.');
+    let mut value = document.get(segments.next()?)?;
+    for segment in segments {
+        value = match value {
+            Bson::Document(inner) => inner.get(segment)?,
+            Bson::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn as_number(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(n) => Some(*n as f64),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn values_equal(a: &Bson, b: &Bson) -> bool {
+    match (as_number(a), as_number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Order of values of the same kind; values of different kinds do not compare
+fn compare_values(a: &Bson, b: &Bson) -> Option<Ordering> {
+    match (a, b) {
+        (Bson::String(a), Bson::String(b)) => Some(a.cmp(b)),
+        (Bson::Boolean(a), Bson::Boolean(b)) => Some(a.cmp(b)),
+        (Bson::DateTime(a), Bson::DateTime(b)) => Some(a.cmp(b)),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => Some(a.cmp(b)),
+        _ => as_number(a)?.partial_cmp(&as_number(b)?),
+    }
+}
+
+// Equality the way a query means it: null also matches a missing field, and a scalar
+// matches an array that contains it
+fn equals(value: Option<&Bson>, expected: &Bson) -> bool {
+    match value {
+        None | Some(Bson::Null) => matches!(expected, Bson::Null),
+        Some(Bson::Array(items)) if !matches!(expected, Bson::Array(_)) => {
+            items.iter().any(|item| values_equal(item, expected))
+        }
+        Some(value) => values_equal(value, expected),
+    }
+}
+
+fn is_in(value: Option<&Bson>, candidates: &Bson) -> bool {
+    match candidates {
+        Bson::Array(candidates) => candidates.iter().any(|candidate| equals(value, candidate)),
+        _ => false,
+    }
+}
+
+fn compares(value: Option<&Bson>, bound: &Bson, accept: fn(Ordering) -> bool) -> bool {
+    value
+        .and_then(|value| compare_values(value, bound))
+        .is_some_and(accept)
+}
+
+fn matches_condition(value: Option<&Bson>, condition: &Bson) -> bool {
+    let operators = match condition {
+        Bson::Document(operators) if operators.keys().all(|key| key.starts_with('$')) => operators,
+        expected => return equals(value, expected),
+    };
+    operators
+        .iter()
+        .all(|(operator, operand)| match operator.as_str() {
+            "$in" => is_in(value, operand),
+            "$nin" => !is_in(value, operand),
+            "$ne" => !equals(value, operand),
+            "$exists" => value.is_some() == operand.as_bool().unwrap_or(true),
+            "$gt" => compares(value, operand, Ordering::is_gt),
+            "$gte" => compares(value, operand, Ordering::is_ge),
+            "$lt" => compares(value, operand, Ordering::is_lt),
+            "$lte" => compares(value, operand, Ordering::is_le),
+            _ => false,
+        })
+}
+
+fn sub_filters(filters: &Bson) -> impl Iterator<Item = &Document> {
+    filters
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Bson::as_document)
+}
+
+// True when `document` matches the query `filter`
+pub fn matches(document: &Document, filter: &Document) -> bool {
+    filter.iter().all(|(key, condition)| match key.as_str() {
+        "$or" => sub_filters(condition).any(|filter| matches(document, filter)),
+        "$and" => sub_filters(condition).all(|filter| matches(document, filter)),
+        path => matches_condition(lookup(document, path), condition),
+    })
+}
+
+// The document or array holding the last segment of `path`, created on the way for $set
+fn parent_mut<'a>(document: &'a mut Document, path: &'a str) -> Option<(&'a mut Bson, &'a str)> {
+    let (head, rest) = path.split_once('.')?;
+    if !document.contains_key(head) {
+        document.insert(head, Document::new());
+    }
+    let mut value = document.get_mut(head)?;
+    let mut rest = rest;
+    while let Some((segment, tail)) = rest.split_once('.') {
+        value = match value {
+            Bson::Document(inner) => {
+                if !inner.contains_key(segment) {
+                    inner.insert(segment, Document::new());
+                }
+                inner.get_mut(segment)?
+            }
+            Bson::Array(items) => items.get_mut(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+        rest = tail;
+    }
+    Some((value, rest))
+}
+
+fn set_path(document: &mut Document, path: &str, new: Bson) {
+    if !path.contains('.') {
+        document.insert(path, new);
+        return;
+    }
+    match parent_mut(document, path) {
+        Some((Bson::Document(parent), key)) => {
+            parent.insert(key, new);
+        }
+        Some((Bson::Array(items), index)) => {
+            if let Some(item) = index.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                *item = new;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn unset_path(document: &mut Document, path: &str) {
+    match path.rsplit_once('.') {
+        None => {
+            document.remove(path);
+        }
+        Some((_, key)) => {
+            if let Some((Bson::Document(parent), _)) = parent_mut(document, path) {
+                parent.remove(key);
+            }
+        }
+    }
+}
+
+// Add two counters, keeping the narrowest type that holds the result
+fn add(current: Option<&Bson>, delta: &Bson) -> Bson {
+    match (current.unwrap_or(&Bson::Int32(0)), delta) {
+        (Bson::Int32(a), Bson::Int32(b)) => Bson::Int32(a + b),
+        (Bson::Int64(a), Bson::Int32(b)) => Bson::Int64(a + *b as i64),
+        (Bson::Int32(a), Bson::Int64(b)) => Bson::Int64(*a as i64 + b),
+        (Bson::Int64(a), Bson::Int64(b)) => Bson::Int64(a + b),
+        (a, b) => Bson::Double(as_number(a).unwrap_or(0.0) + as_number(b).unwrap_or(0.0)),
+    }
+}
+
+// Apply an update document with $set, $unset and $inc to `document`
+pub fn apply_update(document: &mut Document, update: &Document) {
+    for (operator, fields) in update {
+        let fields = match fields.as_document() {
+            Some(fields) => fields,
+            None => continue,
+        };
+        for (path, value) in fields {
+            match operator.as_str() {
+                "$set" => set_path(document, path, value.clone()),
+                "$unset" => unset_path(document, path),
+                "$inc" => {
+                    let sum = add(lookup(document, path), value);
+                    set_path(document, path, sum);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Order documents by a sort document such as { "created_at": -1, "_id": 1 }. Missing
+// values sort first, as null does in MongoDB.
+pub fn sort(documents: &mut [Document], sort: &Document) {
+    documents.sort_by(|a, b| {
+        for (path, direction) in sort {
+            let ordering = match (lookup(a, path), lookup(b, path)) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(a), Some(b)) => compare_values(a, b).unwrap_or(Ordering::Equal),
+            };
+            let ordering = if as_number(direction).unwrap_or(1.0) < 0.0 {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+}
@@ -0,0 +1,108 @@
+use crate::{
+    audit::{AuditContext, AuditEvent},
+    lifecycle::BackgroundJobs,
+    repository::{CommentRepo, ListOptions, PostRepo, Store, UserRepo},
+};
+use chrono::{DateTime, Utc};
+use mongodb::{bson::doc, error::Result};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+// Timestamp before which soft-deleted documents are past their restore window
+pub fn retention_cutoff(retention: Duration) -> String {
+    let retention = chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX);
+    Utc::now()
+        .checked_sub_signed(retention)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+        .to_rfc3339()
+}
+
+// True while a document deleted at `deleted_at` can still be restored
+pub fn within_restore_window(deleted_at: &str, retention: Duration) -> bool {
+    deleted_at > retention_cutoff(retention).as_str()
+}
+
+// Hard-delete every user, post and comment whose restore window has passed, auditing
+// each removal as the system
+pub async fn purge_expired<S: Store>(
+    store: &S,
+    retention: Duration,
+    audit: &AuditContext,
+) -> Result<usize> {
+    let cutoff = retention_cutoff(retention);
+    let expired = doc! { "deleted_at": { "$lte": &cutoff } };
+    let mut purged = 0;
+
+    for user in UserRepo::list(store, expired.clone()).await? {
+        let user_id = user.get_str("_id").unwrap_or_default();
+        UserRepo::purge(store, user_id).await?;
+        info!("Purged deleted user {}", user_id);
+        audit
+            .record(store, AuditEvent::new("purge_user", "user", user_id))
+            .await;
+        purged += 1;
+    }
+
+    for post in PostRepo::list(store, expired.clone(), ListOptions::default()).await? {
+        let post_id = post.get_str("_id").unwrap_or_default();
+        PostRepo::purge(store, post_id).await?;
+        info!("Purged deleted post {}", post_id);
+        audit
+            .record(store, AuditEvent::new("purge_post", "post", post_id))
+            .await;
+        purged += 1;
+    }
+
+    for comment in CommentRepo::list(store, expired).await? {
+        let comment_id = comment.get_str("_id").unwrap_or_default();
+        if !CommentRepo::purge(store, comment_id, &cutoff).await? {
+            continue;
+        }
+        audit
+            .record(
+                store,
+                AuditEvent::new("purge_comment", "comment", comment_id),
+            )
+            .await;
+        purged += 1;
+    }
+
+    Ok(purged)
+}
+
+// Spawn the background task that purges expired soft-deleted documents
+pub fn spawn_purge_job<S: Store>(
+    store: Arc<S>,
+    retention: Duration,
+    audit_retention: Duration,
+    interval: Duration,
+    jobs: &BackgroundJobs,
+) {
+    info!(
+        "Starting purge job with a {} second interval and {} day retention",
+        interval.as_secs(),
+        retention.as_secs() / 86400
+    );
+
+    let stopping = jobs.stopping();
+    jobs.spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        tokio::pin!(stopping);
+        loop {
+            // A run in progress is finished before stopping
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = &mut stopping => {
+                    info!("Stopping the purge job");
+                    return;
+                }
+            }
+            let audit = AuditContext::system(audit_retention);
+            match purge_expired(store.as_ref(), retention, &audit).await {
+                Ok(0) => {}
+                Ok(count) => info!("Purge job removed {} deleted documents", count),
+                Err(e) => error!("Purge job failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,180 @@
+use crate::{auth, handlers::*};
+use actix_web::{middleware::from_fn, web};
+
+// Register every API route. The destructive /api/test endpoints are only added in
+// development mode, and only admins can call them.
+pub fn configure(cfg: &mut web::ServiceConfig, dev_mode: bool) {
+    cfg.route("/api/posts", web::get().to(get_posts_handler))
+        .route("/api/posts/{id}", web::get().to(get_post_by_id_handler))
+        .route("/api/posts/{id}", web::delete().to(delete_post_handler))
+        .route(
+            "/api/posts/{id}/restore",
+            web::post().to(restore_post_handler),
+        )
+        .route(
+            "/api/posts/{id}/publish",
+            web::post().to(publish_post_handler),
+        )
+        .route("/api/posts/{id}/repost", web::post().to(repost_handler))
+        .route(
+            "/api/posts/{id}/repost",
+            web::delete().to(undo_repost_handler),
+        )
+        .route("/api/posts/{id}/vote", web::post().to(vote_poll_handler))
+        .route("/api/users", web::get().to(get_users_handler))
+        .route("/api/users/{id}", web::get().to(get_user_by_id_handler))
+        .route("/api/users/{id}", web::delete().to(delete_user_handler))
+        .route(
+            "/api/users/{id}/restore",
+            web::post().to(restore_user_handler),
+        )
+        .route(
+            "/api/users/{id}/export",
+            web::post().to(request_export_handler),
+        )
+        .route(
+            "/api/users/{id}/export/{export_id}",
+            web::get().to(get_export_handler),
+        )
+        .route(
+            "/api/users/{id}/export/{export_id}/download",
+            web::get().to(download_export_handler),
+        )
+        .route("/api/comments", web::get().to(get_comments_handler))
+        .route(
+            "/api/comments/{id}",
+            web::get().to(get_comment_by_id_handler),
+        )
+        .route(
+            "/api/comments/{id}",
+            web::delete().to(delete_comment_handler),
+        )
+        .route(
+            "/api/comments/{id}/restore",
+            web::post().to(restore_comment_handler),
+        )
+        .route(
+            "/api/comments/post/{post_id}",
+            web::get().to(get_comments_by_post_id_handler),
+        )
+        .route(
+            "/api/comments/user/{user_id}",
+            web::get().to(get_comments_by_user_id_handler),
+        )
+        .route(
+            "/api/users/following/{user_id}",
+            web::get().to(get_following_users_handler),
+        )
+        .route(
+            "/api/users/followers/{user_id}",
+            web::get().to(get_followers_users_handler),
+        )
+        .route(
+            "/api/users/posts/{user_id}",
+            web::get().to(get_posts_by_user_id_handler),
+        )
+        .route(
+            "/api/users/{id}/bookmarks",
+            web::get().to(get_bookmarks_handler),
+        )
+        .route(
+            "/api/users/{id}/bookmarks",
+            web::post().to(add_bookmark_handler),
+        )
+        .route(
+            "/api/users/{id}/bookmarks",
+            web::delete().to(remove_bookmark_handler),
+        )
+        .route(
+            "/api/users/{id}/bookmarks/collections",
+            web::get().to(get_bookmark_collections_handler),
+        )
+        .route(
+            "/api/users/{id}/privacy",
+            web::put().to(update_privacy_handler),
+        )
+        .route(
+            "/api/users/{id}/follow_requests",
+            web::get().to(get_follow_requests_handler),
+        )
+        .route(
+            "/api/users/{id}/follow_requests/{follower_id}/approve",
+            web::post().to(approve_follow_request_handler),
+        )
+        .route(
+            "/api/users/{id}/follow_requests/{follower_id}/reject",
+            web::post().to(reject_follow_request_handler),
+        )
+        .route("/api/users/{id}/block", web::post().to(block_user_handler))
+        .route(
+            "/api/users/{id}/block",
+            web::delete().to(unblock_user_handler),
+        )
+        .route("/api/users/{id}/mute", web::post().to(mute_user_handler))
+        .route(
+            "/api/users/{id}/mute",
+            web::delete().to(unmute_user_handler),
+        )
+        .route(
+            "/api/users/{id}/blocks",
+            web::get().to(get_blocked_users_handler),
+        )
+        .route(
+            "/api/users/{id}/mutes",
+            web::get().to(get_muted_users_handler),
+        )
+        .route("/api/users/{id}/drafts", web::get().to(get_drafts_handler))
+        .route(
+            "/api/users/{id}/notifications",
+            web::get().to(get_notifications_handler),
+        )
+        .route("/api/reports", web::post().to(report_content_handler))
+        .service(
+            web::scope("/api/moderation")
+                .wrap(from_fn(auth::require_moderator))
+                .route("/reports", web::get().to(get_reports_handler))
+                .route("/reports/{id}", web::get().to(get_report_by_id_handler))
+                .route("/reports/{id}/claim", web::post().to(claim_report_handler))
+                .route(
+                    "/reports/{id}/resolve",
+                    web::post().to(resolve_report_handler),
+                ),
+        )
+        .service(
+            web::scope("/api/admin")
+                .wrap(from_fn(auth::require_admin))
+                .route("/users/{id}/role", web::put().to(update_user_role_handler))
+                .route("/audit", web::get().to(get_audit_log_handler))
+                .route("/cluster", web::get().to(get_cluster_status_handler)),
+        )
+        .route(
+            "/api/users/timeline/{user_id}",
+            web::get().to(get_timeline_handler),
+        )
+        .route(
+            "/api/posts/comments/{post_id}",
+            web::get().to(get_comments_by_post_id_handler),
+        )
+        .route("/api/create_user", web::post().to(create_user_handler))
+        .route("/api/auth/token", web::post().to(create_token_handler))
+        .route("/api/create_post", web::post().to(create_post_handler))
+        .route(
+            "/api/create_comment",
+            web::post().to(create_comment_handler),
+        )
+        .route("/api/follow_user", web::post().to(follow_user_handler))
+        .route("/api/health", web::get().to(readiness_handler))
+        .route("/livez", web::get().to(liveness_handler))
+        .route("/readyz", web::get().to(readiness_handler))
+        .route("/metrics", web::get().to(metrics_handler));
+
+    if dev_mode {
+        cfg.service(
+            web::scope("/api/test")
+                .wrap(from_fn(auth::require_admin))
+                .route("/populate", web::post().to(populate_database_handler))
+                .route("/populate/{id}", web::get().to(get_seed_job_handler))
+                .route("/clean", web::post().to(clean_database_handler)),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    auth::USER_ID_HEADER,
+    config::{CorsConfig, SecurityHeadersConfig},
+};
+use actix_cors::Cors;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY,
+        STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+    },
+    middleware::Next,
+    web, Error,
+};
+
+// traceparent and tracestate carry the W3C trace context of the frontend, and X-User-Id
+// names the caller
+const ALLOWED_HEADERS: [&str; 11] = [
+    "Content-Type",
+    "Authorization",
+    USER_ID_HEADER,
+    "Accept",
+    "Origin",
+    "X-Requested-With",
+    "X-Request-Id",
+    "traceparent",
+    "tracestate",
+    "Access-Control-Request-Method",
+    "Access-Control-Request-Headers",
+];
+
+// The request ID lets the frontend report which request failed
+const EXPOSED_HEADERS: [&str; 3] = ["Content-Length", "Content-Type", "X-Request-Id"];
+
+// Whether `origin` is one of the allowed origins or a subdomain of a wildcard entry.
+// https://*.example.com allows https://app.example.com but not https://example.com itself.
+pub fn origin_allowed(allowed_origins: &[String], origin: &str) -> bool {
+    allowed_origins
+        .iter()
+        .any(|allowed| match allowed.split_once("://*.") {
+            Some((scheme, domain)) => origin
+                .strip_prefix(scheme)
+                .and_then(|rest| rest.strip_prefix("://"))
+                .and_then(|host| host.strip_suffix(domain))
+                .and_then(|subdomain| subdomain.strip_suffix('.'))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+            None => allowed == origin,
+        })
+}
+
+pub fn cors(config: &CorsConfig) -> Cors {
+    let allowed_origins = config.allowed_origins.clone();
+    let cors = Cors::default()
+        .allowed_origin_fn(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origin_allowed(&allowed_origins, origin))
+        })
+        .allowed_methods(config.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(ALLOWED_HEADERS)
+        .expose_headers(EXPOSED_HEADERS)
+        .max_age(config.max_age_secs);
+    if config.allow_credentials {
+        cors.supports_credentials()
+    } else {
+        cors
+    }
+}
+
+// The security headers of the running environment, registered as app data for the middleware
+#[derive(Clone, Debug)]
+pub struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    // Only sent on HTTPS responses, browsers ignore it over plain HTTP
+    hsts: Option<HeaderValue>,
+}
+
+impl SecurityHeaders {
+    pub fn new(config: &SecurityHeadersConfig) -> Self {
+        let mut headers = Vec::new();
+        if let Ok(csp) = HeaderValue::from_str(&config.content_security_policy) {
+            if !csp.is_empty() {
+                headers.push((CONTENT_SECURITY_POLICY, csp));
+            }
+        }
+        if let Ok(policy) = HeaderValue::from_str(&config.referrer_policy) {
+            if !policy.is_empty() {
+                headers.push((REFERRER_POLICY, policy));
+            }
+        }
+        if config.nosniff {
+            headers.push((X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")));
+        }
+
+        let hsts = (config.hsts_max_age_secs > 0).then(|| {
+            let mut value = format!("max-age={}", config.hsts_max_age_secs);
+            if config.hsts_include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            HeaderValue::from_str(&value).expect("max-age is ASCII")
+        });
+
+        SecurityHeaders { headers, hsts }
+    }
+
+    fn apply(&self, response_headers: &mut HeaderMap, https: bool) {
+        let hsts = self
+            .hsts
+            .iter()
+            .filter(|_| https)
+            .map(|value| (&STRICT_TRANSPORT_SECURITY, value));
+        let headers = self.headers.iter().map(|(name, value)| (name, value));
+        for (name, value) in headers.chain(hsts) {
+            if !response_headers.contains_key(name) {
+                response_headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+// Middleware adding the security headers to every response, including errors returned
+// by inner middleware such as the role guards and CORS. Headers set by a handler win.
+pub async fn add_security_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let security_headers = req
+        .app_data::<web::Data<SecurityHeaders>>()
+        .expect("SecurityHeaders is registered on the app")
+        .clone();
+    let https = req.connection_info().scheme() == "https";
+
+    match next.call(req).await {
+        Ok(mut res) => {
+            security_headers.apply(res.headers_mut(), https);
+            Ok(res)
+        }
+        Err(e) => {
+            // The error only becomes a response later on, so render it here to add the headers
+            let mut response = e.error_response();
+            security_headers.apply(response.headers_mut(), https);
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
@@ -0,0 +1,600 @@
+use crate::{
+    lifecycle::BackgroundJobs,
+    models::{JobStatus, PostType, SeedOptions, SeedSummary},
+    repository::SeedRepo,
+    validation::parse_timestamp,
+};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::{
+    bson::{doc, Document},
+    error::Result,
+};
+use rand::{
+    distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, seq::SliceRandom, Rng,
+    SeedableRng,
+};
+use std::{collections::HashSet, sync::Arc};
+use tracing::{error, info};
+use uuid::Builder;
+
+// How far back generated activity reaches from the anchor time
+const HISTORY_DAYS: i64 = 180;
+
+// Exponent of the follower distribution; higher values concentrate followers on fewer users
+const FOLLOWER_SKEW: f64 = 1.1;
+
+// Give up on unique pairs after this many draws per requested pair, for counts close to
+// the number of possible pairs
+const MAX_DRAWS_PER_PAIR: usize = 20;
+
+const NAMES: [&str; 26] = [
+    "Alice", "Bob", "Charlie", "Daisy", "Eve", "Frank", "Grace", "Hank", "Ivy", "Jack", "Karen",
+    "Leo", "Mona", "Nina", "Oscar", "Paul", "Quinn", "Rita", "Steve", "Tina", "Uma", "Victor",
+    "Wendy", "Xander", "Yara", "Zane",
+];
+
+const BIO_TEMPLATES: [&str; 5] = [
+    "Digital creator passionate about {}",
+    "Exploring the world of {} one post at a time",
+    "Professional {} enthusiast",
+    "{} advocate | Content Creator",
+    "Living life through the lens of {}",
+];
+
+const INTERESTS: [&str; 10] = [
+    "technology",
+    "art",
+    "music",
+    "photography",
+    "travel",
+    "food",
+    "fitness",
+    "gaming",
+    "books",
+    "nature",
+];
+
+const CONTENT_TEMPLATES: [&str; 15] = [
+    "Exploring the transformative power of {} and how it is reshaping industries, driving innovation, and creating new opportunities for growth and development. From its origins to its current applications, this post dives deep into the impact of {} on our world.",
+    "The future of {} is here, and it's more exciting than ever. In this post, we examine the groundbreaking advancements in {} and their potential to revolutionize the way we live, work, and interact with technology.",
+    "What makes {} such a game-changer? This post uncovers the key innovations, challenges, and opportunities that {} brings to the table, and why it is set to redefine the future of technology.",
+    "A comprehensive look at the evolution of {} and its journey from a niche concept to a mainstream phenomenon. Discover the stories, breakthroughs, and visionaries behind {} and its role in shaping the future.",
+    "10 reasons why {} is not just a buzzword but a transformative force that is driving change across industries. This post explores the practical applications, benefits, and future potential of {}.",
+    "How {} is paving the way for a more sustainable, efficient, and connected world. This post delves into the innovations and ideas that are making {} a cornerstone of modern technology.",
+    "The untold story of {}: from its humble beginnings to its current status as a revolutionary technology. This post highlights the milestones, challenges, and future prospects of {}.",
+    "Behind the scenes of {}: a closer look at the people, ideas, and innovations that are driving its success. This post offers an insider's perspective on the world of {}.",
+    "My personal journey with {}: the lessons learned, the challenges faced, and the incredible potential that {} holds for the future. This post is a reflection on the transformative power of {}.",
+    "Revolutionizing the world with {}: an in-depth exploration of the technologies, ideas, and innovations that are making {} a driving force in the modern era.",
+    "The intersection of {} and everyday life: how this technology is quietly influencing the way we work, communicate, and solve problems. This post examines the subtle yet profound impact of {}.",
+    "The challenges and opportunities of {}: a balanced look at the hurdles that need to be overcome and the immense potential that lies ahead for this groundbreaking technology.",
+    "How {} is enabling new possibilities in fields like healthcare, education, and entertainment. This post explores the real-world applications of {} and their impact on society.",
+    "The role of {} in the global economy: how this technology is creating new markets, disrupting traditional industries, and driving economic growth worldwide.",
+    "A visionary look at the future of {}: what lies ahead for this technology, and how it could shape the next decade of innovation and progress.",
+];
+
+const TITLES: [&str; 10] = [
+    "The Future of AI",
+    "Blockchain Revolution",
+    "Sustainable Living",
+    "Digital Artistry",
+    "Remote Work Culture",
+    "Space Exploration",
+    "Virtual Reality Experiences",
+    "Renewable Energy Solutions",
+    "Quantum Computing Breakthroughs",
+    "Robotics in Everyday Life",
+];
+
+const TOPICS: [&str; 10] = [
+    "AI",
+    "blockchain",
+    "sustainability",
+    "digital art",
+    "remote work",
+    "space exploration",
+    "virtual reality",
+    "renewable energy",
+    "quantum computing",
+    "robotics",
+];
+
+const POST_TYPES: [PostType; 4] = [
+    PostType::Text,
+    PostType::Image,
+    PostType::Video,
+    PostType::Link,
+];
+
+const COMMENT_TEXTS: [&str; 15] = [
+    "This is incredible!",
+    "I totally agree with this.",
+    "Not sure about this one...",
+    "Thanks for sharing!",
+    "Interesting perspective.",
+    "Can you explain more?",
+    "This changed my view completely.",
+    "I have a different opinion.",
+    "Mind = blown 🤯",
+    "This is the content I'm here for!",
+    "Well said!",
+    "Couldn't agree more.",
+    "This is the way.",
+    "Very insightful!",
+    "You might want to reconsider this.",
+];
+
+// Who did what and when, without any document content. Holding only IDs, times, index
+// pairs and counters keeps millions of documents cheap, and lets every counter be known
+// before the first document is written.
+struct Plan {
+    anchor: DateTime<Utc>,
+    user_ids: Vec<String>,
+    user_joined: Vec<DateTime<Utc>>,
+    post_ids: Vec<String>,
+    post_authors: Vec<usize>,
+    post_created: Vec<DateTime<Utc>>,
+    // (user, post) index pairs
+    comments: Vec<(usize, usize)>,
+    likes: Vec<(usize, usize)>,
+    // (follower, following) index pairs
+    follows: Vec<(usize, usize)>,
+}
+
+// Per-document counters, indexed like the plan's users and posts
+struct Counters {
+    user_posts: Vec<i32>,
+    user_comments: Vec<i32>,
+    user_likes_given: Vec<i32>,
+    user_likes_received: Vec<i32>,
+    user_followers: Vec<i32>,
+    user_following: Vec<i32>,
+    post_comments: Vec<i32>,
+    post_likes: Vec<i32>,
+}
+
+// UUIDs drawn from the seeded generator so IDs are reproducible too
+fn seeded_uuid(rng: &mut StdRng) -> String {
+    Builder::from_random_bytes(rng.gen())
+        .into_uuid()
+        .to_string()
+}
+
+// A time between `earliest` and `latest`, or `latest` when the range is empty
+fn random_time_between(
+    rng: &mut StdRng,
+    earliest: DateTime<Utc>,
+    latest: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let span = (latest - earliest).num_seconds();
+    if span <= 0 {
+        return latest;
+    }
+    earliest + Duration::seconds(rng.gen_range(0..=span))
+}
+
+// Midnight UTC today, so runs on the same day produce identical timestamps
+fn default_anchor() -> DateTime<Utc> {
+    Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or_else(Utc::now)
+}
+
+// Draw up to `count` distinct pairs, skipping pairs `accept` rejects
+fn unique_pairs(
+    rng: &mut StdRng,
+    count: usize,
+    max_pairs: usize,
+    mut draw: impl FnMut(&mut StdRng) -> (usize, usize),
+    accept: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    let target = count.min(max_pairs);
+    let mut pairs = Vec::with_capacity(target);
+    let mut seen = HashSet::with_capacity(target);
+    let mut draws = 0;
+    while pairs.len() < target && draws < target.saturating_mul(MAX_DRAWS_PER_PAIR) {
+        draws += 1;
+        let (a, b) = draw(rng);
+        if accept(a, b) && seen.insert((a, b)) {
+            pairs.push((a, b));
+        }
+    }
+    pairs
+}
+
+impl Plan {
+    fn new(rng: &mut StdRng, options: &SeedOptions, anchor: DateTime<Utc>) -> Plan {
+        let earliest = anchor - Duration::days(HISTORY_DAYS);
+        let user_total = options.users;
+        let mut user_ids = Vec::with_capacity(user_total);
+        let mut user_joined = Vec::with_capacity(user_total);
+        for _ in 0..user_total {
+            user_ids.push(seeded_uuid(rng));
+            user_joined.push(random_time_between(rng, earliest, anchor));
+        }
+
+        // Posts are written some time after their author joined
+        let post_total = if user_total == 0 { 0 } else { options.posts };
+        let mut post_ids = Vec::with_capacity(post_total);
+        let mut post_authors = Vec::with_capacity(post_total);
+        let mut post_created = Vec::with_capacity(post_total);
+        for _ in 0..post_total {
+            let author = rng.gen_range(0..user_total);
+            post_ids.push(seeded_uuid(rng));
+            post_authors.push(author);
+            post_created.push(random_time_between(rng, user_joined[author], anchor));
+        }
+
+        let comments = if post_total == 0 {
+            Vec::new()
+        } else {
+            (0..options.comments)
+                .map(|_| (rng.gen_range(0..user_total), rng.gen_range(0..post_total)))
+                .collect()
+        };
+
+        let likes = if post_total == 0 {
+            Vec::new()
+        } else {
+            unique_pairs(
+                rng,
+                options.likes,
+                user_total.saturating_mul(post_total),
+                |rng| (rng.gen_range(0..user_total), rng.gen_range(0..post_total)),
+                |_, _| true,
+            )
+        };
+
+        // Who gets followed follows a power law, so a few users collect most followers
+        // while most have only a handful. Ranks are shuffled so the most followed users
+        // are not simply the first ones.
+        let follows = if user_total < 2 {
+            Vec::new()
+        } else {
+            let mut ranks: Vec<usize> = (1..=user_total).collect();
+            ranks.shuffle(rng);
+            let weights = ranks
+                .iter()
+                .map(|rank| 1.0 / (*rank as f64).powf(FOLLOWER_SKEW));
+            let popularity = WeightedIndex::new(weights).unwrap();
+            unique_pairs(
+                rng,
+                options.follows,
+                user_total.saturating_mul(user_total - 1),
+                |rng| (rng.gen_range(0..user_total), popularity.sample(rng)),
+                |follower, following| follower != following,
+            )
+        };
+
+        Plan {
+            anchor,
+            user_ids,
+            user_joined,
+            post_ids,
+            post_authors,
+            post_created,
+            comments,
+            likes,
+            follows,
+        }
+    }
+
+    fn counters(&self) -> Counters {
+        let mut counters = Counters {
+            user_posts: vec![0; self.user_ids.len()],
+            user_comments: vec![0; self.user_ids.len()],
+            user_likes_given: vec![0; self.user_ids.len()],
+            user_likes_received: vec![0; self.user_ids.len()],
+            user_followers: vec![0; self.user_ids.len()],
+            user_following: vec![0; self.user_ids.len()],
+            post_comments: vec![0; self.post_ids.len()],
+            post_likes: vec![0; self.post_ids.len()],
+        };
+        for author in &self.post_authors {
+            counters.user_posts[*author] += 1;
+        }
+        for (user, post) in &self.comments {
+            counters.user_comments[*user] += 1;
+            counters.post_comments[*post] += 1;
+        }
+        for (user, post) in &self.likes {
+            counters.user_likes_given[*user] += 1;
+            counters.user_likes_received[self.post_authors[*post]] += 1;
+            counters.post_likes[*post] += 1;
+        }
+        for (follower, following) in &self.follows {
+            counters.user_following[*follower] += 1;
+            counters.user_followers[*following] += 1;
+        }
+        counters
+    }
+
+    // The later of when a user joined and when a post was written, the earliest an
+    // interaction between them can happen
+    fn interaction_start(&self, user: usize, post: usize) -> DateTime<Utc> {
+        self.user_joined[user].max(self.post_created[post])
+    }
+
+    fn summary(&self, seed: u64) -> SeedSummary {
+        SeedSummary {
+            seed,
+            anchor: self.anchor.to_rfc3339(),
+            users: self.user_ids.len(),
+            posts: self.post_ids.len(),
+            comments: self.comments.len(),
+            likes: self.likes.len(),
+            follows: self.follows.len(),
+        }
+    }
+}
+
+fn user_doc(rng: &mut StdRng, plan: &Plan, counters: &Counters, index: usize) -> Document {
+    let name = NAMES.choose(rng).unwrap();
+    // The index keeps usernames and emails unique however many users are generated
+    let number = index + 1;
+    let bio = BIO_TEMPLATES
+        .choose(rng)
+        .unwrap()
+        .replace("{}", INTERESTS.choose(rng).unwrap());
+
+    doc! {
+        "_id": &plan.user_ids[index],
+        "username": format!("{}_{}", name, number),
+        "email": format!("{}{}@example.com", name.to_lowercase(), number),
+        "password_hash": format!("hashed_password_{}", number),
+        "bio": bio,
+        "profile_picture_url": format!("https://randomuser.me/api/portraits/{}/{}.jpg",
+            if rng.gen_bool(0.5) { "men" } else { "women" },
+            rng.gen_range(1..100)
+        ),
+        "role": "user",
+        "join_date": plan.user_joined[index].to_rfc3339(),
+        "follower_count": counters.user_followers[index],
+        "following_count": counters.user_following[index],
+        "post_count": counters.user_posts[index],
+        "comment_count": counters.user_comments[index],
+        "total_likes_received": counters.user_likes_received[index],
+        "total_likes_given": counters.user_likes_given[index],
+    }
+}
+
+fn post_doc(rng: &mut StdRng, plan: &Plan, counters: &Counters, index: usize) -> Document {
+    let topic = TOPICS.choose(rng).unwrap();
+    let content = CONTENT_TEMPLATES.choose(rng).unwrap().replace("{}", topic);
+    let title = TITLES.choose(rng).unwrap();
+
+    let post_type = POST_TYPES.choose(rng).unwrap();
+    let media_urls = match post_type {
+        PostType::Image => vec![format!(
+            "https://picsum.photos/seed/{}/800/600",
+            seeded_uuid(rng)
+        )],
+        PostType::Video => vec![format!(
+            "https://example.com/videos/{}.mp4",
+            seeded_uuid(rng)
+        )],
+        PostType::Link => vec![format!("https://example.com/article/{}", seeded_uuid(rng))],
+        PostType::Text | PostType::Poll => Vec::new(),
+    };
+
+    let created_at = plan.post_created[index].to_rfc3339();
+    doc! {
+        "_id": &plan.post_ids[index],
+        "user_id": &plan.user_ids[plan.post_authors[index]],
+        "title": title,
+        "content": &content,
+        "media_urls": &media_urls,
+        "post_type": post_type.as_str(),
+        "status": "published",
+        "created_at": &created_at,
+        "published_at": &created_at,
+        "like_count": counters.post_likes[index],
+        "comment_count": counters.post_comments[index],
+    }
+}
+
+fn comment_doc(rng: &mut StdRng, plan: &Plan, (user, post): (usize, usize)) -> Document {
+    let created_at = random_time_between(rng, plan.interaction_start(user, post), plan.anchor);
+    doc! {
+        "_id": seeded_uuid(rng),
+        "post_id": &plan.post_ids[post],
+        "user_id": &plan.user_ids[user],
+        "content": COMMENT_TEXTS.choose(rng).unwrap(),
+        "created_at": created_at.to_rfc3339(),
+    }
+}
+
+fn like_doc(rng: &mut StdRng, plan: &Plan, (user, post): (usize, usize)) -> Document {
+    let created_at = random_time_between(rng, plan.interaction_start(user, post), plan.anchor);
+    doc! {
+        "_id": seeded_uuid(rng),
+        "user_id": &plan.user_ids[user],
+        "post_id": &plan.post_ids[post],
+        "created_at": created_at.to_rfc3339(),
+    }
+}
+
+fn follow_doc(rng: &mut StdRng, plan: &Plan, (follower, following): (usize, usize)) -> Document {
+    let start = plan.user_joined[follower].max(plan.user_joined[following]);
+    let created_at = random_time_between(rng, start, plan.anchor);
+    doc! {
+        "_id": seeded_uuid(rng),
+        "follower_id": &plan.user_ids[follower],
+        "following_id": &plan.user_ids[following],
+        "created_at": created_at.to_rfc3339(),
+    }
+}
+
+// Writes generated documents in unordered insert_many batches and reports progress,
+// both to the log and to the seed job when there is one
+struct BatchWriter<'a> {
+    store: &'a dyn SeedRepo,
+    job_id: Option<&'a str>,
+    batch_size: usize,
+    progress: Document,
+}
+
+impl BatchWriter<'_> {
+    async fn write(
+        &mut self,
+        collection: &str,
+        total: usize,
+        mut next: impl FnMut(usize) -> Document,
+    ) -> Result<()> {
+        let mut inserted = 0;
+        while inserted < total {
+            let end = (inserted + self.batch_size).min(total);
+            let batch: Vec<Document> = (inserted..end).map(&mut next).collect();
+            self.store.insert_many(collection, batch).await?;
+            inserted = end;
+            self.report(collection, inserted, total).await?;
+        }
+        Ok(())
+    }
+
+    async fn report(&mut self, collection: &str, inserted: usize, total: usize) -> Result<()> {
+        info!("Seeded {}/{} {}", inserted, total, collection);
+        self.progress.insert(
+            collection,
+            doc! { "inserted": inserted as i64, "total": total as i64 },
+        );
+        if let Some(job_id) = self.job_id {
+            self.store
+                .update_job(
+                    job_id,
+                    doc! {
+                        "progress": self.progress.clone(),
+                        "updated_at": Utc::now().to_rfc3339(),
+                    },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+// Replace all application data with a freshly generated dataset. Progress is recorded
+// on the seed job `job_id` when one is given.
+pub async fn populate(
+    store: &dyn SeedRepo,
+    options: &SeedOptions,
+    job_id: Option<&str>,
+) -> Result<SeedSummary> {
+    store.clear_data().await?;
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let anchor = options
+        .anchor
+        .as_deref()
+        .and_then(parse_timestamp)
+        .unwrap_or_else(default_anchor);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let plan = Plan::new(&mut rng, options, anchor);
+    let counters = plan.counters();
+    let summary = plan.summary(seed);
+    info!(
+        "Seeding {} users, {} posts, {} comments, {} likes and {} follows with seed {}",
+        summary.users, summary.posts, summary.comments, summary.likes, summary.follows, seed
+    );
+
+    let mut writer = BatchWriter {
+        store,
+        job_id,
+        batch_size: options.batch_size.max(1),
+        progress: Document::new(),
+    };
+    for (collection, total) in [
+        ("users", summary.users),
+        ("posts", summary.posts),
+        ("comments", summary.comments),
+        ("likes", summary.likes),
+        ("follows", summary.follows),
+    ] {
+        writer.report(collection, 0, total).await?;
+    }
+
+    writer
+        .write("users", summary.users, |i| {
+            user_doc(&mut rng, &plan, &counters, i)
+        })
+        .await?;
+    writer
+        .write("posts", summary.posts, |i| {
+            post_doc(&mut rng, &plan, &counters, i)
+        })
+        .await?;
+    writer
+        .write("comments", summary.comments, |i| {
+            comment_doc(&mut rng, &plan, plan.comments[i])
+        })
+        .await?;
+    writer
+        .write("likes", summary.likes, |i| {
+            like_doc(&mut rng, &plan, plan.likes[i])
+        })
+        .await?;
+    writer
+        .write("follows", summary.follows, |i| {
+            follow_doc(&mut rng, &plan, plan.follows[i])
+        })
+        .await?;
+
+    Ok(summary)
+}
+
+// Run a seed job in the background, recording the outcome on the job so clients polling
+// its progress see it
+pub fn spawn_seed_job(
+    jobs: &BackgroundJobs,
+    store: Arc<dyn SeedRepo>,
+    job_id: String,
+    options: SeedOptions,
+) {
+    jobs.spawn(async move {
+        let now = Utc::now().to_rfc3339();
+        let started = store
+            .update_job(
+                &job_id,
+                doc! {
+                    "status": JobStatus::Running.as_str(),
+                    "started_at": &now,
+                    "updated_at": &now,
+                },
+            )
+            .await;
+        if let Err(e) = started {
+            error!("Error starting seed job {}: {}", job_id, e);
+            if let Err(e) = store.unlock(&job_id).await {
+                error!("Error releasing the lock of seed job {}: {}", job_id, e);
+            }
+            return;
+        }
+
+        let update = match populate(store.as_ref(), &options, Some(&job_id)).await {
+            Ok(summary) => {
+                info!("Seed job {} completed with seed {}", job_id, summary.seed);
+                doc! {
+                    "status": JobStatus::Completed.as_str(),
+                    "seed": summary.seed.to_string(),
+                    "anchor": &summary.anchor,
+                    "completed_at": Utc::now().to_rfc3339(),
+                }
+            }
+            Err(e) => {
+                error!("Seed job {} failed: {}", job_id, e);
+                doc! {
+                    "status": JobStatus::Failed.as_str(),
+                    "error": e.to_string(),
+                    "completed_at": Utc::now().to_rfc3339(),
+                }
+            }
+        };
+        if let Err(e) = store.update_job(&job_id, update).await {
+            error!("Error recording the outcome of seed job {}: {}", job_id, e);
+        }
+        if let Err(e) = store.unlock(&job_id).await {
+            error!("Error releasing the lock of seed job {}: {}", job_id, e);
+        }
+    });
+}
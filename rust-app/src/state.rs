@@ -1,5 +1,67 @@
-use mongodb::Database;
+use crate::{
+    lifecycle::BackgroundJobs,
+    repository::{
+        AuditRepo, BlockRepo, BookmarkRepo, CommentRepo, DatabaseRepo, ExportRepo, FollowRepo,
+        LikeRepo, NotificationRepo, PostRepo, ReportRepo, SeedRepo, Store, UserRepo,
+    },
+};
+use std::{sync::Arc, time::Duration};
 
 pub struct AppState {
-    pub db: Database,
+    pub users: Arc<dyn UserRepo>,
+    pub posts: Arc<dyn PostRepo>,
+    pub comments: Arc<dyn CommentRepo>,
+    pub follows: Arc<dyn FollowRepo>,
+    pub likes: Arc<dyn LikeRepo>,
+    pub bookmarks: Arc<dyn BookmarkRepo>,
+    pub notifications: Arc<dyn NotificationRepo>,
+    pub blocks: Arc<dyn BlockRepo>,
+    pub reports: Arc<dyn ReportRepo>,
+    pub audit_log: Arc<dyn AuditRepo>,
+    pub exports: Arc<dyn ExportRepo>,
+    pub seeding: Arc<dyn SeedRepo>,
+    pub database: Arc<dyn DatabaseRepo>,
+    // How long soft-deleted users, posts and comments can be restored before they are purged
+    pub deletion_retention: Duration,
+    // How long entries stay in the audit log before the TTL index expires them
+    pub audit_retention: Duration,
+    // Seed and export jobs started by requests, drained on shutdown
+    pub jobs: BackgroundJobs,
+    // Signs the tokens that authenticate callers, see `auth::issue_token`
+    pub auth_secret: String,
+}
+
+impl AppState {
+    // Serve every repository from `store`, e.g. a `MongoStore` or a `MemoryStore`
+    pub fn new<S: Store>(
+        store: Arc<S>,
+        deletion_retention: Duration,
+        audit_retention: Duration,
+    ) -> Self {
+        AppState {
+            users: store.clone(),
+            posts: store.clone(),
+            comments: store.clone(),
+            follows: store.clone(),
+            likes: store.clone(),
+            bookmarks: store.clone(),
+            notifications: store.clone(),
+            blocks: store.clone(),
+            reports: store.clone(),
+            audit_log: store.clone(),
+            exports: store.clone(),
+            seeding: store.clone(),
+            database: store,
+            deletion_retention,
+            audit_retention,
+            jobs: BackgroundJobs::new(),
+            auth_secret: hex::encode(rand::random::<[u8; 32]>()),
+        }
+    }
+
+    // Sign tokens with `secret` instead of the random one picked at construction
+    pub fn with_auth_secret(mut self, secret: String) -> Self {
+        self.auth_secret = secret;
+        self
+    }
 }
@@ -0,0 +1,201 @@
+use crate::config::{TraceExporter, TracingConfig};
+use actix_web::http::header::HeaderMap;
+use mongodb::{
+    event::command::{CommandFailedEvent, CommandStartedEvent},
+    options::ServerAddress,
+};
+use opentelemetry::{
+    propagation::{Extractor, TextMapPropagator},
+    trace::{SpanId, Status, TracerProvider},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    error::{OTelSdkError, OTelSdkResult},
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracer, SdkTracerProvider, SpanData, SpanExporter},
+    Resource,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    sync::Mutex,
+    time::SystemTime,
+};
+use tracing::{info_span, Span};
+
+// Scope of the spans in exported traces
+const TRACER_NAME: &str = "ddbp";
+
+// Build the tracer provider for the configured exporter, or None when tracing is off.
+// The provider has to be shut down on exit so the last batch of spans is sent.
+pub fn tracer_provider(
+    config: &TracingConfig,
+    environment: &str,
+) -> io::Result<Option<SdkTracerProvider>> {
+    let builder = SdkTracerProvider::builder()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .with_attributes([
+                    KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+                    KeyValue::new("deployment.environment.name", environment.to_string()),
+                ])
+                .build(),
+        );
+    let builder = match config.exporter {
+        TraceExporter::None => return Ok(None),
+        TraceExporter::Otlp => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(&config.otlp_endpoint)
+                .build()
+                .map_err(io::Error::other)?;
+            builder.with_batch_exporter(exporter)
+        }
+        TraceExporter::Stdout => builder.with_batch_exporter(JsonLinesExporter::new(io::stdout())),
+        TraceExporter::File => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&config.file)?;
+            builder.with_batch_exporter(JsonLinesExporter::new(file))
+        }
+    };
+    Ok(Some(builder.build()))
+}
+
+// The tracer the tracing layer records spans with
+pub fn tracer(provider: &SdkTracerProvider) -> SdkTracer {
+    provider.tracer(TRACER_NAME)
+}
+
+// Exporter writing one JSON object per span, for local work without a collector
+pub struct JsonLinesExporter {
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLinesExporter {
+    pub fn new(output: impl Write + Send + 'static) -> Self {
+        JsonLinesExporter {
+            output: Mutex::new(Box::new(output)),
+        }
+    }
+}
+
+impl fmt::Debug for JsonLinesExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JsonLinesExporter")
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos())
+}
+
+// A span as one line of JSON, with the IDs in the hex form trace viewers search for
+pub fn span_to_json(span: &SpanData) -> Value {
+    let attributes: Map<String, Value> = span
+        .attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), Value::from(kv.value.to_string())))
+        .collect();
+    let parent_span_id =
+        (span.parent_span_id != SpanId::INVALID).then(|| span.parent_span_id.to_string());
+    let duration = span
+        .end_time
+        .duration_since(span.start_time)
+        .unwrap_or_default();
+    json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": parent_span_id,
+        "name": span.name,
+        "kind": format!("{:?}", span.span_kind).to_lowercase(),
+        "start_time_unix_nano": unix_nanos(span.start_time).to_string(),
+        "duration_ms": duration.as_secs_f64() * 1000.0,
+        "status": match &span.status {
+            Status::Unset => "unset".to_string(),
+            Status::Ok => "ok".to_string(),
+            Status::Error { description } if description.is_empty() => {
+                "error".to_string()
+            }
+            Status::Error { description } => {
+                format!("error: {}", description)
+            }
+        },
+        "attributes": attributes,
+    })
+}
+
+impl SpanExporter for JsonLinesExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        for span in &batch {
+            writeln!(output, "{}", span_to_json(span))
+                .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))?;
+        }
+        output
+            .flush()
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        output
+            .flush()
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+// The trace the caller is part of, from the W3C traceparent and tracestate headers the
+// frontend sends. Without them the request starts a new trace.
+pub fn extract_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+// Span for a MongoDB command, a child of the handler that sent it. It is named like
+// "find posts" and records which replica set member served the command.
+pub fn command_span(event: &CommandStartedEvent, collection: &str) -> Span {
+    let (address, port) = match &event.connection.address {
+        ServerAddress::Tcp { host, port } => (host.clone(), port.unwrap_or(27017)),
+        address => (address.to_string(), 0),
+    };
+    info_span!(
+        "mongodb",
+        otel.name = %format!("{} {}", event.command_name, collection),
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+        otel.status_description = tracing::field::Empty,
+        db.system.name = "mongodb",
+        db.namespace = %event.db,
+        db.collection.name = %collection,
+        db.operation.name = %event.command_name,
+        server.address = %address,
+        server.port = port,
+    )
+}
+
+// Mark the command span as failed. It ends when dropped.
+pub fn fail_command_span(span: &Span, event: &CommandFailedEvent) {
+    span.record("otel.status_code", "error");
+    span.record("otel.status_description", event.failure.to_string());
+}
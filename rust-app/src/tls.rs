@@ -0,0 +1,172 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::LOCATION,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, ServerConfig,
+};
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info};
+
+// Serves the certificate most recently loaded from disk. Reloading swaps it for new
+// handshakes while established connections keep the one they negotiated.
+pub struct ReloadableCertResolver {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+fn invalid_data(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), message),
+    )
+}
+
+fn load_certified_key(cert_file: &Path, key_file: &Path) -> io::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_file)?))?;
+    if certs.is_empty() {
+        return Err(invalid_data(cert_file, "no PEM certificates found"));
+    }
+
+    let mut reader = BufReader::new(File::open(key_file)?);
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => break key,
+            Some(_) => continue,
+            None => return Err(invalid_data(key_file, "no PEM private key found")),
+        }
+    };
+    let signing_key = sign::any_supported_type(&PrivateKey(key))
+        .map_err(|_| invalid_data(key_file, "unsupported private key type"))?;
+
+    Ok(CertifiedKey::new(
+        certs.into_iter().map(Certificate).collect(),
+        signing_key,
+    ))
+}
+
+impl ReloadableCertResolver {
+    pub fn load(cert_file: &Path, key_file: &Path) -> io::Result<Arc<Self>> {
+        let certified_key = load_certified_key(cert_file, key_file)?;
+        Ok(Arc::new(ReloadableCertResolver {
+            cert_file: cert_file.to_path_buf(),
+            key_file: key_file.to_path_buf(),
+            current: RwLock::new(Arc::new(certified_key)),
+        }))
+    }
+
+    // Read the files again; on failure the previous certificate stays in use
+    pub fn reload(&self) -> io::Result<()> {
+        let certified_key = load_certified_key(&self.cert_file, &self.key_file)?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(certified_key);
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+pub fn server_config(resolver: Arc<ReloadableCertResolver>) -> ServerConfig {
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver)
+}
+
+// Reload the certificate whenever the process receives SIGHUP, e.g. after a renewal
+pub fn spawn_reload_on_sighup(resolver: Arc<ReloadableCertResolver>) -> io::Result<()> {
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match resolver.reload() {
+                Ok(()) => info!(
+                    "Reloaded TLS certificate from {}",
+                    resolver.cert_file.display()
+                ),
+                Err(e) => error!(
+                    "Could not reload TLS certificate, keeping the old one: {}",
+                    e
+                ),
+            }
+        }
+    });
+    Ok(())
+}
+
+// Port of the HTTPS listener, registered as app data for the redirect middleware
+#[derive(Clone, Copy, Debug)]
+pub struct HttpsRedirect {
+    pub port: u16,
+}
+
+// The host of a Host header without its port, keeping IPv6 brackets
+fn host_without_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.find(']').map_or(host, |end| &host[..=end]);
+    }
+    host.split(':').next().unwrap_or(host)
+}
+
+// Middleware answering plain HTTP requests with a permanent redirect to the HTTPS listener
+pub async fn redirect_to_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let redirect = *req
+        .app_data::<web::Data<HttpsRedirect>>()
+        .expect("HttpsRedirect is registered on the app")
+        .get_ref();
+    let location = {
+        let connection = req.connection_info();
+        if connection.scheme() == "https" {
+            None
+        } else {
+            let host = host_without_port(connection.host());
+            let port = match redirect.port {
+                443 => String::new(),
+                port => format!(":{}", port),
+            };
+            let path = req
+                .uri()
+                .path_and_query()
+                .map_or("/", |path_and_query| path_and_query.as_str());
+            Some(format!("https://{}{}{}", host, port, path))
+        }
+    };
+
+    match location {
+        Some(location) => Ok(req
+            .into_response(
+                HttpResponse::PermanentRedirect()
+                    .insert_header((LOCATION, location))
+                    .finish(),
+            )
+            .map_into_right_body()),
+        None => next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    errors::is_duplicate_key_error,
+    models::{Comment, Follow, Like, Post, User},
+    repository::TransferRepo,
+};
+use mongodb::bson::{self, Bson, Document};
+use serde::de::DeserializeOwned;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+use tracing::info;
+
+// Every collection holding application data, in the order an import should restore them
+pub const DATA_COLLECTIONS: [&str; 14] = [
+    "users",
+    "posts",
+    "comments",
+    "likes",
+    "follows",
+    "reposts",
+    "poll_votes",
+    "bookmarks",
+    "follow_requests",
+    "blocks",
+    "mutes",
+    "notifications",
+    "reports",
+    "moderation_log",
+];
+
+// What an import does with a document whose _id already exists
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ConflictMode {
+    // Replace the existing document
+    Upsert,
+    // Keep the existing document and carry on
+    Skip,
+    // Stop the import at the first conflict
+    Fail,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub inserted: u64,
+    pub replaced: u64,
+    pub skipped: u64,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn check_model<T: DeserializeOwned>(document: Document) -> Result<(), String> {
+    bson::from_document::<T>(document)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Check a document against the model its collection is written from. Posts store their
+// poll with tallies, so the poll is checked in the shape it was submitted in.
+fn validate_document(collection: &str, document: &Document) -> Result<(), String> {
+    if !document.contains_key("_id") {
+        return Err("missing _id".to_string());
+    }
+    match collection {
+        "users" => check_model::<User>(document.clone()),
+        "posts" => {
+            let mut post = document.clone();
+            if let Ok(poll) = document.get_document("poll") {
+                let mut submitted = poll.clone();
+                let options: Vec<Bson> = poll
+                    .get_array("options")
+                    .map(|options| {
+                        options
+                            .iter()
+                            .filter_map(|option| option.as_document())
+                            .map(|option| Bson::from(option.get_str("text").unwrap_or_default()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                submitted.insert("options", options);
+                post.insert("poll", submitted);
+            }
+            check_model::<Post>(post)
+        }
+        "comments" => check_model::<Comment>(document.clone()),
+        "likes" => check_model::<Like>(document.clone()),
+        "follows" => check_model::<Follow>(document.clone()),
+        _ => Ok(()),
+    }
+}
+
+// Stream a collection to `path`, one canonical Extended JSON document per line
+pub async fn export_collection(
+    store: &dyn TransferRepo,
+    collection: &str,
+    path: &Path,
+    batch_size: u32,
+) -> io::Result<u64> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let exported = store
+        .for_each(collection, batch_size, &mut |document| {
+            serde_json::to_writer(
+                &mut writer,
+                &Bson::Document(document).into_canonical_extjson(),
+            )?;
+            writer.write_all(b"\n")
+        })
+        .await
+        .map_err(io::Error::other)?;
+    writer.flush()?;
+    Ok(exported)
+}
+
+async fn write_batch(
+    store: &dyn TransferRepo,
+    collection: &str,
+    batch: Vec<Document>,
+    mode: ConflictMode,
+    summary: &mut ImportSummary,
+) -> io::Result<()> {
+    let written = store
+        .write_batch(collection, batch, mode)
+        .await
+        .map_err(|e| {
+            if is_duplicate_key_error(&e) {
+                invalid_data(format!(
+                    "A document in {} already exists: {}",
+                    collection, e
+                ))
+            } else {
+                io::Error::other(e)
+            }
+        })?;
+    summary.inserted += written.inserted;
+    summary.replaced += written.replaced;
+    summary.skipped += written.skipped;
+    Ok(())
+}
+
+// Read Extended JSON lines from `path` into a collection, validating every document
+// before anything from its batch is written
+pub async fn import_collection(
+    store: &dyn TransferRepo,
+    collection: &str,
+    path: &Path,
+    batch_size: usize,
+    mode: ConflictMode,
+) -> io::Result<ImportSummary> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut summary = ImportSummary::default();
+    let mut batch = Vec::with_capacity(batch_size);
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        let value: serde_json::Value = serde_json::from_str(&line)
+            .map_err(|e| invalid_data(format!("{}:{}: {}", path.display(), line_number, e)))?;
+        let document = match Bson::try_from(value) {
+            Ok(Bson::Document(document)) => document,
+            Ok(_) => {
+                return Err(invalid_data(format!(
+                    "{}:{}: expected a JSON object",
+                    path.display(),
+                    line_number
+                )))
+            }
+            Err(e) => {
+                return Err(invalid_data(format!(
+                    "{}:{}: {}",
+                    path.display(),
+                    line_number,
+                    e
+                )))
+            }
+        };
+        validate_document(collection, &document).map_err(|e| {
+            invalid_data(format!(
+                "{}:{}: invalid {} document: {}",
+                path.display(),
+                line_number,
+                collection,
+                e
+            ))
+        })?;
+
+        batch.push(document);
+        if batch.len() >= batch_size {
+            write_batch(
+                store,
+                collection,
+                std::mem::take(&mut batch),
+                mode,
+                &mut summary,
+            )
+            .await?;
+            info!(
+                "Imported {} documents into {}",
+                summary.inserted + summary.replaced,
+                collection
+            );
+        }
+    }
+    if !batch.is_empty() {
+        write_batch(store, collection, batch, mode, &mut summary).await?;
+    }
+    Ok(summary)
+}
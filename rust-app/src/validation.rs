@@ -0,0 +1,338 @@
+use crate::{
+    errors::AppError,
+    models::{Poll, Post, PostType, PostVisibility, SeedOptions},
+};
+use chrono::{DateTime, Utc};
+use url::Url;
+use uuid::Uuid;
+
+// Content limits per post type
+pub const MAX_TEXT_CONTENT_LENGTH: usize = 5000;
+pub const MAX_CAPTION_LENGTH: usize = 2200;
+pub const MAX_LINK_CONTENT_LENGTH: usize = 1000;
+pub const MAX_POLL_QUESTION_LENGTH: usize = 300;
+
+// Media limits per post type
+pub const MIN_IMAGES_PER_POST: usize = 1;
+pub const MAX_IMAGES_PER_POST: usize = 10;
+
+// Bookmark collection limits
+pub const DEFAULT_BOOKMARK_COLLECTION: &str = "Saved";
+pub const MAX_BOOKMARK_COLLECTION_LENGTH: usize = 50;
+
+// Mention limits
+pub const MAX_MENTIONS_PER_POST: usize = 50;
+
+// Poll limits
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 6;
+pub const MAX_POLL_OPTION_LENGTH: usize = 80;
+
+// Moderation limits
+pub const MAX_REPORT_REASON_LENGTH: usize = 1000;
+pub const MAX_MODERATION_NOTE_LENGTH: usize = 1000;
+
+// Largest insert_many batch a seeding run may use
+pub const MAX_SEED_BATCH_SIZE: usize = 10_000;
+
+fn invalid(field: &str, message: impl Into<String>) -> AppError {
+    AppError::ValidationError {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+// Returns true for absolute http(s) URLs with a host
+pub fn is_http_url(value: &str) -> bool {
+    match Url::parse(value) {
+        Ok(url) => matches!(url.scheme(), "http" | "https") && url.host_str().is_some(),
+        Err(_) => false,
+    }
+}
+
+// Media entries may be either a hosted URL or the ID of an uploaded media object
+fn is_media_reference(value: &str) -> bool {
+    is_http_url(value) || Uuid::parse_str(value).is_ok()
+}
+
+impl PostType {
+    pub fn max_content_length(&self) -> usize {
+        match self {
+            PostType::Text => MAX_TEXT_CONTENT_LENGTH,
+            PostType::Image | PostType::Video => MAX_CAPTION_LENGTH,
+            PostType::Link => MAX_LINK_CONTENT_LENGTH,
+            PostType::Poll => MAX_POLL_QUESTION_LENGTH,
+        }
+    }
+}
+
+// Validate post content and media against the rules of its post type
+pub fn validate_post(post: &Post) -> Result<(), AppError> {
+    if post.user_id.trim().is_empty() {
+        return Err(invalid("user_id", "User ID cannot be empty"));
+    }
+
+    if post.content.trim().is_empty() {
+        return Err(invalid("content", "Post content cannot be empty"));
+    }
+
+    let max_length = post.post_type.max_content_length();
+    let length = post.content.chars().count();
+    if length > max_length {
+        return Err(invalid(
+            "content",
+            format!(
+                "Post content is {} characters, the maximum for this post type is {}",
+                length, max_length
+            ),
+        ));
+    }
+
+    if let Some(quoted_post_id) = &post.quoted_post_id {
+        if quoted_post_id.trim().is_empty() {
+            return Err(invalid("quoted_post_id", "Quoted post ID cannot be empty"));
+        }
+    }
+
+    if post.mentions.len() > MAX_MENTIONS_PER_POST {
+        return Err(invalid(
+            "mentions",
+            format!("Posts can mention at most {} users", MAX_MENTIONS_PER_POST),
+        ));
+    }
+    if let Some(index) = post.mentions.iter().position(|m| m.trim().is_empty()) {
+        return Err(invalid(
+            &format!("mentions[{}]", index),
+            "Mentioned user ID cannot be empty",
+        ));
+    }
+    if post.visibility == PostVisibility::Mentioned && post.mentions.is_empty() {
+        return Err(invalid(
+            "mentions",
+            "Mentioned-only posts must mention at least one user",
+        ));
+    }
+
+    if let Some(publish_at) = &post.publish_at {
+        if post.draft {
+            return Err(invalid(
+                "publish_at",
+                "Drafts cannot have a publish time, publish the draft to schedule it",
+            ));
+        }
+        validate_publish_at(publish_at)?;
+    }
+
+    if post.poll.is_some() && !matches!(post.post_type, PostType::Poll) {
+        return Err(invalid("poll", "Only poll posts can include a poll"));
+    }
+
+    let media_count = post.media_urls.len();
+    match post.post_type {
+        PostType::Text => {
+            if media_count > 0 {
+                return Err(invalid("media_urls", "Text posts cannot include media"));
+            }
+        }
+        PostType::Poll => {
+            if media_count > 0 {
+                return Err(invalid("media_urls", "Poll posts cannot include media"));
+            }
+            return match &post.poll {
+                Some(poll) => validate_poll(poll),
+                None => Err(invalid("poll", "Poll posts require a poll")),
+            };
+        }
+        PostType::Image => {
+            if !(MIN_IMAGES_PER_POST..=MAX_IMAGES_PER_POST).contains(&media_count) {
+                return Err(invalid(
+                    "media_urls",
+                    format!(
+                        "Image posts require between {} and {} images, got {}",
+                        MIN_IMAGES_PER_POST, MAX_IMAGES_PER_POST, media_count
+                    ),
+                ));
+            }
+        }
+        PostType::Video => {
+            if media_count != 1 {
+                return Err(invalid(
+                    "media_urls",
+                    format!("Video posts require exactly one video, got {}", media_count),
+                ));
+            }
+        }
+        PostType::Link => {
+            if media_count != 1 {
+                return Err(invalid(
+                    "media_urls",
+                    format!("Link posts require exactly one URL, got {}", media_count),
+                ));
+            }
+            if !is_http_url(&post.media_urls[0]) {
+                return Err(invalid(
+                    "media_urls[0]",
+                    format!("{} is not a valid http(s) URL", post.media_urls[0]),
+                ));
+            }
+            return Ok(());
+        }
+    }
+
+    for (index, media) in post.media_urls.iter().enumerate() {
+        if !is_media_reference(media) {
+            return Err(invalid(
+                &format!("media_urls[{}]", index),
+                format!("{} is not a valid http(s) URL or media ID", media),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Parse an RFC 3339 timestamp into UTC
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn validate_poll(poll: &Poll) -> Result<(), AppError> {
+    let option_count = poll.options.len();
+    if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&option_count) {
+        return Err(invalid(
+            "poll.options",
+            format!(
+                "Polls require between {} and {} options, got {}",
+                MIN_POLL_OPTIONS, MAX_POLL_OPTIONS, option_count
+            ),
+        ));
+    }
+
+    for (index, option) in poll.options.iter().enumerate() {
+        let field = format!("poll.options[{}]", index);
+        if option.trim().is_empty() {
+            return Err(invalid(&field, "Poll option cannot be empty"));
+        }
+        if option.chars().count() > MAX_POLL_OPTION_LENGTH {
+            return Err(invalid(
+                &field,
+                format!(
+                    "Poll option cannot exceed {} characters",
+                    MAX_POLL_OPTION_LENGTH
+                ),
+            ));
+        }
+        if poll.options[..index]
+            .iter()
+            .any(|other| other.trim().eq_ignore_ascii_case(option.trim()))
+        {
+            return Err(invalid(&field, "Poll options must be unique"));
+        }
+    }
+
+    if let Some(closes_at) = &poll.closes_at {
+        match parse_timestamp(closes_at) {
+            Some(time) if time > Utc::now() => {}
+            Some(_) => {
+                return Err(invalid(
+                    "poll.closes_at",
+                    "Closing time must be in the future",
+                ));
+            }
+            None => {
+                return Err(invalid(
+                    "poll.closes_at",
+                    format!("{} is not a valid RFC 3339 timestamp", closes_at),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Resolve the bookmark collection name, falling back to the default collection
+pub fn bookmark_collection_name(collection: Option<&str>) -> Result<String, AppError> {
+    let name = match collection {
+        Some(name) => name.trim(),
+        None => return Ok(DEFAULT_BOOKMARK_COLLECTION.to_string()),
+    };
+
+    if name.is_empty() {
+        return Err(invalid("collection", "Collection name cannot be empty"));
+    }
+    if name.chars().count() > MAX_BOOKMARK_COLLECTION_LENGTH {
+        return Err(invalid(
+            "collection",
+            format!(
+                "Collection name cannot exceed {} characters",
+                MAX_BOOKMARK_COLLECTION_LENGTH
+            ),
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+// Seed counts need no checks of their own; generation caps pairs at what is possible
+pub fn validate_seed_options(options: &SeedOptions) -> Result<(), AppError> {
+    if !(1..=MAX_SEED_BATCH_SIZE).contains(&options.batch_size) {
+        return Err(invalid(
+            "batch_size",
+            format!("Batch size must be between 1 and {}", MAX_SEED_BATCH_SIZE),
+        ));
+    }
+    match options.anchor.as_deref() {
+        Some(anchor) if parse_timestamp(anchor).is_none() => Err(invalid(
+            "anchor",
+            format!("{} is not a valid RFC 3339 timestamp", anchor),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Scheduled publish times must be valid RFC 3339 timestamps in the future
+pub fn validate_publish_at(publish_at: &str) -> Result<DateTime<Utc>, AppError> {
+    match parse_timestamp(publish_at) {
+        Some(time) if time > Utc::now() => Ok(time),
+        Some(_) => Err(invalid("publish_at", "Publish time must be in the future")),
+        None => Err(invalid(
+            "publish_at",
+            format!("{} is not a valid RFC 3339 timestamp", publish_at),
+        )),
+    }
+}
+
+// Reports need a reason for moderators to act on; notes on resolutions are optional
+pub fn validate_report_reason(reason: &str) -> Result<String, AppError> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(invalid("reason", "Report reason cannot be empty"));
+    }
+    if reason.chars().count() > MAX_REPORT_REASON_LENGTH {
+        return Err(invalid(
+            "reason",
+            format!(
+                "Report reason cannot exceed {} characters",
+                MAX_REPORT_REASON_LENGTH
+            ),
+        ));
+    }
+    Ok(reason.to_string())
+}
+
+pub fn validate_moderation_note(note: Option<&str>) -> Result<Option<String>, AppError> {
+    match note.map(str::trim) {
+        Some(note) if note.chars().count() > MAX_MODERATION_NOTE_LENGTH => Err(invalid(
+            "note",
+            format!(
+                "Moderation note cannot exceed {} characters",
+                MAX_MODERATION_NOTE_LENGTH
+            ),
+        )),
+        Some(note) if !note.is_empty() => Ok(Some(note.to_string())),
+        _ => Ok(None),
+    }
+}
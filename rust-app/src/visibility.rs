@@ -0,0 +1,381 @@
+use crate::{
+    auth::Actor,
+    models::{PostStatus, PostVisibility, UserRole},
+    repository::ListOptions,
+    state::AppState,
+};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::Result,
+};
+
+// One string field of each document
+fn field_values(documents: &[Document], field: &str) -> Vec<String> {
+    documents
+        .iter()
+        .filter_map(|document| document.get_str(field).ok().map(str::to_string))
+        .collect()
+}
+
+pub async fn user_role(state: &AppState, user_id: &str) -> Result<UserRole> {
+    let user = state.users.find_by_id(user_id).await?;
+    Ok(user
+        .map(|user| UserRole::parse(user.get_str("role").unwrap_or_default()))
+        .unwrap_or_default())
+}
+
+// Who content is shown to: the user whose relationships decide what is visible, and
+// whether the caller may also see content removed by moderation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Viewer<'a> {
+    pub user_id: Option<&'a str>,
+    pub is_moderator: bool,
+}
+
+impl<'a> Viewer<'a> {
+    // A user acting without moderator rights, e.g. the author named in a request body
+    pub fn user(user_id: &'a str) -> Viewer<'a> {
+        Viewer {
+            user_id: Some(user_id),
+            is_moderator: false,
+        }
+    }
+
+    // The authenticated caller, with the rights of their role
+    pub fn actor(actor: &'a Actor) -> Viewer<'a> {
+        Viewer {
+            user_id: Some(&actor.user_id),
+            is_moderator: actor.role.can_moderate(),
+        }
+    }
+
+    // The caller of a route open to anonymous visitors, who see only public content
+    pub fn caller(caller: Option<&'a Actor>) -> Viewer<'a> {
+        caller.map(Viewer::actor).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AccountState {
+    Active,
+    Suspended,
+    // Soft-deleted accounts and accounts that no longer exist at all
+    Deleted,
+}
+
+pub async fn account_state(state: &AppState, user_id: &str) -> Result<AccountState> {
+    let user = state.users.find_by_id(user_id).await?;
+    Ok(match user {
+        Some(user) if user.contains_key("deleted_at") => AccountState::Deleted,
+        Some(user) if user.get_bool("suspended").unwrap_or(false) => AccountState::Suspended,
+        Some(_) => AccountState::Active,
+        None => AccountState::Deleted,
+    })
+}
+
+// Filter matching a user by ID unless they have been soft-deleted
+pub fn active_user_filter(user_id: &str) -> Document {
+    doc! { "_id": user_id, "deleted_at": { "$exists": false } }
+}
+
+// Everything about the viewer that decides what they may see, loaded once per request
+// so list pipelines can filter with plain `$in`/`$nin` matches. Suspended and deleted
+// accounts are left out through the author flags denormalized onto their content.
+pub struct ViewerContext {
+    pub viewer_id: Option<String>,
+    pub following: Vec<String>,
+    // Users the viewer blocked plus users who blocked the viewer
+    pub blocked: Vec<String>,
+    pub muted: Vec<String>,
+    pub is_moderator: bool,
+}
+
+impl ViewerContext {
+    pub async fn load(state: &AppState, viewer: Viewer<'_>) -> Result<ViewerContext> {
+        let is_moderator = viewer.is_moderator;
+        let viewer_id = match viewer.user_id {
+            Some(viewer_id) => viewer_id,
+            None => {
+                return Ok(ViewerContext {
+                    viewer_id: None,
+                    following: Vec::new(),
+                    blocked: Vec::new(),
+                    muted: Vec::new(),
+                    is_moderator,
+                })
+            }
+        };
+
+        let following = field_values(
+            &state
+                .follows
+                .list(doc! { "follower_id": viewer_id })
+                .await?,
+            "following_id",
+        );
+        let blocks = &state.blocks;
+        let mut blocked = field_values(
+            &blocks
+                .list_blocks(doc! { "blocker_id": viewer_id }, ListOptions::default())
+                .await?,
+            "blocked_id",
+        );
+        blocked.extend(field_values(
+            &blocks
+                .list_blocks(doc! { "blocked_id": viewer_id }, ListOptions::default())
+                .await?,
+            "blocker_id",
+        ));
+        let muted = field_values(
+            &blocks
+                .list_mutes(doc! { "muter_id": viewer_id }, ListOptions::default())
+                .await?,
+            "muted_id",
+        );
+
+        Ok(ViewerContext {
+            viewer_id: Some(viewer_id.to_string()),
+            following,
+            blocked,
+            muted,
+            is_moderator,
+        })
+    }
+
+    // Filter matching the published posts the viewer may see. `prefix` addresses the post
+    // fields when the post is embedded in another document, e.g. "post." after a $lookup.
+    pub fn posts_filter(&self, prefix: &str) -> Document {
+        self.build_posts_filter(prefix, &self.hidden_authors())
+    }
+
+    // Feeds additionally leave out muted users
+    pub fn feed_filter(&self, prefix: &str) -> Document {
+        self.build_posts_filter(prefix, &self.hidden_from_feed())
+    }
+
+    // Users whose content the viewer never sees because of a block in either direction
+    pub fn hidden_authors(&self) -> Vec<String> {
+        self.blocked.clone()
+    }
+
+    pub fn hidden_from_feed(&self) -> Vec<String> {
+        let mut hidden = self.hidden_authors();
+        hidden.extend(self.muted.iter().cloned());
+        hidden
+    }
+
+    // Filter for the accounts the viewer may see in user lists
+    pub fn users_filter(&self) -> Document {
+        let mut filter = doc! {
+            "_id": { "$nin": self.hidden_authors() },
+            "deleted_at": { "$exists": false },
+        };
+        if !self.is_moderator {
+            filter.insert("suspended", doc! { "$ne": true });
+        }
+        filter
+    }
+
+    // Whether an account loaded by ID belongs in a user list shown to the viewer
+    pub fn shows_account(&self, user: &Document) -> bool {
+        let user_id = user.get_str("_id").unwrap_or_default();
+        !user.contains_key("deleted_at")
+            && (self.is_moderator || !user.get_bool("suspended").unwrap_or(false))
+            && !self.blocked.iter().any(|id| id == user_id)
+    }
+
+    // Filter for comments the viewer may see, not including the visibility of their post
+    pub fn comments_filter(&self) -> Document {
+        let mut filter = doc! {
+            "user_id": { "$nin": self.hidden_authors() },
+            "deleted_at": { "$exists": false },
+        };
+        filter.extend(self.author_flags_filter(""));
+        if !self.is_moderator {
+            filter.insert("hidden", doc! { "$ne": true });
+        }
+        filter
+    }
+
+    // Leaves out content by deleted accounts, and by suspended accounts unless the viewer
+    // is a moderator
+    fn author_flags_filter(&self, prefix: &str) -> Document {
+        let mut filter = Document::new();
+        filter.insert(format!("{}author_deleted", prefix), doc! { "$ne": true });
+        if !self.is_moderator {
+            filter.insert(format!("{}author_suspended", prefix), doc! { "$ne": true });
+        }
+        filter
+    }
+
+    fn build_posts_filter(&self, prefix: &str, hidden_authors: &[String]) -> Document {
+        let field = |name: &str| format!("{}{}", prefix, name);
+
+        // Posts without a visibility predate the setting and are public
+        let mut public = Document::new();
+        public.insert(
+            field("visibility"),
+            doc! { "$nin": [PostVisibility::Followers.as_str(), PostVisibility::Mentioned.as_str()] },
+        );
+        public.insert(field("author_private"), doc! { "$ne": true });
+        let mut audience = vec![Bson::Document(public)];
+
+        if let Some(viewer_id) = &self.viewer_id {
+            let mut own = Document::new();
+            own.insert(field("user_id"), viewer_id);
+            audience.push(Bson::Document(own));
+
+            let mut followed = Document::new();
+            followed.insert(field("user_id"), doc! { "$in": &self.following });
+            followed.insert(
+                field("visibility"),
+                doc! { "$ne": PostVisibility::Mentioned.as_str() },
+            );
+            audience.push(Bson::Document(followed));
+
+            let mut mentioned = Document::new();
+            mentioned.insert(field("visibility"), PostVisibility::Mentioned.as_str());
+            mentioned.insert(field("mentions"), viewer_id);
+            audience.push(Bson::Document(mentioned));
+        }
+
+        let mut filter = Document::new();
+        filter.insert(
+            field("status"),
+            doc! { "$nin": [PostStatus::Draft.as_str(), PostStatus::Scheduled.as_str()] },
+        );
+        if !hidden_authors.is_empty() {
+            filter.insert(field("user_id"), doc! { "$nin": hidden_authors });
+        }
+        if !self.is_moderator {
+            filter.insert(field("hidden"), doc! { "$ne": true });
+        }
+        filter.extend(self.author_flags_filter(prefix));
+        filter.insert(field("deleted_at"), doc! { "$exists": false });
+        filter.insert("$or", audience);
+        filter
+    }
+}
+
+// Load the viewer's relationships and build the filter for top-level post queries
+pub async fn viewer_posts_filter(state: &AppState, viewer: Viewer<'_>) -> Result<Document> {
+    Ok(ViewerContext::load(state, viewer).await?.posts_filter(""))
+}
+
+// Check a single published post against the viewer without loading every relationship
+pub async fn can_view_post(state: &AppState, post: &Document, viewer: Viewer<'_>) -> Result<bool> {
+    if PostStatus::parse(post.get_str("status").unwrap_or_default()) != PostStatus::Published
+        || post.contains_key("deleted_at")
+    {
+        return Ok(false);
+    }
+
+    // Content removed by moderation stays visible to moderators only, authors included
+    let author_id = post.get_str("user_id").unwrap_or_default();
+    match account_state(state, author_id).await? {
+        AccountState::Deleted => return Ok(false),
+        AccountState::Suspended => return Ok(viewer.is_moderator),
+        AccountState::Active if post.get_bool("hidden").unwrap_or(false) => {
+            return Ok(viewer.is_moderator)
+        }
+        AccountState::Active => {}
+    }
+    let viewer_id = viewer.user_id;
+    if viewer_id == Some(author_id) {
+        return Ok(true);
+    }
+    if let Some(viewer_id) = viewer_id {
+        if state
+            .follows
+            .is_blocked_between(viewer_id, author_id)
+            .await?
+        {
+            return Ok(false);
+        }
+    }
+
+    let visibility = PostVisibility::parse(post.get_str("visibility").unwrap_or_default());
+    let author_private = post.get_bool("author_private").unwrap_or(false);
+
+    match (visibility, viewer_id) {
+        (PostVisibility::Mentioned, Some(viewer_id)) => Ok(post
+            .get_array("mentions")
+            .map(|mentions| mentions.iter().any(|m| m.as_str() == Some(viewer_id)))
+            .unwrap_or(false)),
+        (PostVisibility::Public, _) if !author_private => Ok(true),
+        (_, Some(viewer_id)) => state.follows.is_following(viewer_id, author_id).await,
+        (_, None) => Ok(false),
+    }
+}
+
+// A comment is visible with its post unless it or its author was deleted or the viewer
+// and the author block each other. Hidden comments and comments by suspended authors are
+// left to moderators.
+pub async fn can_view_comment(
+    state: &AppState,
+    comment: &Document,
+    viewer: Viewer<'_>,
+) -> Result<bool> {
+    let post_id = comment.get_str("post_id").unwrap_or_default();
+    let post_visible = match state.posts.find_by_id(post_id).await? {
+        Some(post) => can_view_post(state, &post, viewer).await?,
+        None => false,
+    };
+    if !post_visible || comment.contains_key("deleted_at") {
+        return Ok(false);
+    }
+
+    let author_id = comment.get_str("user_id").unwrap_or_default();
+    let author_state = account_state(state, author_id).await?;
+    if author_state == AccountState::Deleted {
+        return Ok(false);
+    }
+    if let Some(viewer_id) = viewer.user_id {
+        if state
+            .follows
+            .is_blocked_between(viewer_id, author_id)
+            .await?
+        {
+            return Ok(false);
+        }
+    }
+    if comment.get_bool("hidden").unwrap_or(false) || author_state == AccountState::Suspended {
+        return Ok(viewer.is_moderator);
+    }
+    Ok(true)
+}
+
+// Deleted accounts are hidden, suspended accounts are only visible to moderators, blocked
+// users cannot see each other, and private accounts only expose their connections to
+// themselves and their followers
+pub async fn can_view_account(
+    state: &AppState,
+    user: &Document,
+    viewer: Viewer<'_>,
+) -> Result<bool> {
+    let user_id = user.get_str("_id").unwrap_or_default();
+    if user.contains_key("deleted_at") {
+        return Ok(false);
+    }
+    if user.get_bool("suspended").unwrap_or(false) {
+        return Ok(viewer.is_moderator);
+    }
+    let viewer_id = viewer.user_id;
+    if let Some(viewer_id) = viewer_id {
+        if viewer_id == user_id {
+            return Ok(true);
+        }
+        if state.follows.is_blocked_between(viewer_id, user_id).await? {
+            return Ok(false);
+        }
+    }
+
+    if !user.get_bool("is_private").unwrap_or(false) {
+        return Ok(true);
+    }
+
+    match viewer_id {
+        Some(viewer_id) => state.follows.is_following(viewer_id, user_id).await,
+        None => Ok(false),
+    }
+}
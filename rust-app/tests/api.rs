@@ -0,0 +1,2599 @@
+use actix_http::Request;
+use actix_web::{
+    body::to_bytes,
+    dev::{Service, ServiceResponse},
+    http::{header::AUTHORIZATION, StatusCode},
+    test::{self, TestRequest},
+    web, App, Error,
+};
+use ddbp::{
+    audit::{AuditContext, AuditEvent, SYSTEM_ACTOR},
+    auth::{issue_token, USER_ID_HEADER},
+    metrics,
+    repository::{ListOptions, MemoryStore, MongoStore},
+    routes,
+    state::AppState,
+    validation::{
+        MAX_CAPTION_LENGTH, MAX_IMAGES_PER_POST, MAX_LINK_CONTENT_LENGTH, MAX_TEXT_CONTENT_LENGTH,
+    },
+};
+use mongodb::{
+    bson::{doc, Document},
+    options::{ClientOptions, ServerAddress},
+    Client,
+};
+use serde_json::{json, Value};
+use std::{
+    io::{Cursor, Read},
+    sync::Arc,
+    time::Duration,
+};
+use zip::ZipArchive;
+
+const AUTH_SECRET: &str = "a-secret-that-signs-caller-tokens";
+
+// Every repository lives in memory, so the API runs without a MongoDB server
+fn test_state() -> web::Data<AppState> {
+    web::Data::new(
+        AppState::new(
+            Arc::new(MemoryStore::default()),
+            Duration::from_secs(30 * 24 * 60 * 60),
+            Duration::from_secs(90 * 24 * 60 * 60),
+        )
+        .with_auth_secret(AUTH_SECRET.to_string()),
+    )
+}
+
+// Sends the request as `user_id`, with a token signed for them
+trait SignedIn {
+    fn signed_in_as(self, user_id: &str) -> Self;
+}
+
+impl SignedIn for TestRequest {
+    fn signed_in_as(self, user_id: &str) -> Self {
+        self.insert_header((USER_ID_HEADER, user_id))
+            .insert_header((
+                AUTHORIZATION,
+                format!("Bearer {}", issue_token(AUTH_SECRET, user_id)),
+            ))
+    }
+}
+
+// Every repository goes to a MongoDB address nobody listens on, so requests fail fast
+// with a database error
+fn unreachable_state() -> web::Data<AppState> {
+    let options = ClientOptions::builder()
+        .hosts(vec![ServerAddress::Tcp {
+            host: "127.0.0.1".to_string(),
+            port: Some(9),
+        }])
+        .server_selection_timeout(Duration::from_millis(100))
+        .connect_timeout(Duration::from_millis(100))
+        .build();
+    let db = Client::with_options(options)
+        .expect("client options are valid")
+        .database("social_media_test");
+    web::Data::new(AppState::new(
+        Arc::new(MongoStore::new(db)),
+        Duration::from_secs(30 * 24 * 60 * 60),
+        Duration::from_secs(90 * 24 * 60 * 60),
+    ))
+}
+
+async fn test_app(
+    state: &web::Data<AppState>,
+    dev_mode: bool,
+) -> impl Service<Request, Response = ServiceResponse, Error = Error> {
+    test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(move |cfg| routes::configure(cfg, dev_mode)),
+    )
+    .await
+}
+
+// Send a request and return the status with the JSON body, or null when there is none.
+// Guard middleware fails with an error rather than a response, which the server renders
+// the same way as a handler error.
+async fn send(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    request: TestRequest,
+) -> (StatusCode, Value) {
+    let response = match test::try_call_service(app, request.to_request()).await {
+        Ok(response) => response.into_parts().1,
+        Err(error) => error.error_response(),
+    };
+    let status = response.status();
+    let body = to_bytes(response.into_body()).await.unwrap_or_default();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn post_json(uri: &str, body: Value) -> TestRequest {
+    TestRequest::post().uri(uri).set_json(body)
+}
+
+fn assert_error(body: &Value, message: &str) {
+    assert_eq!(body["status"], "error", "{}", body);
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap_or_default()
+            .contains(message),
+        "expected {:?} in {}",
+        message,
+        body
+    );
+}
+
+fn assert_invalid_field(body: &Value, field: &str) {
+    assert_eq!(body["status"], "error", "{}", body);
+    assert_eq!(body["data"]["field"], field, "{}", body);
+}
+
+async fn create_user(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    username: &str,
+) -> String {
+    let (status, body) = send(
+        app,
+        post_json(
+            "/api/create_user",
+            json!({
+                "username": username,
+                "email": format!("{}@example.com", username),
+                "password_hash": "hash",
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["data"].as_str().expect("user ID").to_string()
+}
+
+async fn create_post(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    post: Value,
+) -> String {
+    let (status, body) = send(app, post_json("/api/create_post", post)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["data"].as_str().expect("post ID").to_string()
+}
+
+// Add a user straight to the store, for accounts the public API cannot create
+async fn insert_user(state: &web::Data<AppState>, user_id: &str, fields: Document) {
+    let mut user = doc! {
+        "_id": user_id,
+        "username": user_id,
+        "email": format!("{}@example.com", user_id),
+        "password_hash": "hash",
+        "role": "user",
+    };
+    user.extend(fields);
+    state.users.insert(user).await.expect("in-memory insert");
+}
+
+#[actix_web::test]
+async fn create_user_returns_the_new_id() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_user",
+            json!({ "username": "alice", "email": "alice@example.com", "password_hash": "hash" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["status"], "success");
+    let user_id = body["data"].as_str().expect("user ID");
+    assert!(body["message"].as_str().unwrap().contains(user_id));
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/{}", user_id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "success");
+    assert_eq!(body["data"]["_id"], user_id);
+    assert_eq!(body["data"]["username"], "alice");
+    // Other callers see neither the role nor the password hash
+    assert!(body["data"].get("role").is_none(), "{}", body);
+    assert!(body["data"].get("password_hash").is_none(), "{}", body);
+}
+
+#[actix_web::test]
+async fn create_user_rejects_an_empty_username() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_user",
+            json!({ "username": "", "email": "alice@example.com", "password_hash": "hash" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "Username cannot be empty");
+    assert!(body.get("data").is_none());
+}
+
+#[actix_web::test]
+async fn create_user_rejects_a_bad_email() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    for email in ["", "alice.example.com"] {
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/api/create_user",
+                json!({ "username": "alice", "email": email, "password_hash": "hash" }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", email);
+        assert_error(&body, "Invalid email format");
+    }
+}
+
+#[actix_web::test]
+async fn create_user_rejects_an_empty_password_hash() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_user",
+            json!({ "username": "alice", "email": "alice@example.com", "password_hash": "" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "Password hash cannot be empty");
+}
+
+#[actix_web::test]
+async fn create_user_rejects_a_duplicate_email() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    create_user(&app, "alice").await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_user",
+            json!({ "username": "alice2", "email": "alice@example.com", "password_hash": "hash" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "User with email alice@example.com already exists");
+}
+
+#[actix_web::test]
+async fn create_user_rejects_a_malformed_body() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, _) = send(
+        &app,
+        post_json("/api/create_user", json!({ "username": "alice" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn get_user_returns_not_found_for_an_unknown_id() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(&app, TestRequest::get().uri("/api/users/ghost")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "User with ID ghost not found");
+}
+
+#[actix_web::test]
+async fn create_post_requires_an_existing_user() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_post",
+            json!({ "user_id": "ghost", "content": "Hello" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "User with ID ghost not found");
+}
+
+#[actix_web::test]
+async fn restricted_posts_are_shown_to_the_authenticated_caller_only() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let eve = create_user(&app, "eve").await;
+    let (status, _) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let post_id = create_post(
+        &app,
+        json!({ "user_id": &alice, "content": "Friends only", "visibility": "followers" }),
+    )
+    .await;
+
+    let uris = [
+        format!("/api/posts/{}", post_id),
+        format!("/api/comments/post/{}", post_id),
+    ];
+    for uri in &uris {
+        // Naming a follower in the query string no longer shows the post
+        let (status, _) = send(
+            &app,
+            TestRequest::get().uri(&format!("{}?viewer_id={}", uri, bob)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+        let (status, _) = send(&app, TestRequest::get().uri(uri).signed_in_as(&eve)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+        let (status, body) = send(&app, TestRequest::get().uri(uri).signed_in_as(&bob)).await;
+        assert_eq!(status, StatusCode::OK, "{} {}", uri, body);
+    }
+
+    let by_user = format!("/api/users/posts/{}", alice);
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("{}?viewer_id={}", by_user, bob)),
+    )
+    .await;
+    assert_eq!(body["data"], json!([]));
+    let (_, body) = send(&app, TestRequest::get().uri(&by_user).signed_in_as(&bob)).await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(1), "{}", body);
+
+    // A forged caller is rejected rather than treated as anonymous
+    let (status, _) = send(
+        &app,
+        TestRequest::get()
+            .uri(&uris[0])
+            .insert_header(("X-User-Id", bob.as_str())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn timelines_are_for_their_owner() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let timeline = format!("/api/users/timeline/{}", alice);
+
+    let (status, _) = send(&app, TestRequest::get().uri(&timeline)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(&app, TestRequest::get().uri(&timeline).signed_in_as(&bob)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(
+        &body,
+        &format!("User {} cannot change content owned by {}", bob, alice),
+    );
+
+    for caller in [alice.as_str(), "admin"] {
+        let (status, body) =
+            send(&app, TestRequest::get().uri(&timeline).signed_in_as(caller)).await;
+        assert_eq!(status, StatusCode::OK, "{} {}", caller, body);
+    }
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri("/api/users/timeline/ghost")
+            .signed_in_as("admin"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "User with ID ghost not found");
+}
+
+#[actix_web::test]
+async fn create_post_validates_its_fields() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let cases = [
+        (json!({ "user_id": "", "content": "Hello" }), "user_id"),
+        (json!({ "user_id": &alice, "content": "  " }), "content"),
+        (
+            json!({ "user_id": &alice, "content": "Hello", "visibility": "mentioned" }),
+            "mentions",
+        ),
+        (
+            json!({ "user_id": &alice, "content": "Hello", "publish_at": "tomorrow" }),
+            "publish_at",
+        ),
+        (
+            json!({ "user_id": &alice, "content": "Hello", "poll": { "options": ["a", "b"] } }),
+            "poll",
+        ),
+    ];
+    for (post, field) in cases {
+        let (status, body) = send(&app, post_json("/api/create_post", post)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", field);
+        assert_invalid_field(&body, field);
+    }
+}
+
+#[actix_web::test]
+async fn each_post_type_has_its_own_content_and_media_rules() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let post = |post_type: &str, content: String, media_urls: Value| {
+        json!({
+            "user_id": &alice,
+            "post_type": post_type,
+            "content": content,
+            "media_urls": media_urls,
+        })
+    };
+    let image = "https://cdn.example.com/cat.jpg";
+    let media_id = "8c7f2a58-2c3e-4d55-9a44-0d6e5f7b9a10";
+
+    let cases = [
+        (post("text", "Hi".into(), json!([image])), "media_urls"),
+        (
+            post("text", "a".repeat(MAX_TEXT_CONTENT_LENGTH + 1), json!([])),
+            "content",
+        ),
+        (post("image", "Cat".into(), json!([])), "media_urls"),
+        (
+            post(
+                "image",
+                "Cats".into(),
+                json!(vec![image; MAX_IMAGES_PER_POST + 1]),
+            ),
+            "media_urls",
+        ),
+        (
+            post("image", "Cat".into(), json!(["cat.jpg"])),
+            "media_urls[0]",
+        ),
+        (
+            post("image", "a".repeat(MAX_CAPTION_LENGTH + 1), json!([image])),
+            "content",
+        ),
+        (
+            post("video", "Clip".into(), json!([media_id, media_id])),
+            "media_urls",
+        ),
+        (post("link", "Read".into(), json!([])), "media_urls"),
+        (
+            post("link", "Read".into(), json!(["ftp://example.com/file"])),
+            "media_urls[0]",
+        ),
+        (
+            post(
+                "link",
+                "a".repeat(MAX_LINK_CONTENT_LENGTH + 1),
+                json!(["https://example.com"]),
+            ),
+            "content",
+        ),
+        (post("poll", "Which?".into(), json!([])), "poll"),
+    ];
+    for (post, field) in cases {
+        let (status, body) = send(&app, post_json("/api/create_post", post.clone())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", post);
+        assert_invalid_field(&body, field);
+    }
+
+    // Media may be hosted URLs or the IDs of uploaded media
+    for valid in [
+        post("text", "a".repeat(MAX_TEXT_CONTENT_LENGTH), json!([])),
+        post("image", "Cats".into(), json!([image, media_id])),
+        post("video", "Clip".into(), json!([media_id])),
+        post(
+            "link",
+            "Read".into(),
+            json!(["https://example.com/article"]),
+        ),
+    ] {
+        create_post(&app, valid).await;
+    }
+}
+
+#[actix_web::test]
+async fn create_post_rejects_unknown_mentions() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_post",
+            json!({ "user_id": alice, "content": "Hi", "mentions": ["ghost"] }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "One or more mentioned users were not found");
+}
+
+#[actix_web::test]
+async fn suspended_users_cannot_post() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "suspended", doc! { "suspended": true }).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_post",
+            json!({ "user_id": "suspended", "content": "Hello" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "User suspended is suspended");
+}
+
+#[actix_web::test]
+async fn only_moderating_callers_see_suspended_users() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "moderator", doc! { "role": "moderator" }).await;
+    insert_user(&state, "spammer", doc! { "suspended": true }).await;
+
+    // Naming a moderator in the query string grants nothing
+    let (status, _) = send(
+        &app,
+        TestRequest::get().uri("/api/users/spammer?viewer_id=moderator"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri("/api/users/spammer")
+            .signed_in_as("moderator"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["_id"], "spammer");
+}
+
+#[actix_web::test]
+async fn suspended_and_deleted_authors_drop_out_of_lists() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "moderator", doc! { "role": "moderator" }).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    create_post(&app, json!({ "user_id": &alice, "content": "Spam" })).await;
+    create_post(&app, json!({ "user_id": &bob, "content": "Hello" })).await;
+
+    let list_authors = |caller: Option<&'static str>| {
+        let app = &app;
+        async move {
+            let mut request = TestRequest::get().uri("/api/posts");
+            if let Some(caller) = caller {
+                request = request.signed_in_as(caller);
+            }
+            let (status, body) = send(app, request).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            let mut authors: Vec<String> = body["data"]
+                .as_array()
+                .expect("post list")
+                .iter()
+                .map(|post| post["user_id"].as_str().unwrap_or_default().to_string())
+                .collect();
+            authors.sort();
+            authors
+        }
+    };
+
+    // Suspending alice through a report hides her posts from everyone but moderators
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/reports",
+            json!({ "target_type": "user", "target_id": &alice, "reason": "Spam" }),
+        )
+        .signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let report_uri = format!("/api/moderation/reports/{}", body["data"].as_str().unwrap());
+    for (uri, body) in [
+        (format!("{}/claim", report_uri), json!({})),
+        (
+            format!("{}/resolve", report_uri),
+            json!({ "action": "suspend_user" }),
+        ),
+    ] {
+        let (status, body) = send(&app, post_json(&uri, body).signed_in_as("moderator")).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+    }
+    assert_eq!(list_authors(None).await, vec![bob.clone()]);
+    let mut everyone = vec![alice.clone(), bob.clone()];
+    everyone.sort();
+    assert_eq!(list_authors(Some("moderator")).await, everyone);
+
+    // Deleted accounts are hidden from moderators too, until they are restored
+    let (status, body) = send(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/api/users/{}", bob))
+            .signed_in_as(bob.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(list_authors(Some("moderator")).await, vec![alice.clone()]);
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/users/{}/restore", bob))
+            .signed_in_as(bob.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(list_authors(None).await, vec![bob]);
+}
+
+#[actix_web::test]
+async fn reporters_can_only_report_what_they_can_see() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let carol = create_user(&app, "carol").await;
+    insert_user(
+        &state,
+        "gone",
+        doc! { "deleted_at": "2026-01-01T00:00:00+00:00" },
+    )
+    .await;
+    let (status, _) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let post_id = create_post(
+        &app,
+        json!({ "user_id": &alice, "content": "Friends only", "visibility": "followers" }),
+    )
+    .await;
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": &post_id, "user_id": &alice, "content": "Still friends only" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let comments = state
+        .comments
+        .list(doc! { "post_id": &post_id })
+        .await
+        .unwrap();
+    let comment_id = comments[0].get_str("_id").unwrap().to_string();
+    let report = |target_type: &str, target_id: &str| {
+        post_json(
+            "/api/reports",
+            json!({ "target_type": target_type, "target_id": target_id, "reason": "Spam" }),
+        )
+    };
+
+    let (status, _) = send(&app, report("comment", &comment_id)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The comment is only visible through a post carol cannot see
+    let (status, body) = send(&app, report("comment", &comment_id).signed_in_as(&carol)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, &format!("comment with ID {} not found", comment_id));
+    let (status, body) = send(&app, report("user", "gone").signed_in_as(&carol)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "user with ID gone not found");
+
+    // The reporter is the caller, whoever the body names
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/reports",
+            json!({
+                "reporter_id": &carol,
+                "target_type": "comment",
+                "target_id": &comment_id,
+                "reason": "Spam",
+            }),
+        )
+        .signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let stored = state
+        .reports
+        .find_one(doc! { "_id": body["data"].as_str().expect("report ID") })
+        .await
+        .unwrap()
+        .expect("report");
+    assert_eq!(stored.get_str("reporter_id").unwrap(), bob);
+    assert_eq!(stored.get_str("target_user_id").unwrap(), alice);
+}
+
+#[actix_web::test]
+async fn published_posts_can_be_fetched() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let post_id = create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", post_id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "success");
+    assert_eq!(body["data"]["id"], post_id);
+    assert_eq!(body["data"]["username"], "alice");
+    assert_eq!(body["data"]["content"], "Hello");
+    assert_eq!(body["data"]["status"], "published");
+    assert_eq!(body["data"]["comment_count"], 0);
+
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/{}", alice)),
+    )
+    .await;
+    assert_eq!(body["data"]["post_count"], 1);
+}
+
+#[actix_web::test]
+async fn drafts_are_hidden_and_not_counted() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let post_id = create_post(
+        &app,
+        json!({ "user_id": &alice, "content": "Later", "draft": true }),
+    )
+    .await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", post_id)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "not found");
+
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/{}", alice)),
+    )
+    .await;
+    assert!(body["data"]["post_count"].is_null());
+}
+
+#[actix_web::test]
+async fn quoting_a_post_counts_the_quote() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let original = create_post(&app, json!({ "user_id": &alice, "content": "Original" })).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_post",
+            json!({ "user_id": &bob, "content": "Quote", "quoted_post_id": "ghost" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "Quoted post with ID ghost not found");
+
+    let quote = create_post(
+        &app,
+        json!({ "user_id": &bob, "content": "Quote", "quoted_post_id": &original }),
+    )
+    .await;
+
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", original)),
+    )
+    .await;
+    assert_eq!(body["data"]["quote_count"], 1);
+
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", quote)),
+    )
+    .await;
+    assert_eq!(body["data"]["quoted_post"]["id"], original);
+    assert_eq!(body["data"]["quoted_post"]["available"], true);
+    assert_eq!(body["data"]["quoted_post"]["username"], "alice");
+}
+
+#[actix_web::test]
+async fn create_comment_validates_its_fields() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let post_id = create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": &post_id, "user_id": &alice, "content": "" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "All fields are required");
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": &post_id, "user_id": "ghost", "content": "Hi" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "User with ID ghost not found");
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": "ghost", "user_id": &alice, "content": "Hi" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "Post with ID ghost not found");
+}
+
+#[actix_web::test]
+async fn comments_count_towards_their_post() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let post_id = create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+
+    // The comment notification cannot be stored without MongoDB, which must not fail the comment
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": &post_id, "user_id": &bob, "content": "Hi" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["status"], "success");
+    assert!(body.get("data").is_none());
+
+    let (_, body) = send(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", post_id)),
+    )
+    .await;
+    assert_eq!(body["data"]["comment_count"], 1);
+}
+
+#[actix_web::test]
+async fn get_comment_returns_not_found_for_an_unknown_id() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+
+    let (status, body) = send(&app, TestRequest::get().uri("/api/comments/ghost")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(&body, "Comment with ID ghost not found");
+}
+
+#[actix_web::test]
+async fn follow_user_checks_both_users() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let cases = [
+        (
+            json!({ "follower_id": "", "following_id": &alice }),
+            StatusCode::BAD_REQUEST,
+            "Follower and Following IDs are required".to_string(),
+        ),
+        (
+            json!({ "follower_id": "ghost", "following_id": &alice }),
+            StatusCode::NOT_FOUND,
+            "User with ID ghost not found".to_string(),
+        ),
+        (
+            json!({ "follower_id": &alice, "following_id": "ghost" }),
+            StatusCode::NOT_FOUND,
+            "User with ID ghost not found".to_string(),
+        ),
+    ];
+    for (follow, expected_status, message) in cases {
+        let (status, body) = send(&app, post_json("/api/follow_user", follow)).await;
+        assert_eq!(status, expected_status, "{}", message);
+        assert_error(&body, &message);
+    }
+}
+
+#[actix_web::test]
+async fn follow_counters_track_follows_and_blocks() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let carol = create_user(&app, "carol").await;
+
+    let counters = |user_id: String| {
+        let app = &app;
+        async move {
+            let (status, body) = send(
+                app,
+                TestRequest::get().uri(&format!("/api/users/{}", user_id)),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            let count = |field: &str| body["data"][field].as_i64().unwrap_or(0);
+            (count("follower_count"), count("following_count"))
+        }
+    };
+
+    // A direct follow and an approved request both count
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &alice, "following_id": &bob }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = send(
+        &app,
+        TestRequest::put()
+            .uri(&format!("/api/users/{}/privacy", carol))
+            .signed_in_as(carol.as_str())
+            .set_json(json!({ "is_private": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &alice, "following_id": &carol }),
+        ),
+    )
+    .await;
+    assert!(status.is_success(), "{}", body);
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!(
+                "/api/users/{}/follow_requests/{}/approve",
+                carol, alice
+            ))
+            .signed_in_as(carol.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(counters(alice.clone()).await, (0, 2));
+    assert_eq!(counters(bob.clone()).await, (1, 0));
+    assert_eq!(counters(carol.clone()).await, (1, 0));
+
+    // Blocking cuts the follow and takes it off both counters
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/users/{}/block", alice))
+            .signed_in_as(bob.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(counters(alice).await, (0, 1));
+    assert_eq!(counters(bob).await, (0, 0));
+}
+
+#[actix_web::test]
+async fn poll_votes_are_cast_by_the_caller() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    insert_user(&state, "suspended", doc! { "suspended": true }).await;
+    let poll_id = create_post(
+        &app,
+        json!({
+            "user_id": &alice,
+            "content": "Tea or coffee?",
+            "post_type": "poll",
+            "poll": { "options": ["Tea", "Coffee"] },
+        }),
+    )
+    .await;
+    let vote_uri = format!("/api/posts/{}/vote", poll_id);
+    let vote =
+        |option_indexes: Value| post_json(&vote_uri, json!({ "option_indexes": option_indexes }));
+
+    let (status, _) = send(&app, vote(json!([0]))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(&app, vote(json!([0])).signed_in_as("suspended")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "User suspended is suspended");
+
+    let (status, body) = send(&app, vote(json!([0, 1])).signed_in_as(&bob)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_invalid_field(&body, "option_indexes");
+    assert_error(&body, "This poll only allows a single choice");
+
+    // A user ID in the body is ignored, the vote belongs to the caller
+    let (status, body) = send(
+        &app,
+        post_json(
+            &vote_uri,
+            json!({ "user_id": &alice, "option_indexes": [1] }),
+        )
+        .signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = send(&app, vote(json!([0])).signed_in_as(&bob)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_error(
+        &body,
+        &format!("User {} has already voted on poll {}", bob, poll_id),
+    );
+    let (status, _) = send(&app, vote(json!([0])).signed_in_as(&alice)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let body = succeed(
+        &app,
+        TestRequest::get()
+            .uri(&format!("/api/posts/{}", poll_id))
+            .signed_in_as(&bob),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"]["poll"]["total_votes"], 2);
+    assert_eq!(body["data"]["poll"]["options"][1]["vote_count"], 1);
+    assert_eq!(body["data"]["poll"]["has_voted"], true, "{}", body);
+    assert_eq!(body["data"]["poll"]["options"][1]["voted"], true);
+
+    // Polls that closed take no more votes
+    state
+        .posts
+        .insert_published(doc! {
+            "_id": "closed-poll",
+            "user_id": &alice,
+            "content": "Too late",
+            "post_type": "poll",
+            "status": "published",
+            "visibility": "public",
+            "created_at": "2020-01-01T00:00:00+00:00",
+            "poll": {
+                "options": [{ "text": "Yes", "vote_count": 0 }, { "text": "No", "vote_count": 0 }],
+                "multiple_choice": false,
+                "closes_at": "2020-01-02T00:00:00+00:00",
+                "total_votes": 0,
+            },
+        })
+        .await
+        .unwrap();
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/posts/closed-poll/vote",
+            json!({ "option_indexes": [0] }),
+        )
+        .signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "Poll on post closed-poll is closed");
+}
+
+#[actix_web::test]
+async fn reposts_are_made_and_undone_by_the_caller() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    insert_user(&state, "suspended", doc! { "suspended": true }).await;
+    let post_id = create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+    let repost_uri = format!("/api/posts/{}/repost", post_id);
+
+    let (status, _) = send(&app, TestRequest::post().uri(&repost_uri)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&repost_uri)
+            .signed_in_as("suspended"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_error(&body, "User suspended is suspended");
+
+    // A user ID in the body is ignored, the repost belongs to the caller
+    let (status, body) = send(
+        &app,
+        post_json(&repost_uri, json!({ "user_id": &alice })).signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    for (caller, reposted) in [(&bob, true), (&alice, false)] {
+        let body = succeed(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/api/posts/{}", post_id))
+                .signed_in_as(caller),
+            StatusCode::OK,
+        )
+        .await;
+        assert_eq!(body["data"]["has_reposted"], reposted, "{}", caller);
+        assert_eq!(body["data"]["repost_count"], 1);
+    }
+
+    let (status, body) = send(
+        &app,
+        TestRequest::delete().uri(&repost_uri).signed_in_as(&alice),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_error(
+        &body,
+        &format!("User {} has not reposted post {}", alice, post_id),
+    );
+    let (status, _) = send(
+        &app,
+        TestRequest::delete().uri(&repost_uri).signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn timeline_pages_skip_reposts_the_user_cannot_see() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let carol = create_user(&app, "carol").await;
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &alice, "following_id": &bob }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let mut own_posts = Vec::new();
+    for content in ["First", "Second"] {
+        own_posts.push(create_post(&app, json!({ "user_id": &alice, "content": content })).await);
+    }
+
+    // Bob's repost of a muted user's post is the newest activity but must not use up a slot
+    let carol_post = create_post(&app, json!({ "user_id": &carol, "content": "Muted" })).await;
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/posts/{}/repost", carol_post))
+            .signed_in_as(&bob),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/users/{}/mute", carol))
+            .signed_in_as(alice.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri(&format!("/api/users/timeline/{}?page=1&limit=2", alice))
+            .signed_in_as(alice.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let mut ids: Vec<&str> = body["data"]
+        .as_array()
+        .expect("timeline")
+        .iter()
+        .filter_map(|item| item["post"]["id"].as_str())
+        .collect();
+    ids.sort();
+    own_posts.sort();
+    assert_eq!(ids, own_posts);
+}
+
+#[actix_web::test]
+async fn followers_only_posts_need_a_follow() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let post_id = create_post(
+        &app,
+        json!({ "user_id": &alice, "content": "Friends only", "visibility": "followers" }),
+    )
+    .await;
+    let comment = json!({ "post_id": &post_id, "user_id": &bob, "content": "Hi" });
+
+    let (status, _) = send(&app, post_json("/api/create_comment", comment.clone())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["message"], "Follow relationship created successfully");
+
+    let (status, _) = send(&app, post_json("/api/create_comment", comment)).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[actix_web::test]
+async fn guarded_scopes_check_the_caller() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let (status, body) = send(&app, TestRequest::get().uri("/api/admin/audit")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "Missing X-User-Id header");
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri("/api/admin/audit")
+            .signed_in_as("ghost"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "Unknown user ghost");
+
+    for uri in ["/api/admin/audit", "/api/moderation/reports"] {
+        let (status, body) = send(
+            &app,
+            TestRequest::get().uri(uri).signed_in_as(alice.as_str()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_error(&body, "is not allowed to access");
+    }
+}
+
+#[actix_web::test]
+async fn staff_requests_are_validated() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    insert_user(&state, "moderator", doc! { "role": "moderator" }).await;
+
+    let (status, body) = send(
+        &app,
+        TestRequest::put()
+            .uri("/api/admin/users/admin/role")
+            .signed_in_as("admin")
+            .set_json(json!({ "role": "user" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "Admins cannot change their own role");
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri("/api/moderation/reports/some-report/resolve")
+            .signed_in_as("moderator")
+            .set_json(json!({ "action": "dismiss", "note": "x".repeat(1001) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_invalid_field(&body, "note");
+}
+
+#[actix_web::test]
+async fn callers_prove_who_they_are_with_a_token() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    let audit = "/api/admin/audit";
+
+    // X-User-Id alone no longer names the caller
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri(audit)
+            .insert_header(("X-User-Id", "admin")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "Missing bearer token for user admin");
+
+    // Neither does a token signed for someone else or with another secret
+    for token in [
+        issue_token(AUTH_SECRET, "alice"),
+        issue_token("another-secret-that-signs-tokens!", "admin"),
+        "not hex".to_string(),
+    ] {
+        let (status, body) = send(
+            &app,
+            TestRequest::get()
+                .uri(audit)
+                .insert_header(("X-User-Id", "admin"))
+                .insert_header(("Authorization", format!("Bearer {}", token))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", token);
+        assert_error(&body, "Invalid token for user admin");
+    }
+
+    // The token endpoint checks the password hash
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/auth/token",
+            json!({ "email": "admin@example.com", "password_hash": "wrong" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "Invalid email or password");
+    let (status, _) = send(
+        &app,
+        post_json(
+            "/api/auth/token",
+            json!({ "email": "nobody@example.com", "password_hash": "hash" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let body = succeed(
+        &app,
+        post_json(
+            "/api/auth/token",
+            json!({ "email": "admin@example.com", "password_hash": "hash" }),
+        ),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"]["user_id"], "admin");
+    let token = body["data"]["token"].as_str().expect("token");
+    let (status, _) = send(
+        &app,
+        TestRequest::get()
+            .uri(audit)
+            .insert_header(("X-User-Id", "admin"))
+            .insert_header(("Authorization", format!("Bearer {}", token))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn bookmarks_are_kept_per_collection() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let first = create_post(&app, json!({ "user_id": &bob, "content": "First" })).await;
+    let second = create_post(&app, json!({ "user_id": &bob, "content": "Second" })).await;
+    let followers_only = create_post(
+        &app,
+        json!({ "user_id": &bob, "content": "Friends", "visibility": "followers" }),
+    )
+    .await;
+    let bookmarks = format!("/api/users/{}/bookmarks", alice);
+    let save = |body: Value| post_json(&bookmarks, body).signed_in_as(&alice);
+
+    // Without a collection name a post goes to the default collection
+    for body in [
+        json!({ "post_id": &first }),
+        json!({ "post_id": &first, "collection": " Reading " }),
+        json!({ "post_id": &second, "collection": "Reading" }),
+    ] {
+        succeed(&app, save(body), StatusCode::CREATED).await;
+    }
+    let (status, body) = send(
+        &app,
+        save(json!({ "post_id": &first, "collection": "Reading" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_error(&body, "already saved in Reading");
+
+    // Posts the user cannot see cannot be saved, and collection names are checked
+    let (status, _) = send(&app, save(json!({ "post_id": &followers_only }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = send(
+        &app,
+        save(json!({ "post_id": &first, "collection": "x".repeat(51) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_invalid_field(&body, "collection");
+
+    let body = succeed(
+        &app,
+        TestRequest::get()
+            .uri(&format!("{}/collections", bookmarks))
+            .signed_in_as(&alice),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(
+        body["data"],
+        json!([{ "name": "Reading", "count": 2 }, { "name": "Saved", "count": 1 }])
+    );
+    let body = succeed(
+        &app,
+        TestRequest::get()
+            .uri(&format!("{}?collection=Reading", bookmarks))
+            .signed_in_as(&alice),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(2));
+
+    // Removing without a collection takes the post out of every collection
+    let remove = |post_id: &str| {
+        TestRequest::delete()
+            .uri(&bookmarks)
+            .set_json(json!({ "post_id": post_id }))
+            .signed_in_as(&alice)
+    };
+    succeed(&app, remove(&first), StatusCode::OK).await;
+    let (status, _) = send(&app, remove(&first)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Deleted posts drop out of the list
+    succeed(
+        &app,
+        TestRequest::delete()
+            .uri(&format!("/api/posts/{}", second))
+            .signed_in_as(&bob),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&bookmarks).signed_in_as(&alice),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"], json!([]));
+}
+
+#[actix_web::test]
+async fn owner_routes_check_the_caller() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+
+    let draft_id = create_post(
+        &app,
+        json!({ "user_id": &bob, "content": "Later", "draft": true }),
+    )
+    .await;
+
+    let (status, body) = send(&app, TestRequest::delete().uri("/api/posts/some-post")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_error(&body, "Missing X-User-Id header");
+
+    let requests = [
+        TestRequest::delete().uri(&format!("/api/users/{}", bob)),
+        TestRequest::post().uri(&format!("/api/users/{}/export", bob)),
+        TestRequest::get().uri(&format!("/api/users/{}/export/some-export", bob)),
+        TestRequest::get().uri(&format!("/api/users/{}/bookmarks", bob)),
+        post_json(
+            &format!("/api/users/{}/bookmarks", bob),
+            json!({ "post_id": "some-post" }),
+        ),
+        TestRequest::delete()
+            .uri(&format!("/api/users/{}/bookmarks", bob))
+            .set_json(json!({ "post_id": "some-post" })),
+        TestRequest::get().uri(&format!("/api/users/{}/bookmarks/collections", bob)),
+        TestRequest::get().uri(&format!("/api/users/{}/drafts", bob)),
+        TestRequest::get().uri(&format!("/api/users/{}/notifications", bob)),
+        TestRequest::post().uri(&format!("/api/posts/{}/publish", draft_id)),
+        TestRequest::get().uri(&format!("/api/users/{}/follow_requests", bob)),
+        TestRequest::post().uri(&format!(
+            "/api/users/{}/follow_requests/{}/approve",
+            bob, alice
+        )),
+        TestRequest::post().uri(&format!(
+            "/api/users/{}/follow_requests/{}/reject",
+            bob, alice
+        )),
+        TestRequest::put()
+            .uri(&format!("/api/users/{}/privacy", bob))
+            .set_json(json!({ "is_private": true })),
+        TestRequest::get().uri(&format!("/api/users/{}/blocks", bob)),
+        TestRequest::get().uri(&format!("/api/users/{}/mutes", bob)),
+    ];
+    for request in requests {
+        let (status, body) = send(&app, request.signed_in_as(alice.as_str())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_error(&body, "cannot change content owned by");
+    }
+
+    // Who a user blocks or mutes is nobody else's business
+    for path in ["blocks", "mutes"] {
+        let (status, _) = send(
+            &app,
+            TestRequest::get().uri(&format!("/api/users/{}/{}", bob, path)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", path);
+    }
+}
+
+#[actix_web::test]
+async fn validation_errors_name_the_field() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let requests = [
+        (
+            post_json(
+                &format!("/api/users/{}/bookmarks", alice),
+                json!({ "post_id": "some-post", "collection": " " }),
+            ),
+            "collection",
+        ),
+        (
+            post_json(
+                &format!("/api/users/{}/bookmarks", alice),
+                json!({ "post_id": "" }),
+            ),
+            "post_id",
+        ),
+        (
+            post_json(
+                "/api/reports",
+                json!({ "target_type": "post", "target_id": "some-post", "reason": "" }),
+            ),
+            "reason",
+        ),
+    ];
+    for (request, field) in requests {
+        let (status, body) = send(&app, request.signed_in_as(alice.as_str())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", field);
+        assert_invalid_field(&body, field);
+    }
+}
+
+#[actix_web::test]
+async fn actions_on_unknown_users_and_posts_are_not_found() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let requests = [
+        (
+            post_json(
+                "/api/posts/some-post/vote",
+                json!({ "option_indexes": [0] }),
+            ),
+            "Post with ID some-post not found",
+        ),
+        (
+            TestRequest::post().uri("/api/posts/some-post/repost"),
+            "Post with ID some-post not found",
+        ),
+        (
+            post_json(
+                &format!("/api/users/{}/bookmarks", alice),
+                json!({ "post_id": "some-post" }),
+            ),
+            "Post with ID some-post not found",
+        ),
+        (
+            TestRequest::post().uri("/api/users/ghost/block"),
+            "User with ID ghost not found",
+        ),
+        (
+            TestRequest::get().uri("/api/users/posts/ghost"),
+            "User with ID ghost not found",
+        ),
+        (
+            TestRequest::get().uri("/api/comments/post/ghost"),
+            "Post with ID ghost not found",
+        ),
+    ];
+    for (request, message) in requests {
+        let (status, body) = send(&app, request.signed_in_as(alice.as_str())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", message);
+        assert_error(&body, message);
+    }
+
+    let (status, body) = send(
+        &app,
+        TestRequest::post()
+            .uri(&format!("/api/users/{}/mute", alice))
+            .signed_in_as(alice.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_error(&body, "Users cannot block or mute themselves");
+}
+
+#[actix_web::test]
+async fn audit_entries_name_the_resolved_actor() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    let alice = create_user(&app, "alice").await;
+
+    // An X-User-Id header the handler did not act on does not become the actor
+    let (status, body) = send(
+        &app,
+        post_json(
+            "/api/create_post",
+            json!({ "user_id": &alice, "content": "Hello" }),
+        )
+        .signed_in_as("admin"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let post_id = body["data"].as_str().expect("post ID").to_string();
+
+    // An admin managing someone else's bookmarks is the actor, not the owner
+    let bookmarks = format!("/api/users/{}/bookmarks", alice);
+    let (status, body) = send(
+        &app,
+        post_json(&bookmarks, json!({ "post_id": &post_id })).signed_in_as("admin"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = send(
+        &app,
+        TestRequest::delete()
+            .uri(&bookmarks)
+            .set_json(json!({ "post_id": &post_id }))
+            .signed_in_as("admin"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Background jobs and commands record their changes as the system
+    AuditContext::system(Duration::from_secs(60))
+        .record(
+            state.audit_log.as_ref(),
+            AuditEvent::new("purge_user", "user", "gone"),
+        )
+        .await;
+
+    for (action, actor) in [
+        ("create_post", alice.as_str()),
+        ("add_bookmark", "admin"),
+        ("remove_bookmark", "admin"),
+        ("purge_user", SYSTEM_ACTOR),
+    ] {
+        let (status, body) = send(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/api/admin/audit?action={}", action))
+                .signed_in_as("admin"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"][0]["actor_id"], actor, "{}", action);
+    }
+}
+
+#[actix_web::test]
+async fn test_endpoints_only_exist_in_development_mode() {
+    let state = test_state();
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    let as_admin = |request: TestRequest| request.signed_in_as("admin");
+
+    let app = test_app(&state, false).await;
+    let (status, _) = send(
+        &app,
+        as_admin(TestRequest::post().uri("/api/test/populate")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let app = test_app(&state, true).await;
+    let (status, body) = send(
+        &app,
+        as_admin(TestRequest::post().uri("/api/test/populate?batch_size=0")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_invalid_field(&body, "batch_size");
+
+    let (status, body) = send(
+        &app,
+        as_admin(TestRequest::post().uri("/api/test/populate?anchor=yesterday")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_invalid_field(&body, "anchor");
+}
+
+#[actix_web::test]
+async fn test_endpoints_are_for_admins_only() {
+    let state = test_state();
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    insert_user(&state, "moderator", doc! { "role": "moderator" }).await;
+    let app = test_app(&state, true).await;
+
+    for uri in ["/api/test/populate", "/api/test/clean"] {
+        let (status, body) = send(&app, TestRequest::post().uri(uri)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", uri);
+        assert_error(&body, "Missing X-User-Id header");
+
+        let (status, _) = send(&app, TestRequest::post().uri(uri).signed_in_as("moderator")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+    }
+    let (status, _) = send(&app, TestRequest::get().uri("/api/test/populate/job")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Cleaning keeps the admin, and the audit log names them
+    let body = succeed(
+        &app,
+        TestRequest::post()
+            .uri("/api/test/clean")
+            .signed_in_as("admin"),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["status"], "success");
+    let users = state.users.list(doc! {}).await.unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].get_str("_id").unwrap(), "admin");
+    let entries = state
+        .audit_log
+        .list(doc! { "action": "clean_database" }, ListOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(entries[0].get_str("actor_id").unwrap(), "admin");
+}
+
+#[actix_web::test]
+async fn database_errors_keep_the_envelope() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    // A finished export whose archive is missing makes the store fail the read
+    state
+        .exports
+        .insert(doc! { "_id": "lost", "user_id": &alice, "status": "completed" })
+        .await
+        .expect("in-memory insert");
+
+    let (status, body) = send(
+        &app,
+        TestRequest::get()
+            .uri(&format!("/api/users/{}/export/lost/download", alice))
+            .signed_in_as(alice.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["status"], "error");
+    assert_eq!(body["message"], "A database error occurred");
+}
+
+#[actix_web::test]
+async fn health_check_reports_an_unreachable_database() {
+    let state = unreachable_state();
+    let app = test_app(&state, false).await;
+
+    for uri in ["/api/health", "/readyz"] {
+        let (status, body) = send(&app, TestRequest::get().uri(uri)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_error(&body, "Service is unhealthy");
+    }
+
+    // Liveness does not depend on the database
+    let (status, body) = send(&app, TestRequest::get().uri("/livez")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Service is alive");
+}
+
+#[actix_web::test]
+async fn metrics_count_what_the_api_creates() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let metrics = metrics::metrics();
+    let (users, posts, follows) = (
+        metrics.users_created.get(),
+        metrics.posts_created.get(),
+        metrics.follows_created.get(),
+    );
+
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+    let (status, _) = send(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Other tests create users in parallel, so only a lower bound holds
+    assert!(metrics.users_created.get() >= users + 2);
+    assert!(metrics.posts_created.get() > posts);
+    assert!(metrics.follows_created.get() > follows);
+
+    let res = test::call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/plain; version=0.0.4"
+    );
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(body.contains("# TYPE ddbp_users_created_total counter"));
+}
+
+#[actix_web::test]
+async fn cluster_status_is_admin_only() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+
+    let (status, _) = send(
+        &app,
+        TestRequest::get()
+            .uri("/api/admin/cluster")
+            .signed_in_as(alice.as_str()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// Every route registered by `routes::configure` answers with the JSON envelope. Routes
+// that need collections without a repository end in a database error here; the point is
+// that the route exists and its handler, not the router, produced the response.
+// Send a request that must succeed and return its body
+async fn succeed(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    request: TestRequest,
+    expected: StatusCode,
+) -> Value {
+    let request = request.to_request();
+    let route = format!("{} {}", request.method(), request.path());
+    let response = test::call_service(app, request).await;
+    let status = response.status();
+    let body: Value = serde_json::from_slice(&test::read_body(response).await)
+        .unwrap_or_else(|_| panic!("{} did not answer with JSON", route));
+    assert_eq!(status, expected, "{} answered {}", route, body);
+    assert_eq!(body["status"], "success", "{} answered {}", route, body);
+    assert!(body["message"].is_string(), "{} has no message", route);
+    body
+}
+
+// Wait for a background job polled at `uri` to leave the pending and running states
+async fn finished_job(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    uri: &str,
+    caller: &str,
+) -> Value {
+    for _ in 0..200 {
+        let body = succeed(
+            app,
+            TestRequest::get().uri(uri).signed_in_as(caller),
+            StatusCode::OK,
+        )
+        .await;
+        if !matches!(body["data"]["status"].as_str(), Some("pending" | "running")) {
+            return body["data"].clone();
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} did not finish", uri);
+}
+
+// Files of an export archive in the order they are written
+const EXPORT_FILES: [&str; 14] = [
+    "README.txt",
+    "profile.json",
+    "posts.json",
+    "comments.json",
+    "likes.json",
+    "reposts.json",
+    "poll_votes.json",
+    "following.json",
+    "followers.json",
+    "bookmarks.json",
+    "blocks.json",
+    "mutes.json",
+    "notifications.json",
+    "reports.json",
+];
+
+// The name and contents of each file in a zip archive
+fn unzip(archive: &[u8]) -> Vec<(String, String)> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).expect("zip archive");
+    (0..archive.len())
+        .map(|index| {
+            let mut file = archive.by_index(index).expect("archived file");
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).expect("UTF-8 contents");
+            (file.name().to_string(), contents)
+        })
+        .collect()
+}
+
+// Export the data of `user_id` as them and download the archive once it is built
+async fn download_export(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    user_id: &str,
+) -> Vec<(String, String)> {
+    let body = succeed(
+        app,
+        TestRequest::post()
+            .uri(&format!("/api/users/{}/export", user_id))
+            .signed_in_as(user_id),
+        StatusCode::ACCEPTED,
+    )
+    .await;
+    let export_uri = format!(
+        "/api/users/{}/export/{}",
+        user_id,
+        body["data"]["_id"].as_str().expect("export ID")
+    );
+    let export = finished_job(app, &export_uri, user_id).await;
+    assert_eq!(export["status"], "completed", "{}", export);
+
+    let response = test::call_service(
+        app,
+        TestRequest::get()
+            .uri(&format!("{}/download", export_uri))
+            .signed_in_as(user_id)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/zip"
+    );
+    unzip(&test::read_body(response).await)
+}
+
+#[actix_web::test]
+async fn exports_hold_blocks_mutes_notifications_and_reports() {
+    let state = test_state();
+    let app = test_app(&state, false).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    let carol = create_user(&app, "carol").await;
+    let post_id = create_post(&app, json!({ "user_id": &bob, "content": "Buy now" })).await;
+
+    for target in ["block", "mute"] {
+        succeed(
+            &app,
+            TestRequest::post()
+                .uri(&format!("/api/users/{}/{}", carol, target))
+                .signed_in_as(&alice),
+            StatusCode::CREATED,
+        )
+        .await;
+    }
+    succeed(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+        StatusCode::CREATED,
+    )
+    .await;
+    succeed(
+        &app,
+        post_json(
+            "/api/reports",
+            json!({ "target_type": "post", "target_id": &post_id, "reason": "Spam" }),
+        )
+        .signed_in_as(&alice),
+        StatusCode::CREATED,
+    )
+    .await;
+
+    let files = download_export(&app, &alice).await;
+    let contents = |name: &str| -> Value {
+        let (_, json) = files
+            .iter()
+            .find(|(file_name, _)| file_name == name)
+            .unwrap_or_else(|| panic!("{} is missing", name));
+        serde_json::from_str(json).expect("JSON file")
+    };
+    assert_eq!(contents("blocks.json")[0]["blocked_id"], carol);
+    assert_eq!(contents("mutes.json")[0]["muted_id"], carol);
+    let notifications = contents("notifications.json");
+    assert_eq!(notifications[0]["kind"], "follow");
+    assert_eq!(notifications[0]["actor_id"], bob);
+    let reports = contents("reports.json");
+    assert_eq!(reports[0]["target_id"], post_id);
+    assert_eq!(reports[0]["reason"], "Spam");
+
+    // Bob's own export has none of Alice's data
+    let files = download_export(&app, &bob).await;
+    for name in ["blocks.json", "mutes.json", "reports.json"] {
+        let (_, json) = files
+            .iter()
+            .find(|(file_name, _)| file_name == name)
+            .unwrap();
+        assert_eq!(json, "[]", "{}", name);
+    }
+
+    let (_, readme) = &files[0];
+    assert!(readme.contains("- notifications.json"), "{}", readme);
+    assert!(readme.contains("no direct messaging feature"), "{}", readme);
+}
+
+#[actix_web::test]
+async fn every_route_answers_with_the_envelope() {
+    let state = test_state();
+    let app = test_app(&state, true).await;
+    let alice = create_user(&app, "alice").await;
+    let bob = create_user(&app, "bob").await;
+    insert_user(&state, "admin", doc! { "role": "admin" }).await;
+    let user = |path: &str| format!("/api/users/{}{}", alice, path);
+    let as_alice = |request: TestRequest| request.signed_in_as(alice.as_str());
+    let as_bob = |request: TestRequest| request.signed_in_as(bob.as_str());
+    let as_admin = |request: TestRequest| request.signed_in_as("admin");
+
+    // Health
+    for uri in ["/api/health", "/readyz"] {
+        let body = succeed(&app, TestRequest::get().uri(uri), StatusCode::OK).await;
+        assert_eq!(body["message"], "Service is healthy");
+    }
+    let body = succeed(&app, TestRequest::get().uri("/livez"), StatusCode::OK).await;
+    assert_eq!(body["message"], "Service is alive");
+
+    // Users
+    let carol = create_user(&app, "carol").await;
+    let body = succeed(&app, TestRequest::get().uri("/api/users"), StatusCode::OK).await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(4));
+    assert!(body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .all(|user| user.get("role").is_none() && user.get("password_hash").is_none()));
+    let body = succeed(&app, TestRequest::get().uri(&user("")), StatusCode::OK).await;
+    assert_eq!(body["data"]["username"], "alice");
+    let carol_uri = format!("/api/users/{}", carol);
+    let as_carol = |request: TestRequest| request.signed_in_as(carol.as_str());
+    succeed(
+        &app,
+        as_carol(TestRequest::delete().uri(&carol_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    let (status, _) = send(&app, TestRequest::get().uri(&carol_uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    succeed(
+        &app,
+        as_carol(TestRequest::post().uri(&format!("{}/restore", carol_uri))),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(&app, TestRequest::get().uri(&carol_uri), StatusCode::OK).await;
+
+    // Posts
+    let post_id = create_post(&app, json!({ "user_id": &alice, "content": "Hello" })).await;
+    let body = succeed(&app, TestRequest::get().uri("/api/posts"), StatusCode::OK).await;
+    assert_eq!(body["data"][0]["_id"], post_id);
+    let post_uri = format!("/api/posts/{}", post_id);
+    let body = succeed(&app, TestRequest::get().uri(&post_uri), StatusCode::OK).await;
+    assert_eq!(body["data"]["id"], post_id);
+    assert_eq!(body["data"]["content"], "Hello");
+    succeed(
+        &app,
+        as_alice(TestRequest::delete().uri(&post_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    let (status, _) = send(&app, TestRequest::get().uri(&post_uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&format!("{}/restore", post_uri))),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(&app, TestRequest::get().uri(&post_uri), StatusCode::OK).await;
+
+    let draft_id = create_post(
+        &app,
+        json!({ "user_id": &alice, "content": "Later", "draft": true }),
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/drafts"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["id"], draft_id);
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&format!("/api/posts/{}/publish", draft_id))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(
+        body["message"],
+        format!("Post {} published successfully", draft_id)
+    );
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/drafts"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"], json!([]));
+
+    let repost_uri = format!("{}/repost", post_uri);
+    succeed(
+        &app,
+        as_bob(TestRequest::post().uri(&repost_uri)),
+        StatusCode::CREATED,
+    )
+    .await;
+    let body = succeed(&app, TestRequest::get().uri(&post_uri), StatusCode::OK).await;
+    assert_eq!(body["data"]["repost_count"], 1);
+    succeed(
+        &app,
+        as_bob(TestRequest::delete().uri(&repost_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(&app, TestRequest::get().uri(&post_uri), StatusCode::OK).await;
+    assert_eq!(body["data"]["repost_count"], 0);
+
+    let poll_id = create_post(
+        &app,
+        json!({
+            "user_id": &alice,
+            "content": "Tea or coffee?",
+            "post_type": "poll",
+            "poll": { "options": ["Tea", "Coffee"] },
+        }),
+    )
+    .await;
+    succeed(
+        &app,
+        as_bob(post_json(
+            &format!("/api/posts/{}/vote", poll_id),
+            json!({ "option_indexes": [1] }),
+        )),
+        StatusCode::CREATED,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&format!("/api/posts/{}", poll_id)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"]["poll"]["total_votes"], 1);
+
+    // Comments
+    succeed(
+        &app,
+        post_json(
+            "/api/create_comment",
+            json!({ "post_id": &post_id, "user_id": &bob, "content": "Hi" }),
+        ),
+        StatusCode::CREATED,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&format!("/api/comments/post/{}", post_id)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["content"], "Hi");
+    let comment_id = body["data"][0]["_id"]
+        .as_str()
+        .expect("comment ID")
+        .to_string();
+    for uri in [
+        "/api/comments".to_string(),
+        format!("/api/posts/comments/{}", post_id),
+        format!("/api/comments/user/{}", bob),
+    ] {
+        let body = succeed(&app, TestRequest::get().uri(&uri), StatusCode::OK).await;
+        assert_eq!(body["data"][0]["_id"], comment_id, "{}", uri);
+    }
+    let comment_uri = format!("/api/comments/{}", comment_id);
+    let body = succeed(&app, TestRequest::get().uri(&comment_uri), StatusCode::OK).await;
+    assert_eq!(body["data"]["content"], "Hi");
+    succeed(
+        &app,
+        as_bob(TestRequest::delete().uri(&comment_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    let (status, _) = send(&app, TestRequest::get().uri(&comment_uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    succeed(
+        &app,
+        as_bob(TestRequest::post().uri(&format!("{}/restore", comment_uri))),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(&app, TestRequest::get().uri(&comment_uri), StatusCode::OK).await;
+
+    // Follows, the timeline and notifications
+    succeed(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &bob, "following_id": &alice }),
+        ),
+        StatusCode::CREATED,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/following/{}", bob)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["_id"], alice);
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/followers/{}", alice)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["_id"], bob);
+    assert!(body["data"][0].get("password_hash").is_none());
+    let body = succeed(
+        &app,
+        TestRequest::get().uri(&format!("/api/users/posts/{}", alice)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(3));
+    let body = succeed(
+        &app,
+        as_bob(TestRequest::get().uri(&format!("/api/users/timeline/{}", bob))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(3));
+    assert_eq!(body["data"][0]["post"]["user_id"], alice);
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/notifications"))),
+        StatusCode::OK,
+    )
+    .await;
+    let kinds: Vec<&str> = body["data"]
+        .as_array()
+        .expect("notifications")
+        .iter()
+        .filter_map(|notification| notification["kind"].as_str())
+        .collect();
+    assert!(kinds.contains(&"follow"), "{:?}", kinds);
+    assert!(kinds.contains(&"comment"), "{:?}", kinds);
+
+    // Private accounts and follow requests
+    succeed(
+        &app,
+        as_alice(
+            TestRequest::put()
+                .uri(&user("/privacy"))
+                .set_json(json!({ "is_private": true })),
+        ),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &carol, "following_id": &alice }),
+        ),
+        StatusCode::ACCEPTED,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/follow_requests"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["follower_id"], carol);
+    succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&user(&format!("/follow_requests/{}/approve", carol)))),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&format!("/api/users/followers/{}", alice))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(2));
+    let dave = create_user(&app, "dave").await;
+    succeed(
+        &app,
+        post_json(
+            "/api/follow_user",
+            json!({ "follower_id": &dave, "following_id": &alice }),
+        ),
+        StatusCode::ACCEPTED,
+    )
+    .await;
+    succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&user(&format!("/follow_requests/{}/reject", dave)))),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/follow_requests"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"], json!([]));
+
+    // Blocks and mutes
+    let block_uri = format!("/api/users/{}/block", dave);
+    let mute_uri = format!("/api/users/{}/mute", dave);
+    succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&block_uri)),
+        StatusCode::CREATED,
+    )
+    .await;
+    succeed(
+        &app,
+        as_alice(TestRequest::post().uri(&mute_uri)),
+        StatusCode::CREATED,
+    )
+    .await;
+    for (path, field) in [("/blocks", "blocked_id"), ("/mutes", "muted_id")] {
+        let body = succeed(
+            &app,
+            as_alice(TestRequest::get().uri(&user(path))),
+            StatusCode::OK,
+        )
+        .await;
+        assert_eq!(body["data"][0][field], dave, "{}", path);
+    }
+    succeed(
+        &app,
+        as_alice(TestRequest::delete().uri(&block_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(
+        &app,
+        as_alice(TestRequest::delete().uri(&mute_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    for path in ["/blocks", "/mutes"] {
+        let body = succeed(
+            &app,
+            as_alice(TestRequest::get().uri(&user(path))),
+            StatusCode::OK,
+        )
+        .await;
+        assert_eq!(body["data"], json!([]), "{}", path);
+    }
+
+    // Bookmarks
+    succeed(
+        &app,
+        as_alice(post_json(
+            &user("/bookmarks"),
+            json!({ "post_id": &post_id, "collection": "Reading" }),
+        )),
+        StatusCode::CREATED,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/bookmarks"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["id"], post_id);
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/bookmarks/collections"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"], json!([{ "name": "Reading", "count": 1 }]));
+    succeed(
+        &app,
+        as_alice(
+            TestRequest::delete()
+                .uri(&user("/bookmarks"))
+                .set_json(json!({ "post_id": &post_id })),
+        ),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_alice(TestRequest::get().uri(&user("/bookmarks"))),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"], json!([]));
+
+    // Exports
+    let files = download_export(&app, &alice).await;
+    let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, EXPORT_FILES);
+    let profile: Value = serde_json::from_str(&files[1].1).expect("profile JSON");
+    assert_eq!(profile[0]["_id"], alice);
+    assert_eq!(profile[0]["username"], "alice");
+    assert!(profile[0].get("password_hash").is_none(), "{}", profile);
+
+    // Reports and moderation
+    let body = succeed(
+        &app,
+        as_bob(post_json(
+            "/api/reports",
+            json!({ "target_type": "post", "target_id": &post_id, "reason": "Spam" }),
+        )),
+        StatusCode::CREATED,
+    )
+    .await;
+    let report_id = body["data"].as_str().expect("report ID").to_string();
+    let body = succeed(
+        &app,
+        as_admin(TestRequest::get().uri("/api/moderation/reports")),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["_id"], report_id);
+    let report_uri = format!("/api/moderation/reports/{}", report_id);
+    succeed(
+        &app,
+        as_admin(TestRequest::post().uri(&format!("{}/claim", report_uri))),
+        StatusCode::OK,
+    )
+    .await;
+    succeed(
+        &app,
+        as_admin(post_json(
+            &format!("{}/resolve", report_uri),
+            json!({ "action": "dismiss" }),
+        )),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(
+        &app,
+        as_admin(TestRequest::get().uri(&report_uri)),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"]["status"], "resolved", "{}", body);
+    assert_eq!(body["data"]["resolution"]["action"], "dismiss");
+    assert_eq!(body["data"]["history"].as_array().map(Vec::len), Some(3));
+
+    // Administration
+    succeed(
+        &app,
+        as_admin(
+            TestRequest::put()
+                .uri(&format!("/api/admin/users/{}/role", bob))
+                .set_json(json!({ "role": "moderator" })),
+        ),
+        StatusCode::OK,
+    )
+    .await;
+    let stored = state.users.find_by_id(&bob).await.unwrap().expect("bob");
+    assert_eq!(stored.get_str("role").unwrap(), "moderator");
+    let body = succeed(
+        &app,
+        as_admin(TestRequest::get().uri("/api/admin/audit")),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"][0]["action"], "update_user_role");
+    assert_eq!(body["data"][0]["actor_id"], "admin");
+    assert_eq!(body["data"][0]["changes"]["role"]["after"], "moderator");
+    let body = succeed(
+        &app,
+        as_admin(TestRequest::get().uri("/api/admin/cluster")),
+        StatusCode::OK,
+    )
+    .await;
+    assert_eq!(body["data"]["members"][0]["healthy"], true);
+
+    // Seeding
+    let body = succeed(
+        &app,
+        as_admin(TestRequest::post().uri(
+            "/api/test/populate?users=3&posts=5&comments=5&likes=5&follows=3&seed=18446744073709551615",
+        )),
+        StatusCode::ACCEPTED,
+    )
+    .await;
+    let job_id = body["data"]["_id"].as_str().expect("job ID").to_string();
+    let job = finished_job(&app, &format!("/api/test/populate/{}", job_id), "admin").await;
+    assert_eq!(job["status"], "completed", "{}", job);
+    // Seeds use the full u64 range, so jobs keep them as decimal strings
+    assert_eq!(job["seed"], "18446744073709551615");
+    assert_eq!(job["options"]["seed"], "18446744073709551615");
+    // The admin who seeded the database keeps their account
+    let body = succeed(&app, TestRequest::get().uri("/api/users"), StatusCode::OK).await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(4));
+    succeed(
+        &app,
+        as_admin(TestRequest::post().uri("/api/test/clean")),
+        StatusCode::OK,
+    )
+    .await;
+    let body = succeed(&app, TestRequest::get().uri("/api/users"), StatusCode::OK).await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(1));
+    assert_eq!(body["data"][0]["_id"], "admin");
+}
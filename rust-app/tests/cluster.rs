@@ -0,0 +1,93 @@
+use ddbp::cluster::ClusterStatus;
+use mongodb::bson::{doc, DateTime, Document};
+
+fn at(millis: i64) -> DateTime {
+    DateTime::from_millis(1_760_000_000_000 + millis)
+}
+
+// Trimmed `replSetGetStatus` output of the three-node rs0 set, answered by a secondary
+fn rs0_status(primary_state: &str) -> Document {
+    doc! {
+        "set": "rs0",
+        "date": at(10_000),
+        "myState": 2,
+        "term": 7_i64,
+        "members": [
+            {
+                "_id": 0,
+                "name": "central-mongodb:27017",
+                "health": 1.0,
+                "state": 1,
+                "stateStr": primary_state,
+                "uptime": 3600,
+                "optimeDate": at(9_000),
+                "lastHeartbeat": at(8_500),
+                "pingMs": 2_i64,
+                "syncSourceHost": "",
+            },
+            {
+                "_id": 1,
+                "name": "secondary-mongodb-1:27017",
+                "health": 1.0,
+                "state": 2,
+                "stateStr": "SECONDARY",
+                "uptime": 3590,
+                "optimeDate": at(6_000),
+                "syncSourceHost": "central-mongodb:27017",
+                "self": true,
+            },
+            {
+                "_id": 2,
+                "name": "secondary-mongodb-2:27017",
+                "health": 0.0,
+                "state": 8,
+                "stateStr": "(not reachable/healthy)",
+                "uptime": 0,
+                "optimeDate": at(-50_000),
+                "lastHeartbeat": at(-40_000),
+            },
+        ],
+    }
+}
+
+#[test]
+fn reports_members_primary_and_term() {
+    let status = ClusterStatus::from_document(&rs0_status("PRIMARY"));
+    assert_eq!(status.set, "rs0");
+    assert_eq!(status.term, Some(7));
+    assert_eq!(status.primary.as_deref(), Some("central-mongodb:27017"));
+    assert!(status.checked_at.is_some());
+    assert_eq!(status.members.len(), 3);
+
+    let primary = &status.members[0];
+    assert_eq!(primary.state, "PRIMARY");
+    assert!(primary.healthy);
+    assert!(!primary.is_self);
+    assert_eq!(primary.lag_secs, Some(0));
+    assert_eq!(primary.ping_ms, Some(2));
+    assert!(primary.last_heartbeat.is_some());
+    assert!(primary.sync_source.is_none());
+
+    let answering = &status.members[1];
+    assert!(answering.is_self);
+    assert_eq!(answering.lag_secs, Some(3));
+    assert!(answering.last_heartbeat.is_none());
+    assert_eq!(
+        answering.sync_source.as_deref(),
+        Some("central-mongodb:27017")
+    );
+
+    let down = &status.members[2];
+    assert!(!down.healthy);
+    assert_eq!(down.lag_secs, Some(59));
+}
+
+#[test]
+fn lag_is_unknown_without_a_primary() {
+    let status = ClusterStatus::from_document(&rs0_status("SECONDARY"));
+    assert!(status.primary.is_none());
+    assert!(status
+        .members
+        .iter()
+        .all(|member| member.lag_secs.is_none()));
+}
@@ -0,0 +1,29 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use ddbp::{
+    config::{Config, ConfigError},
+    errors::AppError,
+};
+
+// The field named by the validation error of `config`
+pub fn invalid_field(config: &Config) -> String {
+    match config.validate() {
+        Err(ConfigError::Invalid { field, .. }) => field,
+        other => panic!("expected a validation error, got {:?}", other),
+    }
+}
+
+// Middleware rejecting every request with an AppError, like the role guards do
+pub async fn deny(
+    _req: ServiceRequest,
+    _next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    Err::<ServiceResponse, _>(AppError::Forbidden("Members only".to_string()).into())
+}
@@ -0,0 +1,33 @@
+use ddbp::lifecycle::BackgroundJobs;
+use std::time::Duration;
+
+#[actix_web::test]
+async fn shutdown_stops_periodic_jobs() {
+    let jobs = BackgroundJobs::new();
+    let stopping = jobs.stopping();
+    jobs.spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_millis(10));
+        tokio::select! {
+            _ = async { loop { ticker.tick().await; } } => {}
+            _ = stopping => {}
+        }
+    });
+    assert_eq!(jobs.running(), 1);
+
+    assert_eq!(jobs.shutdown(Duration::from_secs(5)).await, 0);
+    assert_eq!(jobs.running(), 0);
+}
+
+#[actix_web::test]
+async fn shutdown_gives_up_after_the_timeout() {
+    let jobs = BackgroundJobs::new();
+    jobs.spawn(async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    });
+    jobs.spawn(async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    });
+
+    // The short job finishes, the one ignoring the stop signal is left behind
+    assert_eq!(jobs.shutdown(Duration::from_millis(200)).await, 1);
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use actix_web::{
+    http::StatusCode,
+    middleware::from_fn,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use common::{deny, invalid_field};
+use ddbp::{
+    audit::AuditContext,
+    config::Config,
+    errors::AppError,
+    logging::{self, REQUEST_ID_HEADER},
+};
+use serde_json::Value;
+
+// Send a request through the request ID middleware and return the status, the returned
+// request ID and the body. Errors from middleware are rendered like the server would.
+async fn call(request: TestRequest) -> (StatusCode, String, Value) {
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(logging::assign_request_id))
+            .route(
+                "/audit",
+                web::get().to(|audit: AuditContext| async move {
+                    HttpResponse::Ok().json(serde_json::json!({ "request_id": audit.request_id }))
+                }),
+            )
+            .route(
+                "/missing",
+                web::get().to(|| async {
+                    Err::<HttpResponse, _>(AppError::NotFound("Nothing here".to_string()))
+                }),
+            )
+            .service(
+                web::scope("/members")
+                    .wrap(from_fn(deny))
+                    .route("", web::get().to(HttpResponse::Ok)),
+            ),
+    )
+    .await;
+    let res = match test::try_call_service(&app, request.to_request()).await {
+        Ok(res) => res.into_parts().1.map_into_boxed_body(),
+        Err(e) => e.error_response(),
+    };
+    let status = res.status();
+    let request_id = res
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .expect("every response has a request ID")
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+    (
+        status,
+        request_id,
+        serde_json::from_slice(&body).unwrap_or(Value::Null),
+    )
+}
+
+#[actix_web::test]
+async fn requests_get_an_id_that_handlers_see() {
+    let (status, request_id, body) = call(TestRequest::get().uri("/audit")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(uuid::Uuid::parse_str(&request_id).is_ok());
+    assert_eq!(body["request_id"], request_id);
+}
+
+#[actix_web::test]
+async fn valid_client_ids_are_kept() {
+    let (_, request_id, body) = call(
+        TestRequest::get()
+            .uri("/audit")
+            .insert_header((REQUEST_ID_HEADER, "frontend-42.retry:1")),
+    )
+    .await;
+    assert_eq!(request_id, "frontend-42.retry:1");
+    assert_eq!(body["request_id"], "frontend-42.retry:1");
+
+    let long = "a".repeat(129);
+    for invalid in ["two words", "id;drop", "{\"json\":1}", long.as_str()] {
+        let (_, request_id, _) = call(
+            TestRequest::get()
+                .uri("/audit")
+                .insert_header((REQUEST_ID_HEADER, invalid)),
+        )
+        .await;
+        assert!(uuid::Uuid::parse_str(&request_id).is_ok(), "{}", invalid);
+    }
+}
+
+#[actix_web::test]
+async fn error_bodies_carry_the_request_id() {
+    let (status, request_id, body) = call(
+        TestRequest::get()
+            .uri("/missing")
+            .insert_header((REQUEST_ID_HEADER, "req-1")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(request_id, "req-1");
+    assert_eq!(body["status"], "error");
+    assert_eq!(body["message"], "Nothing here");
+    assert_eq!(body["request_id"], "req-1");
+
+    // Errors returned by middleware are tagged too
+    let (status, request_id, body) = call(TestRequest::get().uri("/members")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["request_id"], request_id);
+}
+
+#[test]
+fn log_levels_come_from_the_config() {
+    let config = Config::parse(
+        r#"
+        [logging]
+        format = "json"
+        level = "warn"
+
+        [logging.modules]
+        "ddbp::handlers" = "debug"
+        mongodb = "error"
+        "#,
+    )
+    .expect("valid file");
+    config.validate().expect("valid levels");
+    assert_eq!(
+        config.logging.directives(),
+        "warn,ddbp::handlers=debug,mongodb=error"
+    );
+    assert_eq!(Config::default().logging.directives(), "info");
+
+    let mut config = Config::default();
+    config.logging.level = "verbose".to_string();
+    assert_eq!(invalid_field(&config), "logging.level");
+
+    let mut config = Config::default();
+    config
+        .logging
+        .modules
+        .insert("ddbp=trace,hyper".to_string(), "info".to_string());
+    assert_eq!(invalid_field(&config), "logging.modules.ddbp=trace,hyper");
+
+    assert!(Config::parse("[logging]\nformat = \"yaml\"").is_err());
+}
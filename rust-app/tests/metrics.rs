@@ -0,0 +1,98 @@
+mod common;
+
+use actix_web::{
+    middleware::from_fn,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use common::deny;
+use ddbp::{
+    errors::AppError,
+    metrics::{self, metrics},
+};
+use mongodb::options::ClientOptions;
+
+// Metrics are process-wide and tests run in parallel, so every test uses its own routes
+fn requests(route: &str, status: &str) -> u64 {
+    metrics()
+        .http_requests
+        .with_label_values(&["GET", route, status])
+        .get()
+}
+
+fn errors(variant: &str) -> u64 {
+    metrics().app_errors.with_label_values(&[variant]).get()
+}
+
+#[actix_web::test]
+async fn requests_are_counted_by_route_pattern_and_status() {
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(metrics::track_requests))
+            .route(
+                "/counted/{id}",
+                web::get().to(|| async { HttpResponse::Ok().finish() }),
+            )
+            .route(
+                "/missing/{id}",
+                web::get().to(|| async {
+                    Err::<HttpResponse, _>(AppError::NotFound("Nothing here".to_string()))
+                }),
+            )
+            .service(
+                web::scope("/members")
+                    .wrap(from_fn(deny))
+                    .route("/{id}", web::get().to(HttpResponse::Ok)),
+            ),
+    )
+    .await;
+    let not_found = errors("NotFound");
+    let forbidden = errors("Forbidden");
+
+    for id in ["a", "b"] {
+        let request = TestRequest::get().uri(&format!("/counted/{}", id));
+        test::call_service(&app, request.to_request()).await;
+    }
+    assert_eq!(requests("/counted/{id}", "200"), 2);
+    let latency = &metrics().http_request_duration;
+    let samples = latency.with_label_values(&["GET", "/counted/{id}", "200"]);
+    assert_eq!(samples.get_sample_count(), 2);
+
+    let request = TestRequest::get().uri("/missing/a").to_request();
+    test::call_service(&app, request).await;
+    assert_eq!(requests("/missing/{id}", "404"), 1);
+    assert!(errors("NotFound") > not_found);
+
+    // Errors returned by middleware keep the status of the AppError, and are counted once
+    // the server renders them
+    let request = TestRequest::get().uri("/members/a").to_request();
+    let error = test::try_call_service(&app, request)
+        .await
+        .err()
+        .expect("the middleware rejects the request");
+    assert_eq!(requests("/members/{id}", "403"), 1);
+    assert_eq!(error.error_response().status(), 403);
+    assert!(errors("Forbidden") > forbidden);
+}
+
+#[actix_web::test]
+async fn unknown_paths_share_one_label() {
+    let app = test::init_service(App::new().wrap(from_fn(metrics::track_requests))).await;
+    let before = requests("unmatched", "404");
+    for uri in ["/nope/1", "/nope/2"] {
+        test::call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+    }
+    assert!(requests("unmatched", "404") >= before + 2);
+}
+
+#[test]
+fn monitoring_subscribes_to_driver_events() {
+    let mut options = ClientOptions::builder().max_pool_size(20).build();
+    metrics::monitor(&mut options);
+    assert!(options.command_event_handler.is_some());
+    assert!(options.cmap_event_handler.is_some());
+    assert_eq!(metrics().mongo_pool_max_connections.get(), 20);
+
+    let text = metrics().render();
+    assert!(text.contains("# TYPE ddbp_mongo_pool_max_connections gauge"));
+}
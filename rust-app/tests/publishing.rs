@@ -0,0 +1,146 @@
+use ddbp::{
+    audit::AuditContext,
+    publishing::publish_due_posts,
+    repository::{AuditRepo, ExportRepo, ListOptions, MemoryStore, SeedRepo},
+};
+use mongodb::bson::{doc, Document};
+use std::time::Duration;
+
+const AUDIT_RETENTION: Duration = Duration::from_secs(86400);
+
+async fn one(store: &MemoryStore, collection: &str, id: &str) -> Document {
+    store
+        .collect(collection, doc! { "_id": id })
+        .await
+        .expect("collect")
+        .pop()
+        .unwrap_or_else(|| panic!("{} {} is missing", collection, id))
+}
+
+async fn publish(store: &MemoryStore) -> usize {
+    publish_due_posts(store, &AuditContext::system(AUDIT_RETENTION))
+        .await
+        .expect("publish")
+}
+
+// A post that was due long ago, mentioning a user and quoting a post, next to a post
+// scheduled far in the future and a draft
+async fn scheduled_posts() -> MemoryStore {
+    let store = MemoryStore::default();
+    let users = ["author", "mentioned", "quoted"]
+        .map(|id| doc! { "_id": id, "post_count": 0 })
+        .to_vec();
+    store.insert_many("users", users).await.expect("users");
+    store
+        .insert_many(
+            "posts",
+            vec![
+                doc! { "_id": "original", "user_id": "quoted", "status": "published", "quote_count": 0 },
+                doc! {
+                    "_id": "due",
+                    "user_id": "author",
+                    "status": "scheduled",
+                    "publish_at": "2000-01-01T00:00:00+00:00",
+                    "mentions": ["mentioned"],
+                    "quoted_post_id": "original",
+                },
+                doc! {
+                    "_id": "future",
+                    "user_id": "author",
+                    "status": "scheduled",
+                    "publish_at": "2999-01-01T00:00:00+00:00",
+                },
+                doc! { "_id": "draft", "user_id": "author", "status": "draft" },
+            ],
+        )
+        .await
+        .expect("posts");
+    store
+}
+
+#[tokio::test]
+async fn due_posts_are_published_exactly_once() {
+    let store = scheduled_posts().await;
+    assert_eq!(publish(&store).await, 1);
+    // A second run, e.g. after a restart, finds nothing left to publish
+    assert_eq!(publish(&store).await, 0);
+
+    let due = one(&store, "posts", "due").await;
+    assert_eq!(due.get_str("status"), Ok("published"));
+    assert!(due.get_str("published_at").is_ok());
+    assert!(!due.contains_key("publish_at"));
+
+    let entries = AuditRepo::list(&store, doc! {}, ListOptions::default())
+        .await
+        .expect("audit log");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].get_str("action"), Ok("publish_post"));
+    assert_eq!(entries[0].get_str("target_id"), Ok("due"));
+}
+
+#[tokio::test]
+async fn concurrent_runs_publish_a_post_once() {
+    let store = scheduled_posts().await;
+    let (first, second) = tokio::join!(publish(&store), publish(&store));
+    assert_eq!(first + second, 1);
+    assert_eq!(
+        one(&store, "users", "author").await.get_i32("post_count"),
+        Ok(1)
+    );
+}
+
+#[tokio::test]
+async fn future_posts_and_drafts_stay_unpublished() {
+    let store = scheduled_posts().await;
+    publish(&store).await;
+
+    let future = one(&store, "posts", "future").await;
+    assert_eq!(future.get_str("status"), Ok("scheduled"));
+    assert_eq!(
+        future.get_str("publish_at"),
+        Ok("2999-01-01T00:00:00+00:00")
+    );
+    assert_eq!(
+        one(&store, "posts", "draft").await.get_str("status"),
+        Ok("draft")
+    );
+}
+
+#[tokio::test]
+async fn publishing_applies_the_counters_and_notifications() {
+    let store = scheduled_posts().await;
+    publish(&store).await;
+
+    assert_eq!(
+        one(&store, "users", "author").await.get_i32("post_count"),
+        Ok(1)
+    );
+    assert_eq!(
+        one(&store, "posts", "original")
+            .await
+            .get_i32("quote_count"),
+        Ok(1)
+    );
+
+    let notifications = store
+        .collect("notifications", doc! {})
+        .await
+        .expect("notifications");
+    let received: Vec<(&str, &str, &str)> = notifications
+        .iter()
+        .map(|notification| {
+            (
+                notification.get_str("user_id").unwrap(),
+                notification.get_str("kind").unwrap(),
+                notification.get_str("actor_id").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        received,
+        [
+            ("mentioned", "mention", "author"),
+            ("quoted", "quote", "author")
+        ]
+    );
+}
@@ -0,0 +1,112 @@
+use ddbp::{
+    errors::is_duplicate_key_error,
+    repository::{ListOptions, MemoryStore, PostRepo, SeedRepo, UserRepo},
+};
+use mongodb::bson::{doc, Bson, Document};
+
+// Five posts from oldest to newest, with a poll on the last two
+async fn posts() -> MemoryStore {
+    let store = MemoryStore::default();
+    let posts = (1..=5)
+        .map(|n| {
+            let mut post = doc! {
+                "_id": format!("p{}", n),
+                "user_id": if n % 2 == 0 { "alice" } else { "bob" },
+                "mentions": if n == 3 { vec!["carol", "dave"] } else { Vec::new() },
+                "created_at": format!("2026-01-0{}T00:00:00+00:00", n),
+            };
+            if n >= 4 {
+                post.insert("poll", doc! { "total_votes": n * 10 });
+            }
+            if n == 1 {
+                post.insert("deleted_at", "2026-02-01T00:00:00+00:00");
+            }
+            post
+        })
+        .collect();
+    store.insert_many("posts", posts).await.expect("insert");
+    store
+}
+
+async fn ids(store: &MemoryStore, filter: Document, options: ListOptions) -> Vec<String> {
+    PostRepo::list(store, filter, options)
+        .await
+        .expect("list")
+        .iter()
+        .map(|post| post.get_str("_id").unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn memory_filters_match_like_mongodb_queries() {
+    let store = posts().await;
+    let cases = [
+        (doc! { "user_id": "alice" }, vec!["p2", "p4"]),
+        // A scalar matches an array holding it
+        (doc! { "mentions": "dave" }, vec!["p3"]),
+        (
+            doc! { "user_id": { "$in": ["alice"] }, "_id": { "$ne": "p2" } },
+            vec!["p4"],
+        ),
+        (
+            doc! { "deleted_at": { "$exists": false }, "user_id": { "$nin": ["alice"] } },
+            vec!["p3", "p5"],
+        ),
+        (
+            doc! { "created_at": { "$lte": "2026-01-02T00:00:00+00:00" } },
+            vec!["p1", "p2"],
+        ),
+        // Dotted paths reach into embedded documents, and numbers compare across types
+        (doc! { "poll.total_votes": { "$gt": 40_i64 } }, vec!["p5"]),
+        // Null matches a missing field
+        (doc! { "poll": Bson::Null }, vec!["p1", "p2", "p3"]),
+        (
+            doc! { "$or": [{ "_id": "p1" }, { "mentions": "carol" }] },
+            vec!["p1", "p3"],
+        ),
+        (
+            doc! { "$and": [{ "user_id": "bob" }, { "poll": { "$exists": true } }] },
+            vec!["p5"],
+        ),
+    ];
+    for (filter, expected) in cases {
+        assert_eq!(
+            ids(&store, filter.clone(), ListOptions::default()).await,
+            expected,
+            "{}",
+            filter
+        );
+    }
+}
+
+#[tokio::test]
+async fn memory_lists_sort_before_cutting_the_page() {
+    let store = posts().await;
+    let newest_first = ListOptions::sorted(doc! { "created_at": -1 });
+    assert_eq!(
+        ids(&store, doc! {}, newest_first.clone().page(2, 2)).await,
+        ["p3", "p2"]
+    );
+    assert_eq!(ids(&store, doc! {}, newest_first.page(3, 2)).await, ["p1"]);
+    assert_eq!(
+        ids(
+            &store,
+            doc! {},
+            ListOptions::sorted(doc! { "user_id": 1, "created_at": -1 })
+        )
+        .await,
+        ["p4", "p2", "p5", "p3", "p1"]
+    );
+}
+
+#[tokio::test]
+async fn memory_writes_report_duplicates_like_mongodb() {
+    let store = MemoryStore::default();
+    let user = doc! { "_id": "alice", "username": "alice" };
+    UserRepo::insert(&store, user.clone())
+        .await
+        .expect("insert");
+    let error = UserRepo::insert(&store, user).await.unwrap_err();
+    assert!(is_duplicate_key_error(&error), "{}", error);
+    assert_eq!(UserRepo::list(&store, doc! {}).await.unwrap().len(), 1);
+}
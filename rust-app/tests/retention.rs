@@ -0,0 +1,261 @@
+use ddbp::{
+    audit::{AuditContext, SYSTEM_ACTOR},
+    repository::{AuditRepo, ExportRepo, ListOptions, MemoryStore, SeedRepo},
+    retention::purge_expired,
+};
+use mongodb::bson::{doc, Document};
+use std::time::Duration;
+
+const RETENTION: Duration = Duration::from_secs(30 * 86400);
+const LONG_AGO: &str = "2000-01-01T00:00:00+00:00";
+const RECENTLY: &str = "2999-01-01T00:00:00+00:00";
+
+async fn insert(store: &MemoryStore, collection: &str, documents: Vec<Document>) {
+    store
+        .insert_many(collection, documents)
+        .await
+        .expect("insert");
+}
+
+async fn all(store: &MemoryStore, collection: &str) -> Vec<Document> {
+    store.collect(collection, doc! {}).await.expect("collect")
+}
+
+async fn one(store: &MemoryStore, collection: &str, id: &str) -> Document {
+    store
+        .collect(collection, doc! { "_id": id })
+        .await
+        .expect("collect")
+        .pop()
+        .unwrap_or_else(|| panic!("{} {} is missing", collection, id))
+}
+
+fn ids(documents: &[Document]) -> Vec<&str> {
+    documents
+        .iter()
+        .map(|document| document.get_str("_id").unwrap())
+        .collect()
+}
+
+async fn purge(store: &MemoryStore) -> usize {
+    purge_expired(store, RETENTION, &AuditContext::system(RETENTION))
+        .await
+        .expect("purge")
+}
+
+// Gone was deleted long ago and left likes, a repost, a poll vote, a quote, follows and
+// bookmarks behind on the accounts and posts of Stays and Follower
+async fn deleted_user_with_connections() -> MemoryStore {
+    let store = MemoryStore::default();
+    insert(
+        &store,
+        "users",
+        vec![
+            doc! { "_id": "gone", "post_count": 1, "deleted_at": LONG_AGO },
+            doc! { "_id": "stays", "post_count": 1, "follower_count": 1, "following_count": 1 },
+            doc! { "_id": "follower", "following_count": 1 },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "posts",
+        vec![
+            doc! {
+                "_id": "kept",
+                "user_id": "stays",
+                "status": "published",
+                "like_count": 1,
+                "repost_count": 1,
+                "quote_count": 1,
+                "poll": {
+                    "options": [{ "text": "yes", "vote_count": 1 }, { "text": "no", "vote_count": 0 }],
+                    "total_votes": 1,
+                },
+            },
+            doc! {
+                "_id": "quote",
+                "user_id": "gone",
+                "status": "published",
+                "quoted_post_id": "kept",
+                "like_count": 1,
+            },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "comments",
+        vec![
+            doc! { "_id": "by-gone", "user_id": "gone", "post_id": "kept" },
+            doc! { "_id": "on-quote", "user_id": "stays", "post_id": "quote" },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "likes",
+        vec![
+            doc! { "_id": "gone-likes-kept", "user_id": "gone", "post_id": "kept" },
+            doc! { "_id": "stays-likes-quote", "user_id": "stays", "post_id": "quote" },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "reposts",
+        vec![doc! { "_id": "gone-reposts-kept", "user_id": "gone", "post_id": "kept" }],
+    )
+    .await;
+    insert(
+        &store,
+        "poll_votes",
+        vec![doc! { "_id": "gone-votes", "user_id": "gone", "post_id": "kept", "option_indexes": [0] }],
+    )
+    .await;
+    insert(
+        &store,
+        "follows",
+        vec![
+            doc! { "_id": "gone-follows-stays", "follower_id": "gone", "following_id": "stays" },
+            doc! { "_id": "stays-follows-gone", "follower_id": "stays", "following_id": "gone" },
+            doc! { "_id": "follower-follows-gone", "follower_id": "follower", "following_id": "gone" },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "bookmarks",
+        vec![
+            doc! { "_id": "gone-bookmarks-kept", "user_id": "gone", "post_id": "kept" },
+            doc! { "_id": "stays-bookmarks-quote", "user_id": "stays", "post_id": "quote" },
+        ],
+    )
+    .await;
+    store
+}
+
+#[tokio::test]
+async fn purging_a_user_removes_everything_they_left_behind() {
+    let store = deleted_user_with_connections().await;
+    assert_eq!(purge(&store).await, 1);
+
+    assert_eq!(ids(&all(&store, "users").await), ["stays", "follower"]);
+    assert_eq!(ids(&all(&store, "posts").await), ["kept"]);
+    for collection in [
+        "comments",
+        "likes",
+        "reposts",
+        "poll_votes",
+        "follows",
+        "bookmarks",
+    ] {
+        assert_eq!(all(&store, collection).await, [], "{} are left", collection);
+    }
+}
+
+#[tokio::test]
+async fn purging_a_user_takes_back_their_share_of_the_counters() {
+    let store = deleted_user_with_connections().await;
+    purge(&store).await;
+
+    let kept = one(&store, "posts", "kept").await;
+    assert_eq!(kept.get_i32("like_count"), Ok(0));
+    assert_eq!(kept.get_i32("repost_count"), Ok(0));
+    assert_eq!(kept.get_i32("quote_count"), Ok(0));
+    let poll = kept.get_document("poll").unwrap();
+    assert_eq!(poll.get_i32("total_votes"), Ok(0));
+    let first_option = poll.get_array("options").unwrap()[0].as_document().unwrap();
+    assert_eq!(first_option.get_i32("vote_count"), Ok(0));
+
+    let stays = one(&store, "users", "stays").await;
+    assert_eq!(stays.get_i32("post_count"), Ok(1));
+    assert_eq!(stays.get_i32("follower_count"), Ok(0));
+    assert_eq!(stays.get_i32("following_count"), Ok(0));
+    let follower = one(&store, "users", "follower").await;
+    assert_eq!(follower.get_i32("following_count"), Ok(0));
+
+    // Running again finds nothing left to purge or to decrement
+    assert_eq!(purge(&store).await, 0);
+    assert_eq!(one(&store, "posts", "kept").await, kept);
+}
+
+#[tokio::test]
+async fn a_soft_deleted_post_keeps_its_counters_when_purged() {
+    let store = MemoryStore::default();
+    insert(
+        &store,
+        "users",
+        vec![doc! { "_id": "author", "post_count": 0 }],
+    )
+    .await;
+    // The quote's share was taken back when it was soft-deleted
+    insert(
+        &store,
+        "posts",
+        vec![
+            doc! { "_id": "original", "user_id": "author", "status": "published", "quote_count": 0 },
+            doc! {
+                "_id": "quote",
+                "user_id": "author",
+                "status": "published",
+                "quoted_post_id": "original",
+                "deleted_at": LONG_AGO,
+            },
+            doc! { "_id": "restorable", "user_id": "author", "status": "published", "deleted_at": RECENTLY },
+        ],
+    )
+    .await;
+    insert(
+        &store,
+        "comments",
+        vec![doc! { "_id": "on-quote", "user_id": "author", "post_id": "quote" }],
+    )
+    .await;
+    insert(
+        &store,
+        "likes",
+        vec![doc! { "_id": "like", "user_id": "author", "post_id": "quote" }],
+    )
+    .await;
+
+    assert_eq!(purge(&store).await, 1);
+    assert_eq!(ids(&all(&store, "posts").await), ["original", "restorable"]);
+    assert_eq!(all(&store, "comments").await, []);
+    assert_eq!(all(&store, "likes").await, []);
+    assert_eq!(
+        one(&store, "posts", "original")
+            .await
+            .get_i32("quote_count"),
+        Ok(0)
+    );
+    assert_eq!(
+        one(&store, "users", "author").await.get_i32("post_count"),
+        Ok(0)
+    );
+}
+
+#[tokio::test]
+async fn comments_are_purged_once_their_restore_window_passes() {
+    let store = MemoryStore::default();
+    insert(
+        &store,
+        "comments",
+        vec![
+            doc! { "_id": "expired", "user_id": "someone", "post_id": "post", "deleted_at": LONG_AGO },
+            doc! { "_id": "restorable", "user_id": "someone", "post_id": "post", "deleted_at": RECENTLY },
+            doc! { "_id": "live", "user_id": "someone", "post_id": "post" },
+        ],
+    )
+    .await;
+
+    assert_eq!(purge(&store).await, 1);
+    assert_eq!(ids(&all(&store, "comments").await), ["restorable", "live"]);
+
+    let entries = AuditRepo::list(&store, doc! {}, ListOptions::default())
+        .await
+        .expect("audit log");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].get_str("action"), Ok("purge_comment"));
+    assert_eq!(entries[0].get_str("actor_id"), Ok(SYSTEM_ACTOR));
+}
@@ -0,0 +1,223 @@
+use actix_web::{
+    http::{
+        header::{self, HeaderMap},
+        StatusCode,
+    },
+    middleware::from_fn,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use ddbp::{
+    config::Config,
+    security::{self, origin_allowed, SecurityHeaders},
+};
+
+fn allowed(origins: &[&str]) -> Vec<String> {
+    origins.iter().map(|origin| origin.to_string()).collect()
+}
+
+#[test]
+fn exact_origins_must_match_completely() {
+    let origins = allowed(&["https://ddbp.example.com", "http://localhost:3000"]);
+    assert!(origin_allowed(&origins, "https://ddbp.example.com"));
+    assert!(origin_allowed(&origins, "http://localhost:3000"));
+    assert!(!origin_allowed(&origins, "http://ddbp.example.com"));
+    assert!(!origin_allowed(&origins, "http://localhost:3001"));
+    assert!(!origin_allowed(
+        &origins,
+        "https://ddbp.example.com.evil.net"
+    ));
+}
+
+#[test]
+fn wildcard_origins_match_subdomains_only() {
+    let origins = allowed(&["https://*.example.com", "http://*.internal:8080"]);
+    assert!(origin_allowed(&origins, "https://app.example.com"));
+    assert!(origin_allowed(&origins, "https://eu.app.example.com"));
+    assert!(origin_allowed(&origins, "http://admin.internal:8080"));
+    assert!(!origin_allowed(&origins, "https://example.com"));
+    assert!(!origin_allowed(&origins, "https://evilexample.com"));
+    assert!(!origin_allowed(&origins, "http://app.example.com"));
+    assert!(!origin_allowed(&origins, "https://app.example.com:8443"));
+    assert!(!origin_allowed(&origins, "http://admin.internal"));
+}
+
+#[test]
+fn wildcards_are_only_valid_as_the_first_label() {
+    let mut config = Config::default();
+    config.cors.allowed_origins = allowed(&["https://*.example.com"]);
+    assert!(config.validate().is_ok());
+
+    for origin in ["https://app.*.example.com", "*", "https://*"] {
+        config.cors.allowed_origins = allowed(&[origin]);
+        assert!(config.validate().is_err(), "{}", origin);
+    }
+}
+
+#[test]
+fn environments_override_the_base_headers() {
+    let mut config = Config::parse(
+        r#"
+        [security_headers]
+        referrer_policy = "no-referrer"
+
+        [security_headers.environments.staging]
+        content_security_policy = ""
+        "#,
+    )
+    .expect("valid file");
+    config.validate().expect("valid headers");
+
+    config.server.environment = "production".to_string();
+    let production = config.security_headers();
+    assert_eq!(production.hsts_max_age_secs, 31_536_000);
+    assert_eq!(production.referrer_policy, "no-referrer");
+    assert!(!production.content_security_policy.is_empty());
+
+    config.server.environment = "staging".to_string();
+    assert!(config.security_headers().content_security_policy.is_empty());
+
+    // Environment tables in the file replace the built-in development table
+    config.server.environment = "development".to_string();
+    assert_eq!(config.security_headers().hsts_max_age_secs, 31_536_000);
+    let mut config = Config::default();
+    config.server.environment = "development".to_string();
+    assert_eq!(config.security_headers().hsts_max_age_secs, 0);
+
+    config.security_headers.referrer_policy = "sometimes".to_string();
+    assert!(config.validate().is_err());
+}
+
+// Send a request through the CORS and security header middleware. Rejections by CORS are
+// errors, which the server would render as responses.
+async fn call(config: &Config, request: TestRequest) -> (StatusCode, HeaderMap) {
+    let cors = config.cors.clone();
+    let headers = web::Data::new(SecurityHeaders::new(&config.security_headers()));
+    let app = test::init_service(
+        App::new()
+            .wrap(security::cors(&cors))
+            .wrap(from_fn(security::add_security_headers))
+            .app_data(headers)
+            .route(
+                "/api/ping",
+                web::get().to(|| async { HttpResponse::Ok().body("pong") }),
+            ),
+    )
+    .await;
+    match test::try_call_service(&app, request.to_request()).await {
+        Ok(res) => (res.status(), res.headers().clone()),
+        Err(e) => {
+            let res = e.error_response();
+            (res.status(), res.headers().clone())
+        }
+    }
+}
+
+#[actix_web::test]
+async fn responses_carry_the_security_headers() {
+    let config = Config::default();
+    let (status, headers) = call(&config, TestRequest::get().uri("/api/ping")).await;
+    assert_eq!(status, StatusCode::OK);
+    let headers = headers;
+    assert_eq!(
+        headers.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+        "default-src 'none'; frame-ancestors 'none'"
+    );
+    assert_eq!(
+        headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+        "nosniff"
+    );
+    assert_eq!(
+        headers.get(header::REFERRER_POLICY).unwrap(),
+        "strict-origin-when-cross-origin"
+    );
+    // Plain HTTP never gets HSTS
+    assert!(headers.get(header::STRICT_TRANSPORT_SECURITY).is_none());
+
+    let (status, headers) = call(
+        &config,
+        TestRequest::get()
+            .uri("/api/ping")
+            .insert_header(("X-Forwarded-Proto", "https")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+        "max-age=31536000; includeSubDomains"
+    );
+}
+
+#[actix_web::test]
+async fn cors_follows_the_configuration() {
+    let mut config = Config::default();
+    config.cors.allowed_origins = allowed(&["https://*.example.com"]);
+    config.cors.allowed_methods = allowed(&["GET"]);
+    config.cors.allow_credentials = false;
+
+    let (status, headers) = call(
+        &config,
+        TestRequest::get()
+            .uri("/api/ping")
+            .insert_header((header::ORIGIN, "https://app.example.com")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://app.example.com"
+    );
+    assert!(headers
+        .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+        .is_none());
+
+    // DELETE is not among the allowed methods
+    let (status, _) = call(
+        &config,
+        TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/ping")
+            .insert_header((header::ORIGIN, "https://app.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Rejected origins are errors, which still carry the security headers
+    let (status, headers) = call(
+        &config,
+        TestRequest::get()
+            .uri("/api/ping")
+            .insert_header((header::ORIGIN, "https://example.org")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(headers.get(header::X_CONTENT_TYPE_OPTIONS).is_some());
+}
+
+#[actix_web::test]
+async fn preflights_may_ask_for_the_caller_header() {
+    let mut config = Config::default();
+    config.cors.allowed_origins = allowed(&["https://app.example.com"]);
+
+    let (status, headers) = call(
+        &config,
+        TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/ping")
+            .insert_header((header::ORIGIN, "https://app.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .insert_header((
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "x-user-id, content-type",
+            )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let allowed_headers = headers
+        .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    assert!(allowed_headers.contains("x-user-id"), "{}", allowed_headers);
+}
@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use ddbp::{
+    models::SeedOptions,
+    repository::{CommentRepo, FollowRepo, ListOptions, MemoryStore, PostRepo, SeedRepo, UserRepo},
+    seeding::populate,
+};
+use mongodb::{
+    bson::{doc, Document},
+    error::Result,
+};
+use serde_json::json;
+use std::sync::Mutex;
+
+fn options(seed: u64) -> SeedOptions {
+    serde_json::from_value(json!({
+        "users": 20,
+        "posts": 40,
+        "comments": 30,
+        "likes": 50,
+        "follows": 25,
+        "seed": seed,
+        "anchor": "2026-01-01T00:00:00Z",
+    }))
+    .expect("seed options")
+}
+
+// Everything a run generated that the stores can list, in storage order
+async fn dataset(options: &SeedOptions) -> Vec<Vec<Document>> {
+    let store = MemoryStore::default();
+    populate(&store, options, None).await.expect("populate");
+    vec![
+        UserRepo::list(&store, doc! {}).await.expect("users"),
+        PostRepo::list(&store, doc! {}, ListOptions::default())
+            .await
+            .expect("posts"),
+        CommentRepo::list(&store, doc! {}).await.expect("comments"),
+        FollowRepo::list(&store, doc! {}).await.expect("follows"),
+    ]
+}
+
+#[tokio::test]
+async fn the_same_seed_and_anchor_give_the_same_dataset() {
+    // Seeds above i64::MAX must not lose their top bit anywhere along the way
+    let first = dataset(&options(u64::MAX)).await;
+    assert_eq!(first[0].len(), 20);
+    assert_eq!(dataset(&options(u64::MAX)).await, first);
+
+    assert_ne!(dataset(&options(42)).await, first);
+}
+
+#[tokio::test]
+async fn populate_reports_the_seed_it_used() {
+    let store = MemoryStore::default();
+    let summary = populate(&store, &options(u64::MAX), None)
+        .await
+        .expect("populate");
+    assert_eq!(summary.seed, u64::MAX);
+    assert_eq!(summary.anchor, "2026-01-01T00:00:00+00:00");
+}
+
+#[tokio::test]
+async fn one_seed_job_holds_the_lock_at_a_time() {
+    let store = MemoryStore::default();
+    let stale_before = "2000-01-01T00:00:00+00:00";
+
+    assert_eq!(store.lock("first", stale_before).await.unwrap(), None);
+    assert_eq!(
+        store.lock("second", stale_before).await.unwrap(),
+        Some("first".to_string())
+    );
+
+    // Only the holder can release the lock
+    store.unlock("second").await.unwrap();
+    assert_eq!(
+        store.lock("third", stale_before).await.unwrap(),
+        Some("first".to_string())
+    );
+    store.unlock("first").await.unwrap();
+    assert_eq!(store.lock("third", stale_before).await.unwrap(), None);
+
+    // A holder that stopped refreshing the lock before `stale_before` is taken over
+    assert_eq!(
+        store
+            .lock("fourth", "2999-01-01T00:00:00+00:00")
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        store.lock("fifth", stale_before).await.unwrap(),
+        Some("fourth".to_string())
+    );
+}
+
+// A store that remembers the collection and size of every insert_many batch
+#[derive(Default)]
+struct RecordingStore {
+    store: MemoryStore,
+    batches: Mutex<Vec<(String, usize)>>,
+}
+
+#[async_trait]
+impl SeedRepo for RecordingStore {
+    async fn find_job(&self, filter: Document) -> Result<Option<Document>> {
+        self.store.find_job(filter).await
+    }
+
+    async fn insert_job(&self, job: Document) -> Result<()> {
+        self.store.insert_job(job).await
+    }
+
+    async fn update_job(&self, job_id: &str, fields: Document) -> Result<()> {
+        self.store.update_job(job_id, fields).await
+    }
+
+    async fn lock(&self, job_id: &str, stale_before: &str) -> Result<Option<String>> {
+        self.store.lock(job_id, stale_before).await
+    }
+
+    async fn unlock(&self, job_id: &str) -> Result<()> {
+        self.store.unlock(job_id).await
+    }
+
+    async fn clear_data(&self) -> Result<Document> {
+        self.store.clear_data().await
+    }
+
+    async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<()> {
+        self.batches
+            .lock()
+            .unwrap()
+            .push((collection.to_string(), documents.len()));
+        self.store.insert_many(collection, documents).await
+    }
+}
+
+#[tokio::test]
+async fn populate_writes_in_batches_and_records_progress_on_the_job() {
+    let store = RecordingStore::default();
+    store
+        .insert_job(doc! { "_id": "job", "status": "running" })
+        .await
+        .unwrap();
+    let mut options = options(7);
+    options.batch_size = 16;
+
+    populate(&store, &options, Some("job"))
+        .await
+        .expect("populate");
+
+    let batches = store.batches.lock().unwrap().clone();
+    let sizes = |collection: &str| -> Vec<usize> {
+        batches
+            .iter()
+            .filter(|(name, _)| name == collection)
+            .map(|(_, size)| *size)
+            .collect()
+    };
+    assert_eq!(sizes("users"), [16, 4]);
+    assert_eq!(sizes("posts"), [16, 16, 8]);
+    assert_eq!(sizes("comments"), [16, 14]);
+    assert_eq!(sizes("likes"), [16, 16, 16, 2]);
+    assert_eq!(sizes("follows"), [16, 9]);
+
+    let job = store
+        .find_job(doc! { "_id": "job" })
+        .await
+        .unwrap()
+        .expect("seed job");
+    assert!(job.get_str("updated_at").is_ok());
+    assert_eq!(
+        job.get_document("progress").unwrap(),
+        &doc! {
+            "users": { "inserted": 20_i64, "total": 20_i64 },
+            "posts": { "inserted": 40_i64, "total": 40_i64 },
+            "comments": { "inserted": 30_i64, "total": 30_i64 },
+            "likes": { "inserted": 50_i64, "total": 50_i64 },
+            "follows": { "inserted": 25_i64, "total": 25_i64 },
+        }
+    );
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use actix_web::{
+    middleware::from_fn,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use common::invalid_field;
+use ddbp::{
+    config::{Config, TraceExporter},
+    logging,
+    telemetry::{self, JsonLinesExporter},
+};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use serde_json::Value;
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+// Spans written by the exporter, shared with the test
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn spans(&self) -> Vec<Value> {
+        let output = self.0.lock().unwrap();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+            .collect()
+    }
+}
+
+fn span<'a>(spans: &'a [Value], name: &str) -> &'a Value {
+    spans
+        .iter()
+        .find(|span| span["name"] == name)
+        .unwrap_or_else(|| panic!("no span named {} in {:?}", name, spans))
+}
+
+#[actix_web::test]
+async fn requests_continue_the_trace_of_the_caller() {
+    let output = Output::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(JsonLinesExporter::new(output.clone()))
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(telemetry::tracer(&provider)));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = test::init_service(App::new().wrap(from_fn(logging::assign_request_id)).route(
+        "/items/{id}",
+        web::get().to(|| async {
+            tracing::info_span!("lookup").in_scope(|| HttpResponse::Ok().finish())
+        }),
+    ))
+    .await;
+    let request = TestRequest::get()
+        .uri("/items/42")
+        .insert_header((
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ))
+        .to_request();
+    test::call_service(&app, request).await;
+    provider.force_flush().expect("spans are written");
+
+    let spans = output.spans();
+    let server = span(&spans, "GET /items/{id}");
+    assert_eq!(server["kind"], "server");
+    assert_eq!(server["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(server["parent_span_id"], "00f067aa0ba902b7");
+    assert_eq!(server["attributes"]["http.route"], "/items/{id}");
+    assert_eq!(server["attributes"]["http.response.status_code"], "200");
+
+    let lookup = span(&spans, "lookup");
+    assert_eq!(lookup["trace_id"], server["trace_id"]);
+    assert_eq!(lookup["parent_span_id"], server["span_id"]);
+}
+
+#[test]
+fn the_file_exporter_appends_json_lines() {
+    let path = std::env::temp_dir().join(format!("ddbp-traces-{}.jsonl", uuid::Uuid::new_v4()));
+    let mut config = Config::default();
+    config.tracing.exporter = TraceExporter::File;
+    config.tracing.file = path.clone();
+    let provider = telemetry::tracer_provider(&config.tracing, "test")
+        .expect("the file can be created")
+        .expect("tracing is on");
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(telemetry::tracer(&provider)));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("job", otel.kind = "internal").in_scope(|| {});
+    });
+    provider.shutdown().expect("the batch is flushed");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    let spans: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0]["name"], "job");
+    assert_eq!(spans[0]["parent_span_id"], Value::Null);
+    assert_eq!(spans[0]["trace_id"].as_str().map(str::len), Some(32));
+
+    assert!(
+        telemetry::tracer_provider(&Config::default().tracing, "test")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn tracing_settings_are_validated() {
+    let config = Config::parse(
+        r#"
+        [tracing]
+        exporter = "otlp"
+        service_name = "ddbp-staging"
+        otlp_endpoint = "http://otel-collector:4318/v1/traces"
+        sample_ratio = 0.25
+        "#,
+    )
+    .expect("valid file");
+    config.validate().expect("valid tracing settings");
+    assert_eq!(config.tracing.exporter, TraceExporter::Otlp);
+
+    let mut https = config.clone();
+    https.tracing.otlp_endpoint = "https://collector.example.com/v1/traces".to_string();
+    assert_eq!(invalid_field(&https), "tracing.otlp_endpoint");
+
+    let mut ratio = config.clone();
+    ratio.tracing.sample_ratio = 1.5;
+    assert_eq!(invalid_field(&ratio), "tracing.sample_ratio");
+
+    let mut name = config;
+    name.tracing.service_name = " ".to_string();
+    assert_eq!(invalid_field(&name), "tracing.service_name");
+
+    assert!(Config::parse("[tracing]\nexporter = \"jaeger\"").is_err());
+}
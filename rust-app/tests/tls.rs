@@ -0,0 +1,249 @@
+mod common;
+
+use actix_web::{
+    http::{header, StatusCode},
+    middleware::from_fn,
+    test::{self, TestRequest},
+    web, App, HttpResponse,
+};
+use common::invalid_field;
+use ddbp::{
+    config::Config,
+    tls::{self, HttpsRedirect, ReloadableCertResolver},
+};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use rustls::{
+    Certificate, ClientConfig, ClientConnection, RootCertStore, ServerConnection, ServerName,
+};
+use std::{path::PathBuf, sync::Arc};
+
+async fn call(port: u16, request: TestRequest) -> (StatusCode, Option<String>) {
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(tls::redirect_to_https))
+            .app_data(web::Data::new(HttpsRedirect { port }))
+            .route(
+                "/api/ping",
+                web::get().to(|| async { HttpResponse::Ok().finish() }),
+            ),
+    )
+    .await;
+    let res = test::call_service(&app, request.to_request()).await;
+    let location = res
+        .headers()
+        .get(header::LOCATION)
+        .map(|location| location.to_str().unwrap().to_string());
+    (res.status(), location)
+}
+
+#[actix_web::test]
+async fn plain_http_is_redirected_to_the_https_port() {
+    let (status, location) = call(
+        8443,
+        TestRequest::get()
+            .uri("/api/ping?page=2")
+            .insert_header((header::HOST, "api.example.com:8080")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        location.as_deref(),
+        Some("https://api.example.com:8443/api/ping?page=2")
+    );
+
+    let (_, location) = call(
+        443,
+        TestRequest::get()
+            .uri("/api/ping")
+            .insert_header((header::HOST, "[::1]:8080")),
+    )
+    .await;
+    assert_eq!(location.as_deref(), Some("https://[::1]/api/ping"));
+}
+
+#[actix_web::test]
+async fn https_requests_are_served() {
+    let (status, location) = call(
+        8443,
+        TestRequest::get()
+            .uri("/api/ping")
+            .insert_header(("X-Forwarded-Proto", "https")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(location.is_none());
+}
+
+#[test]
+fn tls_settings_are_validated() {
+    let mut config = Config::default();
+    config.tls.redirect_http = true;
+    assert_eq!(invalid_field(&config), "tls.redirect_http");
+
+    config.tls.enabled = true;
+    assert_eq!(invalid_field(&config), "tls.cert_file");
+
+    let existing = PathBuf::from(file!());
+    config.tls.cert_file = Some(existing.clone());
+    config.tls.key_file = Some(PathBuf::from("/nonexistent/server.key"));
+    assert_eq!(invalid_field(&config), "tls.key_file");
+
+    config.tls.key_file = Some(existing.clone());
+    config.tls.port = config.server.port;
+    assert_eq!(invalid_field(&config), "tls.port");
+
+    config.tls.port = 8443;
+    assert!(config.validate().is_ok());
+
+    config.mongo.tls_ca_file = Some(PathBuf::from("/nonexistent/ca.pem"));
+    assert_eq!(invalid_field(&config), "mongo.tls_ca_file");
+}
+
+#[actix_web::test]
+async fn a_custom_ca_turns_on_mongo_tls() {
+    let mut config = Config::default();
+    let options = config.mongo.client_options().await.unwrap();
+    assert!(options.tls.is_none());
+
+    config.mongo.tls_ca_file = Some(PathBuf::from("/etc/ddbp/mongo-ca.pem"));
+    let options = config.mongo.client_options().await.unwrap();
+    match options.tls {
+        Some(mongodb::options::Tls::Enabled(tls)) => {
+            assert_eq!(tls.ca_file_path, config.mongo.tls_ca_file)
+        }
+        other => panic!("expected TLS to be enabled, got {:?}", other),
+    }
+}
+
+// A CA and a way to issue server certificates for localhost signed by it
+struct TestCa {
+    cert: rcgen::Certificate,
+    key: KeyPair,
+}
+
+impl TestCa {
+    fn new() -> Self {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "DDBP test CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let cert = params.self_signed(&key).unwrap();
+        TestCa { cert, key }
+    }
+
+    // A new leaf certificate as (certificate PEM, key PEM, certificate DER)
+    fn issue(&self) -> (String, String, Vec<u8>) {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&key, &self.cert, &self.key)
+            .unwrap();
+        (cert.pem(), key.serialize_pem(), cert.der().to_vec())
+    }
+
+    // Run a TLS handshake against the resolver in memory and return the certificate the
+    // server presented
+    fn handshake(&self, resolver: &Arc<ReloadableCertResolver>) -> Vec<u8> {
+        let mut roots = RootCertStore::empty();
+        roots.add(&Certificate(self.cert.der().to_vec())).unwrap();
+        let client_config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let mut client = ClientConnection::new(Arc::new(client_config), server_name).unwrap();
+        let mut server =
+            ServerConnection::new(Arc::new(tls::server_config(resolver.clone()))).unwrap();
+
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut buffer = Vec::new();
+            client.write_tls(&mut buffer).unwrap();
+            server.read_tls(&mut buffer.as_slice()).unwrap();
+            server
+                .process_new_packets()
+                .expect("the client hello is valid");
+            let mut buffer = Vec::new();
+            server.write_tls(&mut buffer).unwrap();
+            client.read_tls(&mut buffer.as_slice()).unwrap();
+            client
+                .process_new_packets()
+                .expect("the client trusts the certificate");
+        }
+        client
+            .peer_certificates()
+            .expect("the server sent a certificate")[0]
+            .0
+            .clone()
+    }
+}
+
+#[test]
+fn reloading_swaps_the_certificate_and_keeps_it_on_errors() {
+    let dir = std::env::temp_dir().join(format!("ddbp-tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_file = dir.join("server.crt");
+    let key_file = dir.join("server.key");
+    let ca = TestCa::new();
+
+    let (cert, key, first) = ca.issue();
+    std::fs::write(&cert_file, cert).unwrap();
+    std::fs::write(&key_file, key).unwrap();
+    let resolver = ReloadableCertResolver::load(&cert_file, &key_file).unwrap();
+    assert_eq!(ca.handshake(&resolver), first);
+
+    // A renewed certificate is served once reloaded
+    let (cert, key, renewed) = ca.issue();
+    std::fs::write(&cert_file, cert).unwrap();
+    std::fs::write(&key_file, key).unwrap();
+    assert_eq!(ca.handshake(&resolver), first);
+    resolver.reload().expect("the renewed files are valid");
+    assert_eq!(ca.handshake(&resolver), renewed);
+
+    // Broken files leave the renewed certificate in place
+    std::fs::write(&cert_file, "not a certificate").unwrap();
+    assert!(resolver.reload().is_err());
+    assert_eq!(ca.handshake(&resolver), renewed);
+    std::fs::write(&cert_file, ca.issue().0).unwrap();
+    std::fs::write(&key_file, "").unwrap();
+    assert!(resolver.reload().is_err());
+    assert_eq!(ca.handshake(&resolver), renewed);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[actix_web::test]
+async fn sighup_reloads_the_certificate() {
+    let dir = std::env::temp_dir().join(format!("ddbp-tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_file = dir.join("server.crt");
+    let key_file = dir.join("server.key");
+    let ca = TestCa::new();
+    let (cert, key, _) = ca.issue();
+    std::fs::write(&cert_file, cert).unwrap();
+    std::fs::write(&key_file, key).unwrap();
+    let resolver = ReloadableCertResolver::load(&cert_file, &key_file).unwrap();
+    tls::spawn_reload_on_sighup(resolver.clone()).unwrap();
+
+    let (cert, key, renewed) = ca.issue();
+    std::fs::write(&cert_file, cert).unwrap();
+    std::fs::write(&key_file, key).unwrap();
+    let status = std::process::Command::new("kill")
+        .args(["-HUP", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The signal is handled by a background task
+    let mut served = Vec::new();
+    for _ in 0..50 {
+        served = ca.handshake(&resolver);
+        if served == renewed {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(served, renewed);
+    std::fs::remove_dir_all(&dir).ok();
+}
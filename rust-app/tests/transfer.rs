@@ -0,0 +1,163 @@
+use ddbp::{
+    repository::{ExportRepo, MemoryStore, SeedRepo},
+    transfer::{export_collection, import_collection, ConflictMode},
+};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+fn user(id: &str, username: &str) -> Document {
+    doc! {
+        "_id": id,
+        "username": username,
+        "email": format!("{}@example.com", username),
+        "password_hash": "hash",
+        "follower_count": 3,
+    }
+}
+
+// A directory of its own for each test
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ddbp-transfer-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).expect("scratch directory");
+    dir
+}
+
+async fn users(store: &MemoryStore) -> Vec<Document> {
+    store.collect("users", doc! {}).await.expect("users")
+}
+
+async fn import(
+    store: &MemoryStore,
+    path: &Path,
+    mode: ConflictMode,
+) -> std::io::Result<(u64, u64, u64)> {
+    let summary = import_collection(store, "users", path, 2, mode).await?;
+    Ok((summary.inserted, summary.replaced, summary.skipped))
+}
+
+#[tokio::test]
+async fn exported_collections_import_unchanged() {
+    let dir = scratch_dir();
+    let path = dir.join("users.jsonl");
+    let source = MemoryStore::default();
+    let mut original = vec![user("alice", "alice"), user("bob", "bob")];
+    // Extended JSON keeps types that plain JSON would lose
+    original.push(doc! {
+        "_id": ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap(),
+        "username": "carol",
+        "email": "carol@example.com",
+        "password_hash": "hash",
+    });
+    source
+        .insert_many("users", original.clone())
+        .await
+        .expect("insert");
+
+    assert_eq!(
+        export_collection(&source, "users", &path, 2).await.unwrap(),
+        3
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+    let target = MemoryStore::default();
+    assert_eq!(
+        import(&target, &path, ConflictMode::Fail).await.unwrap(),
+        (3, 0, 0)
+    );
+    assert_eq!(users(&target).await, original);
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn imports_settle_existing_documents_by_mode() {
+    let dir = scratch_dir();
+    let path = dir.join("users.jsonl");
+    let source = MemoryStore::default();
+    source
+        .insert_many(
+            "users",
+            vec![user("alice", "alice-renamed"), user("bob", "bob")],
+        )
+        .await
+        .expect("insert");
+    export_collection(&source, "users", &path, 100)
+        .await
+        .unwrap();
+
+    let target = MemoryStore::default();
+    target
+        .insert_many("users", vec![user("alice", "alice")])
+        .await
+        .expect("insert");
+
+    let error = import(&target, &path, ConflictMode::Fail)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("already exists"), "{}", error);
+
+    assert_eq!(
+        import(&target, &path, ConflictMode::Skip).await.unwrap(),
+        (1, 0, 1)
+    );
+    assert_eq!(users(&target).await[0].get_str("username"), Ok("alice"));
+
+    // Upserting replaces both documents and adds nothing
+    assert_eq!(
+        import(&target, &path, ConflictMode::Upsert).await.unwrap(),
+        (0, 2, 0)
+    );
+    assert_eq!(users(&target).await, users(&source).await);
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn invalid_documents_stop_the_import_before_their_batch_is_written() {
+    let dir = scratch_dir();
+    let path = dir.join("users.jsonl");
+    fs::write(
+        &path,
+        [
+            r#"{"_id": "alice", "username": "alice", "email": "alice@example.com", "password_hash": "hash"}"#,
+            r#"{"_id": "bob", "username": "bob", "email": "bob@example.com", "password_hash": "hash"}"#,
+            r#"{"_id": "carol", "username": "carol", "password_hash": "hash"}"#,
+            r#"{"_id": "dave", "username": "dave", "email": "dave@example.com", "password_hash": "hash"}"#,
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+
+    let store = MemoryStore::default();
+    let error = import(&store, &path, ConflictMode::Upsert)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let message = error.to_string();
+    assert!(
+        message.contains(":3: invalid users document") && message.contains("email"),
+        "{}",
+        message
+    );
+
+    // The first batch of two went in, the one holding the invalid document did not
+    let imported: Vec<_> = users(&store)
+        .await
+        .iter()
+        .map(|user| user.get_str("_id").unwrap().to_string())
+        .collect();
+    assert_eq!(imported, ["alice", "bob"]);
+
+    fs::write(&path, "{\"username\": \"no-id\"}\n").unwrap();
+    let error = import(&store, &path, ConflictMode::Upsert)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("missing _id"), "{}", error);
+
+    fs::remove_dir_all(dir).ok();
+}